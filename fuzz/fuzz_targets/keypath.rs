@@ -0,0 +1,12 @@
+#![no_main]
+
+use defaults_rs::fuzzing::KeyPath;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    // Whatever parses must print back into the same path.
+    if let Ok(path) = KeyPath::parse(input) {
+        let printed = path.to_string();
+        assert_eq!(KeyPath::parse(&printed).ok(), Some(path), "{printed}");
+    }
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use defaults_rs::fuzzing::{parse_float, parse_int};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let _ = parse_int(input);
+    let _ = parse_float(input);
+});
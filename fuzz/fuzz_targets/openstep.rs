@@ -0,0 +1,8 @@
+#![no_main]
+
+use defaults_rs::fuzzing::PrefValue;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let _ = PrefValue::from_openstep(input);
+});
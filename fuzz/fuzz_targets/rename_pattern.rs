@@ -0,0 +1,15 @@
+#![no_main]
+
+use defaults_rs::fuzzing::parse_rename_pattern;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    if let Ok((regex, replacement, global)) = parse_rename_pattern(input) {
+        let key = "com.example.SomeKey";
+        if global {
+            let _ = regex.replace_all(key, replacement.as_str());
+        } else {
+            let _ = regex.replace(key, replacement.as_str());
+        }
+    }
+});
@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: MIT
+
+//! `drs adopt`: adding values configured by hand to a manifest.
+//!
+//! The current values of the given keys are written into the manifest's block for their domain,
+//! typed as they are stored, so `drs apply` reproduces them exactly. Manifests are edited in
+//! place, keeping their format (plist or typed JSON), key order and any `$when` blocks; values are
+//! added to the domain's last unguarded block, or a new block if all of them are guarded.
+
+use anyhow::{Context, Result, bail};
+use std::{fs, path::Path};
+
+use crate::{Domain, PrefValue, Preferences, core::file, preferences::convert::prefvalue_to_plist};
+
+/// A manifest as it is stored, so it can be written back in the same form.
+enum Document {
+    Json(serde_json::Map<String, serde_json::Value>),
+    Plist(plist::Dictionary),
+}
+
+impl Document {
+    /// Read the manifest at `path`; a missing file starts a new one, typed JSON if it is named
+    /// `*.json` and an XML plist otherwise.
+    fn load(path: &Path) -> Result<Document> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(if path.extension().is_some_and(|e| e == "json") {
+                    Document::Json(serde_json::Map::new())
+                } else {
+                    Document::Plist(plist::Dictionary::new())
+                });
+            }
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+
+        if data.trim_ascii_start().starts_with(b"{") {
+            match serde_json::from_slice(&data)? {
+                serde_json::Value::Object(object) => Ok(Document::Json(object)),
+                _ => bail!("The manifest must be a dictionary of domains."),
+            }
+        } else {
+            match plist::Value::from_reader(std::io::Cursor::new(&data))? {
+                plist::Value::Dictionary(dict) => Ok(Document::Plist(dict)),
+                _ => bail!("The manifest must be a dictionary of domains."),
+            }
+        }
+    }
+
+    /// Set `key` to `value` in the block `domain` applies unconditionally.
+    fn adopt(&mut self, domain: &str, key: &str, value: &PrefValue) -> Result<()> {
+        let not_keys = || {
+            format!("{domain} in the manifest must be a dictionary of keys, or an array of them.")
+        };
+        match self {
+            Document::Json(root) => {
+                use serde_json::Value;
+                let entry = root
+                    .entry(domain)
+                    .or_insert_with(|| Value::Object(Default::default()));
+                let block = match entry {
+                    Value::Object(block) => block,
+                    Value::Array(blocks) => {
+                        let unguarded = blocks
+                            .iter()
+                            .rposition(|b| b.as_object().is_some_and(|b| !b.contains_key("$when")));
+                        let index = match unguarded {
+                            Some(index) => index,
+                            None => {
+                                blocks.push(Value::Object(Default::default()));
+                                blocks.len() - 1
+                            }
+                        };
+                        blocks[index].as_object_mut().with_context(not_keys)?
+                    }
+                    _ => bail!(not_keys()),
+                };
+                block.insert(key.to_string(), value.to_typed_json());
+            }
+            Document::Plist(root) => {
+                use plist::Value;
+                if !root.contains_key(domain) {
+                    root.insert(domain.to_string(), Value::Dictionary(Default::default()));
+                }
+                let block = match root.get_mut(domain) {
+                    Some(Value::Dictionary(block)) => block,
+                    Some(Value::Array(blocks)) => {
+                        let unguarded = blocks.iter().rposition(|b| {
+                            b.as_dictionary().is_some_and(|b| !b.contains_key("$when"))
+                        });
+                        let index = match unguarded {
+                            Some(index) => index,
+                            None => {
+                                blocks.push(Value::Dictionary(Default::default()));
+                                blocks.len() - 1
+                            }
+                        };
+                        blocks[index].as_dictionary_mut().with_context(not_keys)?
+                    }
+                    _ => bail!(not_keys()),
+                };
+                block.insert(key.to_string(), prefvalue_to_plist(value));
+            }
+        }
+        Ok(())
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let mut buf = Vec::new();
+        match self {
+            Document::Json(root) => {
+                serde_json::to_writer_pretty(&mut buf, root)?;
+                buf.push(b'\n');
+            }
+            Document::Plist(root) => {
+                plist::Value::Dictionary(root.clone()).to_writer_xml(&mut buf)?
+            }
+        }
+        file::write_atomic(path, &buf)
+    }
+}
+
+/// Add the current values of `keys` in `domain` to the manifest at `path`.
+///
+/// Keys already in the manifest are updated to their current value.
+pub(crate) fn run(domain: &Domain, keys: &[&str], path: &Path) -> Result<()> {
+    let name = match domain {
+        Domain::User(name) => name.clone(),
+        Domain::Global => "NSGlobalDomain".to_string(),
+        other => bail!("Manifests only manage user domains and NSGlobalDomain, not {other}."),
+    };
+
+    let mut document = Document::load(path)?;
+    let mut adopted = Vec::with_capacity(keys.len());
+    for key in keys {
+        let value = Preferences::read(domain.clone(), key)
+            .with_context(|| format!("{key} isn't set in {domain}"))?;
+        document.adopt(&name, key, &value)?;
+        adopted.push((key, value));
+    }
+    document.save(path)?;
+
+    for (key, value) in adopted {
+        println!("Adopted {domain} {key} = {value} into {}", path.display());
+    }
+    Ok(())
+}
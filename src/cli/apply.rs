@@ -0,0 +1,209 @@
+// SPDX-License-Identifier: MIT
+
+//! `drs apply`: applying a manifest only after a full preview.
+//!
+//! A safer take on piping a defaults script from the web into a shell. Manifests (see
+//! [`super::manifest`]) are read from a file or fetched over HTTPS (redirects included), and every
+//! change they would make is shown along with where each manifest came from and its SHA-256.
+//! Nothing is written until the user types `yes`.
+//!
+//! Once a manifest applied cleanly, its values are recorded (see [`super::state`]), so keys which
+//! are known to be unchanged since are skipped without reading their domain next time.
+
+use anyhow::{Result, bail};
+use std::io::{BufRead, IsTerminal, Write};
+
+use crate::{
+    ApplyOutcome, KeyPath, NormalizeRule, OnError, Preferences, Transaction,
+    preferences::normalize::normalize,
+};
+
+use super::{
+    apps,
+    manifest::{self, Entry, Source},
+    state::State,
+};
+
+/// Ask on the terminal whether to go ahead; only a typed `yes` counts.
+fn confirm(prompt: &str) -> Result<bool> {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        bail!("drs apply needs to be confirmed on a terminal; use --dry-run to only preview.");
+    }
+
+    eprint!("{prompt} Type 'yes' to continue: ");
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    stdin.lock().read_line(&mut answer)?;
+    Ok(answer.trim() == "yes")
+}
+
+/// Order `entries` so each domain comes after the ones it names in `$after`, otherwise keeping
+/// manifest order. Domains which aren't in the manifest, or were skipped, don't hold anything up.
+fn plan(mut pending: Vec<Entry>) -> Result<Vec<Entry>> {
+    let mut planned = Vec::with_capacity(pending.len());
+    while !pending.is_empty() {
+        let ready = pending.iter().position(|entry| {
+            entry
+                .after
+                .iter()
+                .all(|d| *d == entry.domain || !pending.iter().any(|p| p.domain == *d))
+        });
+        match ready {
+            Some(index) => planned.push(pending.remove(index)),
+            None => {
+                let domains: Vec<String> = pending.iter().map(|e| e.domain.to_string()).collect();
+                bail!(
+                    "The $after dependencies in the manifest form a cycle between {}.",
+                    domains.join(", ")
+                )
+            }
+        }
+    }
+    Ok(planned)
+}
+
+/// Preview the manifest at `source` and apply it once confirmed, or only preview with `dry_run`.
+///
+/// Values are normalized with `rules` first, and each normalization is listed in the preview.
+/// Domains are applied in the order [`plan`] gives, and the processes their entries name are
+/// restarted once at the end, unless `restart` is false or the changes were rolled back.
+/// Failures are handled according to `on_error`, and fail the run once the report is printed.
+pub(crate) fn run(
+    source: Source,
+    rules: &[NormalizeRule],
+    dry_run: bool,
+    restart: bool,
+    on_error: OnError,
+) -> Result<()> {
+    let loaded = manifest::load(source.clone())?;
+    let mut manifest = loaded.entries;
+
+    for (source, digest, size) in &loaded.sources {
+        println!("Source:  {source}");
+        println!("SHA-256: {digest}");
+        println!("Size:    {size} bytes");
+        println!();
+    }
+
+    for (domain, when) in &loaded.skipped {
+        println!("Skipped {domain}, as this Mac doesn't match {when}");
+    }
+    if !loaded.skipped.is_empty() {
+        println!();
+    }
+
+    let mut normalized = Vec::new();
+    for entry in &mut manifest {
+        for (key, value) in &mut entry.values {
+            let path = KeyPath::default().key(key);
+            *value = normalize(
+                &entry.domain,
+                &path,
+                std::mem::take(value),
+                rules,
+                &mut normalized,
+            );
+        }
+    }
+    for n in &normalized {
+        println!(
+            "Normalized {} {} ({}): {} -> {}",
+            n.domain, n.key, n.rule, n.before, n.after
+        );
+    }
+    if !normalized.is_empty() {
+        println!();
+    }
+
+    let recorded = State::load()?;
+    let planned = plan(manifest)?;
+    let mut tx = Transaction::new();
+    let mut restarts: Vec<String> = Vec::new();
+    for Entry {
+        domain,
+        values,
+        restart,
+        ..
+    } in &planned
+    {
+        let mut changed = false;
+        for (key, new) in values {
+            if recorded.holds(domain, key, new) {
+                println!("  {domain} {key}: {new} (unchanged since last apply)");
+                continue;
+            }
+            match Preferences::read(domain.clone(), key).ok() {
+                Some(old) if old == *new => println!("  {domain} {key}: {new} (unchanged)"),
+                Some(old) => {
+                    println!("~ {domain} {key}: {old} -> {new}");
+                    tx.write(domain.clone(), key, new.clone());
+                    changed = true;
+                }
+                None => {
+                    println!("+ {domain} {key}: {new}");
+                    tx.write(domain.clone(), key, new.clone());
+                    changed = true;
+                }
+            }
+        }
+        if changed {
+            for process in restart {
+                if !restarts.contains(process) {
+                    restarts.push(process.clone());
+                }
+            }
+        }
+    }
+    println!();
+
+    // Recorded once the values are known to be in place.
+    let sources = loaded
+        .sources
+        .iter()
+        .map(|(source, digest, _)| (source.to_string(), digest.clone()))
+        .collect();
+    let mut state = State::new(sources);
+
+    if tx.is_empty() {
+        println!("Nothing to apply.");
+        if !dry_run {
+            for entry in &planned {
+                state.record(&entry.domain, &entry.values);
+            }
+            state.save()?;
+        }
+        return Ok(());
+    }
+    if !restarts.is_empty() {
+        println!("Then restart: {}", restarts.join(", "));
+        println!();
+    }
+    if dry_run {
+        return Ok(());
+    }
+    if !confirm(&format!("Apply these changes from {source}?"))? {
+        bail!("Aborted; nothing was changed.");
+    }
+    let report = tx.commit_with(on_error)?;
+    println!("{}", report.table());
+    match report.outcome() {
+        ApplyOutcome::RolledBack => return report.into_result().map(|_| ()),
+        ApplyOutcome::Clean => {
+            for entry in &planned {
+                state.record(&entry.domain, &entry.values);
+            }
+            state.save()?;
+        }
+        ApplyOutcome::Partial => {}
+    }
+
+    for process in &restarts {
+        if !restart {
+            println!("Restart {process} (killall {process}) for the changes to take effect.");
+        } else if apps::run("killall", &[process]).is_err() {
+            println!("{process} isn't running; nothing to restart.");
+        }
+    }
+    report.into_result().map(|_| ())
+}
@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: MIT
+
+//! Helpers for looking up applications and their bundle identifiers.
+
+use anyhow::{Context, Result, bail};
+use plist::Value;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Folders searched for installed applications.
+const APP_DIRS: &[&str] = &[
+    "/Applications",
+    "/System/Applications",
+    "/System/Library/CoreServices",
+];
+
+/// An installed application bundle.
+pub(crate) struct AppBundle {
+    pub(crate) path: PathBuf,
+    pub(crate) name: Option<String>,
+    pub(crate) version: Option<String>,
+}
+
+/// Runs a command and returns its trimmed standard output.
+pub(crate) fn run(cmd: &str, args: &[&str]) -> Result<String> {
+    let out = Command::new(cmd)
+        .args(args)
+        .output()
+        .with_context(|| format!("failed to run {cmd}"))?;
+
+    if !out.status.success() {
+        bail!(
+            "{cmd} failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+/// Returns the bundle identifier of the frontmost application.
+pub(crate) fn frontmost_bundle_id() -> Result<String> {
+    let asn = run("lsappinfo", &["front"])?;
+    if asn.is_empty() {
+        bail!("Could not determine the frontmost application.");
+    }
+
+    // Output looks like: "CFBundleIdentifier"="com.apple.Terminal"
+    let info = run("lsappinfo", &["info", "-only", "bundleid", &asn])?;
+    info.split_once('=')
+        .map(|(_, id)| id.trim().trim_matches('"').to_string())
+        .filter(|id| !id.is_empty() && id != "[ NULL ]")
+        .context("The frontmost application has no bundle identifier.")
+}
+
+/// Returns the bundle identifier of an application given by name (`Google Chrome`) or path.
+///
+/// Apps in the usual folders are matched by display or file name, ignoring case; anything else
+/// is looked up through Spotlight, which knows every app LaunchServices has registered.
+pub(crate) fn bundle_id_for(app: &str) -> Result<String> {
+    let not_found = || format!("No application named {app} was found.");
+    let stem = app.strip_suffix(".app").unwrap_or(app);
+
+    if Path::new(app).is_dir() {
+        return bundle_identifier(Path::new(app)).with_context(not_found);
+    }
+
+    let installed = installed_apps().into_values().find(|bundle| {
+        bundle
+            .name
+            .as_deref()
+            .is_some_and(|n| n.eq_ignore_ascii_case(stem))
+            || bundle
+                .path
+                .file_stem()
+                .is_some_and(|s| s.to_string_lossy().eq_ignore_ascii_case(stem))
+    });
+    if let Some(id) = installed.and_then(|bundle| bundle_identifier(&bundle.path)) {
+        return Ok(id);
+    }
+
+    let query = format!(
+        "kMDItemContentType == 'com.apple.application-bundle' && kMDItemFSName == '{}.app'c",
+        stem.replace('\\', r"\\").replace('\'', r"\'")
+    );
+    run("mdfind", &[&query])?
+        .lines()
+        .find_map(|path| bundle_identifier(Path::new(path)))
+        .with_context(not_found)
+}
+
+/// The bundle identifier of the app at `path`, as written in its `Info.plist`.
+fn bundle_identifier(path: &Path) -> Option<String> {
+    let info = Value::from_file(path.join("Contents/Info.plist")).ok()?;
+    info.as_dictionary()?
+        .get("CFBundleIdentifier")?
+        .as_string()
+        .map(str::to_string)
+}
+
+/// Reads the bundle identifier, name and version of an app from its `Info.plist`.
+fn read_bundle(path: &Path) -> Option<(String, AppBundle)> {
+    let info = Value::from_file(path.join("Contents/Info.plist")).ok()?;
+    let info = info.as_dictionary()?;
+    let field = |key: &str| info.get(key).and_then(Value::as_string).map(str::to_string);
+
+    Some((
+        field("CFBundleIdentifier")?.to_lowercase(),
+        AppBundle {
+            path: path.to_path_buf(),
+            name: field("CFBundleDisplayName").or_else(|| field("CFBundleName")),
+            version: field("CFBundleShortVersionString").or_else(|| field("CFBundleVersion")),
+        },
+    ))
+}
+
+/// Installed applications by lowercased bundle identifier.
+///
+/// Apps directly inside the usual application folders (and `~/Applications`) and one folder
+/// below them, like `/Applications/Utilities`, are found.
+pub(crate) fn installed_apps() -> HashMap<String, AppBundle> {
+    let home_apps = dirs::home_dir().map(|home| home.join("Applications"));
+    let roots = APP_DIRS.iter().map(PathBuf::from).chain(home_apps);
+
+    let mut apps = HashMap::new();
+    let mut dirs: Vec<PathBuf> = roots.collect();
+    let mut depth = 0;
+    while !dirs.is_empty() && depth < 2 {
+        let mut next = Vec::new();
+        for dir in dirs {
+            for path in fs::read_dir(&dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|e| e.path())
+            {
+                if path.extension().is_some_and(|e| e == "app") {
+                    if let Some((id, app)) = read_bundle(&path) {
+                        apps.entry(id).or_insert(app);
+                    }
+                } else if path.is_dir() {
+                    next.push(path);
+                }
+            }
+        }
+        dirs = next;
+        depth += 1;
+    }
+    apps
+}
+
+/// The Team ID an app is signed with, if it is signed by a developer team.
+pub(crate) fn team_id(app: &Path) -> Option<String> {
+    // `codesign -dv` prints its details, including a "TeamIdentifier=..." line, to stderr.
+    let out = Command::new("codesign")
+        .args(["-dv", "--verbose=2"])
+        .arg(app)
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&out.stderr)
+        .lines()
+        .find_map(|line| line.strip_prefix("TeamIdentifier="))
+        .map(str::to_string)
+        .filter(|id| id != "not set")
+}
@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: MIT
+
+//! Elements for `drs array insert`.
+
+use anyhow::{Result, bail};
+use clap::ArgMatches;
+
+use crate::PrefValue;
+
+use super::{parse_bool, units};
+
+/// The element passed to `array insert` through one of its type flags.
+pub(crate) fn value(m: &ArgMatches) -> Result<PrefValue> {
+    if let Some(json) = m.get_one::<String>("dict-json") {
+        match from_json(serde_json::from_str(json)?)? {
+            dict @ PrefValue::Dictionary(_) => Ok(dict),
+            _ => bail!("--dict-json takes a JSON object."),
+        }
+    } else if let Some(val) = m.get_one::<String>("int") {
+        Ok(PrefValue::Integer(units::parse_int(val)?))
+    } else if let Some(val) = m.get_one::<String>("float") {
+        Ok(PrefValue::Float(units::parse_float(val)?))
+    } else if let Some(val) = m.get_one::<String>("bool") {
+        Ok(PrefValue::Boolean(parse_bool(
+            val,
+            m.get_flag("strict-bool"),
+        )?))
+    } else if let Some(val) = m.get_one::<String>("string") {
+        Ok(PrefValue::String(val.clone()))
+    } else {
+        bail!("You must specify one of --dict-json, --int, --float, --bool or --string.")
+    }
+}
+
+/// Convert JSON into a value; numbers without a fraction become integers.
+fn from_json(json: serde_json::Value) -> Result<PrefValue> {
+    Ok(match json {
+        serde_json::Value::Null => bail!("null has no property list counterpart."),
+        serde_json::Value::Bool(b) => PrefValue::Boolean(b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => PrefValue::Integer(i),
+            None => PrefValue::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        serde_json::Value::String(s) => PrefValue::String(s),
+        serde_json::Value::Array(arr) => {
+            PrefValue::Array(arr.into_iter().map(from_json).collect::<Result<_>>()?)
+        }
+        serde_json::Value::Object(obj) => PrefValue::Dictionary(
+            obj.into_iter()
+                .map(|(k, v)| Ok((k, from_json(v)?)))
+                .collect::<Result<_>>()?,
+        ),
+    })
+}
@@ -0,0 +1,277 @@
+// SPDX-License-Identifier: MIT
+
+//! Bulk operations over many domains at once.
+//!
+//! `export-all` exports every domain into a directory using a bounded pool of worker threads.
+//! Each finished domain is appended to a progress manifest alongside the modification time of its
+//! plist, so an interrupted run can be resumed without exporting unchanged domains again.
+//!
+//! ByHost preferences are named after the hardware UUID of the Mac they belong to. `export-all`
+//! copies this Mac's ByHost plists into a `ByHost` folder with the UUID stripped from their names
+//! (recording it in a manifest), and `import-all` restores them for the current host, which files
+//! them under the destination Mac's UUID. When the export comes from another Mac, every imported
+//! value is translated through a [`UuidMap`] from that Mac's UUID to this one's, plus any display
+//! UUIDs the user mapped; keys naming other UUIDs are reported rather than imported.
+
+use anyhow::{Result, bail};
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::UNIX_EPOCH,
+};
+
+use super::{host, progress::Progress};
+use crate::{ApplyReport, Domain, Preferences, UuidMap, core::file};
+
+/// Name of the progress manifest written into the export directory.
+pub(crate) const PROGRESS_FILE: &str = ".drs-export-progress";
+
+/// Folder inside an export holding ByHost preferences.
+pub(crate) const BYHOST_DIR: &str = "ByHost";
+
+/// Manifest recording which hardware UUID each ByHost plist was exported from.
+pub(crate) const BYHOST_MANIFEST: &str = ".drs-byhost";
+
+/// Modification time of a domain's plist in nanoseconds since the UNIX epoch, or 0 if unknown.
+fn domain_mtime(domain: &Domain) -> u128 {
+    domain
+        .plist_path()
+        .and_then(|path| fs::metadata(path).ok())
+        .and_then(|meta| meta.modified().ok())
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos())
+}
+
+/// Reads the `<domain>\t<value>` lines of a manifest, if it exists.
+fn read_manifest(path: &Path) -> Vec<(String, String)> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let (domain, value) = line.rsplit_once('\t')?;
+            Some((domain.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Reads the progress manifest of a previous run.
+fn read_progress(dir: &Path) -> HashMap<String, u128> {
+    read_manifest(&dir.join(PROGRESS_FILE))
+        .into_iter()
+        .filter_map(|(domain, mtime)| Some((domain, mtime.parse().ok()?)))
+        .collect()
+}
+
+/// Export every domain into `dir` using up to `jobs` worker threads.
+///
+/// With `resume`, domains recorded in the progress manifest with an unchanged modification time
+/// (and whose export still exists) are skipped.
+pub(crate) fn export_all(dir: &Path, jobs: usize, resume: bool) -> Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let done = if resume {
+        read_progress(dir)
+    } else {
+        HashMap::new()
+    };
+
+    let domains: Vec<Domain> = Preferences::list_domains()?
+        .into_iter()
+        .chain([Domain::Global])
+        .collect();
+    let total = domains.len();
+
+    let mut queue: Vec<(Domain, u128)> = domains
+        .into_iter()
+        .map(|d| {
+            let mtime = domain_mtime(&d);
+            (d, mtime)
+        })
+        .filter(|(d, mtime)| {
+            let name = d.get_cf_name();
+            done.get(&name) != Some(mtime) || !dir.join(format!("{name}.plist")).is_file()
+        })
+        .collect();
+    queue.sort_by_key(|(d, _)| d.get_cf_name());
+
+    let skipped = total - queue.len();
+    let events = Progress::start("export", queue.len());
+    let queue = Mutex::new(queue);
+    let progress = Mutex::new(
+        OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resume)
+            .truncate(!resume)
+            .open(dir.join(PROGRESS_FILE))?,
+    );
+    let failures = Mutex::new(Vec::new());
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.max(1) {
+            scope.spawn(|| {
+                loop {
+                    let next = queue.lock().unwrap_or_else(|e| e.into_inner()).pop();
+                    let Some((domain, mtime)) = next else {
+                        break;
+                    };
+
+                    let name = domain.get_cf_name();
+                    let path = dir.join(format!("{name}.plist"));
+                    let result = Preferences::export(domain, &path.to_string_lossy());
+                    events.step(&name, result.is_ok());
+                    match result {
+                        Ok(()) => {
+                            println!("Exported {name}");
+                            let mut progress = progress.lock().unwrap_or_else(|e| e.into_inner());
+                            let _ = writeln!(progress, "{name}\t{mtime}");
+                        }
+                        Err(e) => failures
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .push(format!("{name}: {e}")),
+                    }
+                }
+            });
+        }
+    });
+
+    if skipped > 0 {
+        println!("Skipped {skipped} unchanged domain(s) from the previous run.");
+    }
+
+    let by_host = export_by_host(dir)?;
+    if by_host > 0 {
+        println!("Exported {by_host} ByHost domain(s).");
+    }
+
+    let failures = failures.into_inner().unwrap_or_else(|e| e.into_inner());
+    if !failures.is_empty() {
+        bail!(
+            "Failed to export {} domain(s):\n{}",
+            failures.len(),
+            failures.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Copy this Mac's ByHost plists into `<dir>/ByHost`, stripping the hardware UUID from their names.
+///
+/// Returns the number of plists exported.
+fn export_by_host(dir: &Path) -> Result<usize> {
+    let Some(src) = dirs::home_dir().map(|home| home.join("Library/Preferences/ByHost")) else {
+        return Ok(0);
+    };
+    let entries = match fs::read_dir(&src) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+
+    let uuid = host::hardware_uuid()?;
+    let suffix = format!(".{uuid}.plist");
+    let out = dir.join(BYHOST_DIR);
+    let mut manifest = String::new();
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(domain) = name.strip_suffix(&suffix) else {
+            continue;
+        };
+
+        fs::create_dir_all(&out)?;
+        file::write_atomic(
+            &out.join(format!("{domain}.plist")),
+            &fs::read(entry.path())?,
+        )?;
+        manifest.push_str(&format!("{domain}\t{uuid}\n"));
+    }
+
+    if !manifest.is_empty() {
+        file::write_atomic(&out.join(BYHOST_MANIFEST), manifest.as_bytes())?;
+    }
+    Ok(manifest.lines().count())
+}
+
+/// Returns the domain an exported plist belongs to, from its file stem.
+fn domain_from_stem(stem: &str) -> Domain {
+    match stem {
+        ".GlobalPreferences" => Domain::Global,
+        other => Domain::User(other.to_string()),
+    }
+}
+
+/// Returns the `.plist` files directly inside `dir`, sorted by name.
+fn plist_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "plist"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Import `path` into `domain`, translated through `uuids` unless there is nothing to map.
+fn import_one(domain: Domain, path: &Path, uuids: &UuidMap) -> Result<ApplyReport> {
+    if uuids.is_empty() {
+        Preferences::import(domain, &path.to_string_lossy())
+    } else {
+        Preferences::import_translated(domain, &path.to_string_lossy(), uuids)
+    }
+}
+
+/// Import every domain previously written by `export-all` from `dir`, translating the UUIDs in
+/// `uuids` as well as the exporting Mac's.
+pub(crate) fn import_all(dir: &Path, mut uuids: UuidMap) -> Result<ApplyReport> {
+    let by_host = dir.join(BYHOST_DIR);
+    let sources: Vec<String> = read_manifest(&by_host.join(BYHOST_MANIFEST))
+        .into_iter()
+        .map(|(_, uuid)| uuid)
+        .collect();
+    let local = host::hardware_uuid().ok();
+    if let (Some(source), Some(local)) = (sources.first(), &local)
+        && source != local
+    {
+        uuids = uuids.map(source, local);
+    }
+
+    let mut report = ApplyReport::default();
+    let files = plist_files(dir)?;
+    let events = Progress::start("import", files.len());
+    for path in files {
+        let Some(stem) = path.file_stem().map(|s| s.to_string_lossy().into_owned()) else {
+            continue;
+        };
+        let imported = import_one(domain_from_stem(&stem), &path, &uuids);
+        events.step(&stem, imported.as_ref().is_ok_and(ApplyReport::is_ok));
+        report.merge(imported?);
+    }
+    events.finish();
+
+    if !by_host.is_dir() {
+        return Ok(report);
+    }
+
+    let files = plist_files(&by_host)?;
+    let events = Progress::start("import-byhost", files.len());
+    for path in files {
+        let Some(stem) = path.file_stem().map(|s| s.to_string_lossy().into_owned()) else {
+            continue;
+        };
+        let imported = import_one(domain_from_stem(&stem).current_host(), &path, &uuids);
+        events.step(&stem, imported.as_ref().is_ok_and(ApplyReport::is_ok));
+        report.merge(imported?);
+    }
+    events.finish();
+
+    if let (Some(source), Some(local)) = (sources.first(), local)
+        && *source != local
+    {
+        println!("ByHost preferences from {source} were restored for this Mac ({local}).");
+    }
+    Ok(report)
+}
@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: MIT
+
+//! `drs changed`: domains and keys modified within a recent window.
+//!
+//! drs keeps no change journal, so the modification times of the domains' plist files decide
+//! which domains changed. The keys of a changed domain are found by diffing it against its newest
+//! drs backup taken before the window started. Without such a backup only the domain is listed.
+
+use anyhow::Result;
+use plist::Value;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    Domain, PrefValue, Preferences, preferences::backup, preferences::convert::plist_to_prefvalue,
+    preferences::watch::changed_keys,
+};
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// The newest backup of `domain` in `backup_dir` written before `cutoff`.
+fn backup_before(backup_dir: &Path, domain: &Domain, cutoff: SystemTime) -> Option<PathBuf> {
+    fs::read_dir(backup::domain_dir(backup_dir, domain))
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter_map(|p| modified(&p).filter(|m| *m < cutoff).map(|m| (m, p)))
+        .max_by_key(|(m, _)| *m)
+        .map(|(_, p)| p)
+}
+
+fn load_backup(path: &Path) -> Result<HashMap<String, PrefValue>> {
+    match plist_to_prefvalue(&Value::from_file(path)?)? {
+        PrefValue::Dictionary(dict) => Ok(dict),
+        _ => Ok(HashMap::new()),
+    }
+}
+
+/// Print the domains (and, where a backup allows, keys) modified within the last `window`.
+pub(crate) fn run(domains: Vec<Domain>, window: Duration, backup_dir: &Path) -> Result<()> {
+    let cutoff = SystemTime::now() - window;
+    let mut found = false;
+
+    for domain in domains {
+        let Some(mtime) = domain.plist_path().and_then(|p| modified(&p)) else {
+            continue;
+        };
+        if mtime < cutoff {
+            continue;
+        }
+        found = true;
+
+        let stamp = chrono::DateTime::<chrono::Local>::from(mtime).format("%Y-%m-%d %H:%M:%S");
+        let Some(backup) = backup_before(backup_dir, &domain, cutoff) else {
+            println!("{domain} (modified {stamp}; no earlier backup to list keys)");
+            continue;
+        };
+
+        let current = match Preferences::read_domain(domain.clone())? {
+            PrefValue::Dictionary(dict) => dict,
+            _ => HashMap::new(),
+        };
+        let keys = changed_keys(&load_backup(&backup)?, &current);
+        println!("{domain} (modified {stamp})");
+        for key in keys {
+            println!("  {key}");
+        }
+    }
+
+    if !found {
+        println!("Nothing changed.");
+    }
+    Ok(())
+}
@@ -0,0 +1,212 @@
+// SPDX-License-Identifier: MIT
+
+//! Cleanup chores: removing window state and recent-item lists apps accumulate.
+
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{Domain, PrefValue, Preferences, RefusedReadOnly, Transaction};
+
+/// Key prefixes AppKit uses for remembered window frames, panels and split views.
+const WINDOW_STATE_PREFIXES: &[&str] = &[
+    "NSWindow Frame",
+    "NSNavPanel",
+    "NSNavLastRootDirectory",
+    "NSNavLastCurrentDirectory",
+    "NSNavBrowserPreferedColumnContentWidth",
+    "NSSplitView Subview Frames",
+];
+
+/// Keys apps use for their recent documents and places.
+const RECENTS_PREFIXES: &[&str] = &[
+    "NSRecentDocuments",
+    "NSRecentDocumentRecords",
+    "NSNavRecentPlaces",
+    "RecentDocuments",
+];
+
+/// The system-wide recent items domain of older macOS releases.
+const RECENT_ITEMS_DOMAIN: &str = "com.apple.recentitems";
+
+/// Where newer macOS releases keep shared file lists (`*.sfl2`/`*.sfl3`).
+const SHARED_FILE_LIST_DIR: &str = "Library/Application Support/com.apple.sharedfilelist";
+
+/// Keys of `domain` matching any of `prefixes`, sorted.
+pub(crate) fn matching_keys(domain: &Domain, prefixes: &[&str]) -> Result<Vec<String>> {
+    let PrefValue::Dictionary(dict) = Preferences::read_domain(domain.clone())? else {
+        return Ok(Vec::new());
+    };
+
+    let mut keys: Vec<String> = dict
+        .into_keys()
+        .filter(|k| prefixes.iter().any(|p| k.starts_with(p)))
+        .collect();
+    keys.sort();
+    Ok(keys)
+}
+
+/// The domains a cleanup applies to: the given one, or every domain with `all`.
+pub(crate) fn target_domains(domain: Option<Domain>, all: bool) -> Result<Vec<Domain>> {
+    if !all {
+        return Ok(domain.into_iter().collect());
+    }
+
+    let mut domains: Vec<Domain> = Preferences::list_domains()?.into_iter().collect();
+    domains.sort_by_key(|d| d.get_cf_name());
+    Ok(domains)
+}
+
+/// Delete `keys` from their domains as one transaction, or only print them with `dry_run`.
+pub(crate) fn delete_keys(keys: Vec<(Domain, Vec<String>)>, dry_run: bool) -> Result<()> {
+    let mut tx = Transaction::new();
+    for (domain, keys) in &keys {
+        for key in keys {
+            println!("{domain}: {key}");
+            tx.delete(domain.clone(), key);
+        }
+    }
+
+    if tx.is_empty() {
+        println!("Nothing to clean up.");
+    } else if !dry_run {
+        println!("{}", tx.commit()?.summary());
+    }
+    Ok(())
+}
+
+/// The Saved Application State folder of a domain, if it exists.
+fn saved_state_dir(domain: &Domain) -> Option<PathBuf> {
+    let Domain::User(name) = domain else {
+        return None;
+    };
+    dirs::home_dir()
+        .map(|home| home.join(format!("Library/Saved Application State/{name}.savedState")))
+        .filter(|dir| dir.is_dir())
+}
+
+/// Remove window frames, panel and split view state, and optionally the saved-state folder.
+pub(crate) fn clean_window_state(
+    domains: Vec<Domain>,
+    saved_state: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let mut keys = Vec::new();
+    for domain in &domains {
+        let matched = matching_keys(domain, WINDOW_STATE_PREFIXES)?;
+        if !matched.is_empty() {
+            keys.push((domain.clone(), matched));
+        }
+    }
+    delete_keys(keys, dry_run)?;
+
+    if !saved_state {
+        return Ok(());
+    }
+    for dir in domains.iter().filter_map(saved_state_dir) {
+        remove_path(&dir, dry_run)?;
+    }
+    Ok(())
+}
+
+/// Print a file or folder and delete it unless `dry_run` is set.
+fn remove_path(path: &Path, dry_run: bool) -> Result<()> {
+    println!("{}", path.display());
+    if dry_run {
+        return Ok(());
+    }
+    if Preferences::is_read_only() {
+        return Err(RefusedReadOnly {
+            target: path.display().to_string(),
+        }
+        .into());
+    }
+
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+    .with_context(|| format!("failed to remove {}", path.display()))
+}
+
+/// Shared file lists to clear: those of `app`, or every list (including the system-wide recent
+/// applications, documents and servers) when `app` is `None`.
+fn shared_file_lists(app: Option<&str>) -> Vec<PathBuf> {
+    let Some(root) = dirs::home_dir().map(|home| home.join(SHARED_FILE_LIST_DIR)) else {
+        return Vec::new();
+    };
+    let is_list = |p: &Path| {
+        p.extension()
+            .is_some_and(|e| e.to_string_lossy().starts_with("sfl"))
+    };
+    let list_files = |dir: &Path| -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| e.path())
+                    .filter(|p| is_list(p))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let per_app = root.join("com.apple.LSSharedFileList.ApplicationRecentDocuments");
+    let mut files: Vec<PathBuf> = match app {
+        Some(app) => {
+            let app = app.to_lowercase();
+            list_files(&per_app)
+                .into_iter()
+                .filter(|p| {
+                    p.file_stem()
+                        .is_some_and(|stem| stem.to_string_lossy().to_lowercase() == app)
+                })
+                .collect()
+        }
+        None => list_files(&root)
+            .into_iter()
+            .filter(|p| {
+                p.file_name()
+                    .is_some_and(|name| name.to_string_lossy().contains("Recent"))
+            })
+            .chain(list_files(&per_app))
+            .collect(),
+    };
+    files.sort();
+    files
+}
+
+/// Clear recent documents of one app, or of every app and the system-wide lists.
+pub(crate) fn scrub_recents(app: Option<&str>, dry_run: bool) -> Result<()> {
+    let mut domains = match app {
+        Some(app) => vec![Domain::User(app.to_string())],
+        None => target_domains(None, true)?,
+    };
+    if app.is_none() {
+        domains.retain(|d| d.get_cf_name() != RECENT_ITEMS_DOMAIN);
+    }
+
+    let mut keys = Vec::new();
+    for domain in domains {
+        let matched = matching_keys(&domain, RECENTS_PREFIXES)?;
+        if !matched.is_empty() {
+            keys.push((domain, matched));
+        }
+    }
+    if app.is_none() {
+        let recent_items = Domain::User(RECENT_ITEMS_DOMAIN.to_string());
+        let matched = matching_keys(&recent_items, &["Recent", "Hosts"])?;
+        if !matched.is_empty() {
+            keys.push((recent_items, matched));
+        }
+    }
+    delete_keys(keys, dry_run)?;
+
+    for file in shared_file_lists(app) {
+        remove_path(&file, dry_run)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: MIT
+
+//! User configuration for the CLI.
+//!
+//! The config file holds flat `key = value` pairs (a subset of TOML), e.g.:
+//!
+//! ```toml
+//! # Back up each domain before it is first modified by a drs run.
+//! backup_on_write = true
+//!
+//! # Keep up to 32 recently read domains in memory.
+//! cache_capacity = 32
+//!
+//! # Keep backups and exports out of Spotlight and Time Machine.
+//! spotlight_exclude = true
+//! backup_exclude = true
+//! ```
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+
+use super::{paths, util::parse_bool};
+
+/// Settings read from the config file.
+#[derive(Debug, Default)]
+pub(crate) struct Config {
+    /// Export a domain to the backup store before it is first modified in a run.
+    pub backup_on_write: bool,
+    /// Number of recently read domains kept in memory (0 disables the cache).
+    pub cache_capacity: usize,
+    /// Keep backup and export directories out of the Spotlight index.
+    pub spotlight_exclude: bool,
+    /// Exclude backup and export directories from Time Machine.
+    pub backup_exclude: bool,
+}
+
+impl Config {
+    /// Load the config file, falling back to defaults if it doesn't exist.
+    pub(crate) fn load() -> Result<Self> {
+        let path = paths::config_file()?;
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+
+        Self::parse(&content).with_context(|| format!("invalid config file: {}", path.display()))
+    }
+
+    fn parse(content: &str) -> Result<Self> {
+        let mut config = Self::default();
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                bail!("Expected `key = value`, got: {line}");
+            };
+
+            match key.trim() {
+                "backup_on_write" => config.backup_on_write = parse_bool(value.trim(), false)?,
+                "cache_capacity" => {
+                    config.cache_capacity = value
+                        .trim()
+                        .parse()
+                        .with_context(|| format!("Expected a number, got: {}", value.trim()))?
+                }
+                "spotlight_exclude" => config.spotlight_exclude = parse_bool(value.trim(), false)?,
+                "backup_exclude" => config.backup_exclude = parse_bool(value.trim(), false)?,
+                other => bail!("Unknown config key: {other}"),
+            }
+        }
+
+        Ok(config)
+    }
+}
@@ -0,0 +1,230 @@
+// SPDX-License-Identifier: MIT
+
+//! Adding and removing Dock apps (`com.apple.dock` `persistent-apps`).
+//!
+//! Every app is a tile dictionary pointing at its bundle through a file URL:
+//!
+//! ```text
+//! {
+//!     "tile-type" = "file-tile";
+//!     "tile-data" = {
+//!         "file-label" = Safari;
+//!         "bundle-identifier" = "com.apple.Safari";
+//!         "file-type" = 41;
+//!         "file-data" = { "_CFURLString" = "file:///Applications/Safari.app/"; "_CFURLStringType" = 15; };
+//!     };
+//! }
+//! ```
+//!
+//! Positions are counted from 1, as they appear in the Dock.
+
+use anyhow::{Context, Result, bail};
+use plist::Value;
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{Domain, KeyPath, PrefValue, Preferences};
+
+use super::apps::run;
+
+const DOMAIN: &str = "com.apple.dock";
+const KEY: &str = "persistent-apps";
+
+/// `_CFURLStringType` of absolute URL strings.
+const URL_STRING_TYPE: i64 = 15;
+/// `file-type` of application bundles.
+const APP_FILE_TYPE: i64 = 41;
+
+fn domain() -> Domain {
+    Domain::User(DOMAIN.to_string())
+}
+
+fn load() -> Result<Vec<PrefValue>> {
+    if !Preferences::is_set(domain(), KEY) {
+        return Ok(Vec::new());
+    }
+    match Preferences::read(domain(), KEY)? {
+        PrefValue::Array(tiles) => Ok(tiles),
+        _ => bail!("{KEY} in {DOMAIN} is not an array."),
+    }
+}
+
+/// Percent-encode `path` as a `file://` URL, as the Dock stores it.
+fn file_url(path: &str) -> String {
+    let mut url = String::from("file://");
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || b"/-._~".contains(&b) {
+            url.push(b as char);
+        } else {
+            url.push_str(&format!("%{b:02X}"));
+        }
+    }
+    url
+}
+
+/// The path a `file://` URL points to, decoding percent escapes.
+fn url_path(url: &str) -> Option<String> {
+    let encoded = url.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let hex = encoded
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (encoded[i], hex) {
+            (b'%', Some(b)) => {
+                bytes.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                bytes.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(bytes)
+        .ok()
+        .map(|p| p.trim_end_matches('/').to_string())
+}
+
+/// The label, bundle identifier and path of a tile.
+fn describe(tile: &PrefValue) -> (String, Option<String>, Option<String>) {
+    let data = match tile {
+        PrefValue::Dictionary(tile) => match tile.get("tile-data") {
+            Some(PrefValue::Dictionary(data)) => Some(data),
+            _ => None,
+        },
+        _ => None,
+    };
+    let field = |key: &str| match data?.get(key) {
+        Some(PrefValue::String(s)) => Some(s.clone()),
+        _ => None,
+    };
+    let path = match data.and_then(|d| d.get("file-data")) {
+        Some(PrefValue::Dictionary(file)) => match file.get("_CFURLString") {
+            Some(PrefValue::String(url)) => url_path(url),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    (
+        field("file-label").unwrap_or_else(|| "(unnamed)".to_string()),
+        field("bundle-identifier"),
+        path,
+    )
+}
+
+/// Build the tile of the app bundle at `app`.
+fn app_tile(app: &Path) -> Result<PrefValue> {
+    let path =
+        fs::canonicalize(app).with_context(|| format!("No such application: {}", app.display()))?;
+    if path.extension().is_none_or(|e| e != "app") || !path.is_dir() {
+        bail!("{} is not an application bundle.", path.display());
+    }
+
+    let info = Value::from_file(path.join("Contents/Info.plist")).ok();
+    let field = |key: &str| {
+        info.as_ref()?
+            .as_dictionary()?
+            .get(key)?
+            .as_string()
+            .map(str::to_string)
+    };
+    let label = field("CFBundleDisplayName")
+        .or_else(|| field("CFBundleName"))
+        .or_else(|| path.file_stem().map(|s| s.to_string_lossy().into_owned()))
+        .unwrap_or_default();
+
+    let mut file_data = HashMap::new();
+    file_data.insert(
+        "_CFURLString".to_string(),
+        PrefValue::String(file_url(&format!("{}/", path.display()))),
+    );
+    file_data.insert(
+        "_CFURLStringType".to_string(),
+        PrefValue::Integer(URL_STRING_TYPE),
+    );
+
+    let mut data = HashMap::new();
+    data.insert("file-data".to_string(), PrefValue::Dictionary(file_data));
+    data.insert("file-label".to_string(), PrefValue::String(label));
+    data.insert("file-type".to_string(), PrefValue::Integer(APP_FILE_TYPE));
+    if let Some(id) = field("CFBundleIdentifier") {
+        data.insert("bundle-identifier".to_string(), PrefValue::String(id));
+    }
+
+    let mut tile = HashMap::new();
+    tile.insert("tile-data".to_string(), PrefValue::Dictionary(data));
+    tile.insert(
+        "tile-type".to_string(),
+        PrefValue::String("file-tile".to_string()),
+    );
+    Ok(PrefValue::Dictionary(tile))
+}
+
+/// Restart the Dock so it picks up the change.
+fn restart(restart_dock: bool) -> Result<()> {
+    if restart_dock {
+        run("killall", &["Dock"])?;
+    } else {
+        println!("Restart the Dock (killall Dock) for the change to take effect.");
+    }
+    Ok(())
+}
+
+/// Print the apps in the Dock with their positions.
+pub(crate) fn list() -> Result<()> {
+    for (i, tile) in load()?.iter().enumerate() {
+        let (label, id, path) = describe(tile);
+        let detail = path.or(id).unwrap_or_default();
+        println!("{:>3}  {label:<32} {detail}", i + 1);
+    }
+    Ok(())
+}
+
+/// Add the app at `app` to the Dock, at `position` or at the end.
+pub(crate) fn add_app(app: &Path, position: Option<usize>, restart_dock: bool) -> Result<()> {
+    let tile = app_tile(app)?;
+    let tiles = load()?;
+    let (label, _, path) = describe(&tile);
+    if tiles.iter().any(|t| describe(t).2 == path) {
+        bail!("{label} is already in the Dock.");
+    }
+
+    let index = match position {
+        Some(0) => bail!("Positions start at 1."),
+        Some(p) => (p - 1).min(tiles.len()),
+        None => tiles.len(),
+    };
+    if tiles.is_empty() {
+        Preferences::write(domain(), KEY, PrefValue::Array(vec![tile]))?;
+    } else {
+        Preferences::array_insert(domain(), &KeyPath::default().key(KEY), index, tile)?;
+    }
+    println!("Added {label} at position {}.", index + 1);
+    restart(restart_dock)
+}
+
+/// Remove the app matching `app` from the Dock: a position, a label, a bundle identifier or a path.
+pub(crate) fn remove_app(app: &str, restart_dock: bool) -> Result<()> {
+    let tiles = load()?;
+    let target = fs::canonicalize(app).ok().map(|p| p.display().to_string());
+
+    let index = match app.parse::<usize>() {
+        Ok(position) if (1..=tiles.len()).contains(&position) => position - 1,
+        _ => tiles
+            .iter()
+            .position(|tile| {
+                let (label, id, path) = describe(tile);
+                label.eq_ignore_ascii_case(app)
+                    || id.is_some_and(|id| id.eq_ignore_ascii_case(app))
+                    || path.is_some_and(|p| p == app || Some(&p) == target.as_ref())
+            })
+            .with_context(|| format!("No Dock app matches {app}."))?,
+    };
+
+    let removed = Preferences::array_remove(domain(), &KeyPath::default().key(KEY), index)?;
+    println!("Removed {}.", describe(&removed).0);
+    restart(restart_dock)
+}
@@ -0,0 +1,111 @@
+// SPDX-License-Identifier: MIT
+
+//! `drs enforce run`: keeping a manifest applied while drs runs.
+//!
+//! Every domain the manifest (see [`super::manifest`]) manages is watched, and whenever one
+//! changes, its managed keys which no longer hold their manifest value are written back. A key
+//! which drifts again within the cooloff after being enforced is left alone until the cooloff
+//! has passed, so drs doesn't end up fighting an app that insists on its own value. Every
+//! enforcement, and every key backed off from, is appended to the journal in the drs data
+//! directory.
+
+use anyhow::{Context, Result};
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    time::{Duration, Instant},
+};
+
+use crate::{Domain, PrefValue, Preferences, WatchOptions};
+
+use super::{
+    manifest::{self, Entry, Source},
+    paths,
+};
+
+/// Print `message` and append it to the journal with a timestamp.
+fn journal(message: &str) -> Result<()> {
+    let now = chrono::Local::now();
+    println!("[{}] {message}", now.format("%H:%M:%S"));
+
+    fs::create_dir_all(paths::data_dir()?)?;
+    let path = paths::journal_file()?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    writeln!(file, "{} {message}", now.to_rfc3339())?;
+    Ok(())
+}
+
+/// When each key was last enforced, and whether backing off from it was already logged.
+type Enforced = HashMap<(Domain, String), (Instant, bool)>;
+
+/// Write back the keys of `entry` which drifted, unless they are cooling off.
+fn enforce(entry: &Entry, cooloff: Duration, enforced: &mut Enforced) -> Result<()> {
+    for (key, value) in &entry.values {
+        let current = Preferences::read(entry.domain.clone(), key).ok();
+        if current.as_ref() == Some(value) {
+            continue;
+        }
+        let shown = current
+            .as_ref()
+            .map_or("(unset)".to_string(), PrefValue::to_string);
+        let domain = &entry.domain;
+
+        if let Some((at, backed_off)) = enforced.get_mut(&(domain.clone(), key.clone()))
+            && at.elapsed() < cooloff
+        {
+            if !*backed_off {
+                journal(&format!(
+                    "backed off {domain} {key}: changed to {shown} again within {}s",
+                    cooloff.as_secs()
+                ))?;
+                *backed_off = true;
+            }
+            continue;
+        }
+
+        match Preferences::write(domain.clone(), key, value.clone()) {
+            Ok(()) => journal(&format!("enforced {domain} {key}: {shown} -> {value}"))?,
+            Err(e) => journal(&format!("failed to enforce {domain} {key}: {e:#}"))?,
+        }
+        enforced.insert((domain.clone(), key.clone()), (Instant::now(), false));
+    }
+    Ok(())
+}
+
+/// Enforce the manifest at `source` until interrupted, re-applying drifted keys at most once per
+/// `cooloff`.
+pub(crate) fn run(source: Source, cooloff: Duration) -> Result<()> {
+    let loaded = manifest::load(source.clone())?;
+    let entries = loaded.entries;
+    let mut enforced = Enforced::new();
+
+    let names: Vec<String> = entries.iter().map(|e| e.domain.to_string()).collect();
+    println!(
+        "Enforcing {source} on {} (Ctrl-C to stop)",
+        names.join(", ")
+    );
+    for entry in &entries {
+        enforce(entry, cooloff, &mut enforced)?;
+    }
+
+    let domains = entries.iter().map(|e| e.domain.clone()).collect();
+    let mut failure = None;
+    Preferences::watch_many(domains, &WatchOptions::default(), |change| {
+        let Some(entry) = entries.iter().find(|e| e.domain == change.domain) else {
+            return true;
+        };
+        match enforce(entry, cooloff, &mut enforced) {
+            Ok(()) => true,
+            Err(e) => {
+                failure = Some(e);
+                false
+            }
+        }
+    })?;
+    failure.map_or(Ok(()), Err)
+}
@@ -0,0 +1,149 @@
+// SPDX-License-Identifier: MIT
+
+//! Default application handlers (`com.apple.LaunchServices/com.apple.launchservices.secure`).
+//!
+//! LaunchServices keeps its user overrides in the `LSHandlers` array. Each entry names either a
+//! content type (`LSHandlerContentType`, e.g. `public.html`) or a URL scheme
+//! (`LSHandlerURLScheme`, e.g. `mailto`) and the bundle identifier handling it per role. Targets
+//! containing a `.` are treated as content types, anything else as a URL scheme.
+
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+
+use crate::{Domain, PrefValue, Preferences};
+
+use super::apps::run;
+
+const DOMAIN: &str = "com.apple.LaunchServices/com.apple.launchservices.secure";
+const KEY: &str = "LSHandlers";
+const LSREGISTER: &str = "/System/Library/Frameworks/CoreServices.framework/Frameworks/LaunchServices.framework/Support/lsregister";
+
+const CONTENT_TYPE: &str = "LSHandlerContentType";
+const URL_SCHEME: &str = "LSHandlerURLScheme";
+const ROLES: &[&str] = &[
+    "LSHandlerRoleAll",
+    "LSHandlerRoleViewer",
+    "LSHandlerRoleEditor",
+];
+
+fn domain() -> Domain {
+    Domain::User(DOMAIN.to_string())
+}
+
+/// The entry key a target is stored under.
+fn target_key(target: &str) -> &'static str {
+    if target.contains('.') {
+        CONTENT_TYPE
+    } else {
+        URL_SCHEME
+    }
+}
+
+fn as_str<'a>(entry: &'a HashMap<String, PrefValue>, key: &str) -> Option<&'a str> {
+    match entry.get(key) {
+        Some(PrefValue::String(s)) => Some(s),
+        _ => None,
+    }
+}
+
+fn load() -> Result<Vec<PrefValue>> {
+    if !Preferences::is_set(domain(), KEY) {
+        return Ok(Vec::new());
+    }
+    match Preferences::read(domain(), KEY)? {
+        PrefValue::Array(handlers) => Ok(handlers),
+        _ => bail!("{KEY} in {DOMAIN} is not an array."),
+    }
+}
+
+/// Returns the target and bundle identifier of a handler entry.
+fn describe(entry: &PrefValue) -> Option<(&str, &str)> {
+    let PrefValue::Dictionary(entry) = entry else {
+        return None;
+    };
+    let target = as_str(entry, CONTENT_TYPE).or_else(|| as_str(entry, URL_SCHEME))?;
+    let handler = ROLES.iter().find_map(|role| as_str(entry, role))?;
+    Some((target, handler))
+}
+
+fn matches(entry: &PrefValue, target: &str) -> bool {
+    let PrefValue::Dictionary(entry) = entry else {
+        return false;
+    };
+    as_str(entry, target_key(target)).is_some_and(|t| t.eq_ignore_ascii_case(target))
+}
+
+/// Print every handler override.
+pub(crate) fn list() -> Result<()> {
+    let handlers = load()?;
+    let mut rows: Vec<(&str, &str)> = handlers.iter().filter_map(describe).collect();
+    rows.sort();
+
+    for (target, handler) in rows {
+        println!("{target:<40} {handler}");
+    }
+    Ok(())
+}
+
+/// Print the handler of a content type or URL scheme.
+pub(crate) fn get(target: &str) -> Result<()> {
+    let handlers = load()?;
+    match handlers
+        .iter()
+        .find(|e| matches(e, target))
+        .and_then(describe)
+    {
+        Some((_, handler)) => println!("{handler}"),
+        None => bail!("No handler set for: {target}"),
+    }
+    Ok(())
+}
+
+/// Make `bundle_id` the handler of a content type or URL scheme, for all roles.
+pub(crate) fn set(target: &str, bundle_id: &str, refresh: bool) -> Result<()> {
+    // LaunchServices stores and compares bundle identifiers in lowercase.
+    let bundle_id = bundle_id.to_lowercase();
+
+    let mut handlers = load()?;
+    handlers.retain(|e| !matches(e, target));
+    handlers.push(PrefValue::Dictionary(HashMap::from([
+        (
+            target_key(target).to_string(),
+            PrefValue::String(target.to_string()),
+        ),
+        (
+            "LSHandlerRoleAll".to_string(),
+            PrefValue::String(bundle_id.clone()),
+        ),
+        (
+            "LSHandlerPreferredVersions".to_string(),
+            PrefValue::Dictionary(HashMap::from([(
+                "LSHandlerRoleAll".to_string(),
+                PrefValue::String("-".to_string()),
+            )])),
+        ),
+    ])));
+
+    Preferences::write(domain(), KEY, PrefValue::Array(handlers))?;
+    println!("{target} → {bundle_id}");
+
+    if refresh {
+        refresh_launch_services()?;
+    } else {
+        println!(
+            "Run `{LSREGISTER} -kill -r -domain local -domain system -domain user` or log out to apply."
+        );
+    }
+    Ok(())
+}
+
+/// Rebuild the LaunchServices database so the new handlers take effect.
+fn refresh_launch_services() -> Result<()> {
+    run(
+        LSREGISTER,
+        &[
+            "-kill", "-r", "-domain", "local", "-domain", "system", "-domain", "user",
+        ],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: MIT
+
+//! Helpers for identifying the current machine.
+
+use anyhow::{Context, Result};
+
+use crate::facts;
+
+/// Returns the hardware UUID of this Mac, which names its ByHost preference files.
+pub(crate) fn hardware_uuid() -> Result<String> {
+    facts::hardware_uuid().context("Could not determine the hardware UUID.")
+}
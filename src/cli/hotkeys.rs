@@ -0,0 +1,339 @@
+// SPDX-License-Identifier: MIT
+
+//! Decoding and editing of system keyboard shortcuts (`com.apple.symbolichotkeys`).
+//!
+//! Shortcuts live in the `AppleSymbolicHotKeys` dictionary, keyed by a numeric ID:
+//!
+//! ```text
+//! "64" = { enabled = 1; value = { parameters = (65535, 49, 1048576); type = standard; }; };
+//! ```
+//!
+//! The parameters are the character code (65535 for keys without one), the virtual key code and
+//! the modifier flags. Shortcuts are written as `cmd+shift+4`, `ctrl+f2` or `ctrl+left`.
+
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+
+use crate::{Domain, PrefValue, Preferences, render::describe_shortcut};
+
+use super::apps::run;
+
+const DOMAIN: &str = "com.apple.symbolichotkeys";
+const KEY: &str = "AppleSymbolicHotKeys";
+
+/// Character code used for keys which don't produce a character.
+const NO_CHAR: i64 = 65535;
+
+/// Known shortcut IDs and what they do.
+pub(crate) const KNOWN: &[(u32, &str)] = &[
+    (7, "Move focus to the menu bar"),
+    (8, "Move focus to the Dock"),
+    (9, "Move focus to active or next window"),
+    (10, "Move focus to window toolbar"),
+    (11, "Move focus to floating window"),
+    (12, "Turn keyboard access on or off"),
+    (13, "Change the way Tab moves focus"),
+    (15, "Turn zoom on or off"),
+    (17, "Zoom in"),
+    (19, "Zoom out"),
+    (21, "Invert colors"),
+    (27, "Move focus to next window"),
+    (28, "Save picture of screen as a file"),
+    (29, "Copy picture of screen to the clipboard"),
+    (30, "Save picture of selected area as a file"),
+    (31, "Copy picture of selected area to the clipboard"),
+    (32, "Mission Control"),
+    (33, "Application windows"),
+    (36, "Show Desktop"),
+    (52, "Turn Dock hiding on or off"),
+    (57, "Move focus to status menus"),
+    (59, "Turn VoiceOver on or off"),
+    (60, "Select the previous input source"),
+    (61, "Select next source in Input menu"),
+    (64, "Show Spotlight search"),
+    (65, "Show Finder search window"),
+    (79, "Move left a space"),
+    (81, "Move right a space"),
+    (118, "Switch to Desktop 1"),
+    (119, "Switch to Desktop 2"),
+    (120, "Switch to Desktop 3"),
+    (121, "Switch to Desktop 4"),
+    (160, "Show Launchpad"),
+    (162, "Show Accessibility controls"),
+    (163, "Show Notification Center"),
+    (175, "Turn Do Not Disturb on or off"),
+    (184, "Screenshot and recording options"),
+    (190, "Quick Note"),
+];
+
+const MODIFIERS: &[(&[&str], i64)] = &[
+    (&["shift"], 0x20000),
+    (&["ctrl", "control"], 0x40000),
+    (&["opt", "option", "alt"], 0x80000),
+    (&["cmd", "command"], 0x100000),
+    (&["fn"], 0x800000),
+];
+
+/// Virtual key codes of an ANSI keyboard, for keys which produce a character.
+const CHAR_KEYS: &[(char, i64)] = &[
+    ('a', 0),
+    ('s', 1),
+    ('d', 2),
+    ('f', 3),
+    ('h', 4),
+    ('g', 5),
+    ('z', 6),
+    ('x', 7),
+    ('c', 8),
+    ('v', 9),
+    ('b', 11),
+    ('q', 12),
+    ('w', 13),
+    ('e', 14),
+    ('r', 15),
+    ('y', 16),
+    ('t', 17),
+    ('1', 18),
+    ('2', 19),
+    ('3', 20),
+    ('4', 21),
+    ('6', 22),
+    ('5', 23),
+    ('=', 24),
+    ('9', 25),
+    ('7', 26),
+    ('-', 27),
+    ('8', 28),
+    ('0', 29),
+    (']', 30),
+    ('o', 31),
+    ('u', 32),
+    ('[', 33),
+    ('i', 34),
+    ('p', 35),
+    ('l', 37),
+    ('j', 38),
+    ('\'', 39),
+    ('k', 40),
+    (';', 41),
+    ('\\', 42),
+    (',', 43),
+    ('/', 44),
+    ('n', 45),
+    ('m', 46),
+    ('.', 47),
+    ('`', 50),
+];
+
+/// Virtual key codes of keys without a character.
+const NAMED_KEYS: &[(&str, i64)] = &[
+    ("tab", 48),
+    ("space", 49),
+    ("f1", 122),
+    ("f2", 120),
+    ("f3", 99),
+    ("f4", 118),
+    ("f5", 96),
+    ("f6", 97),
+    ("f7", 98),
+    ("f8", 100),
+    ("f9", 101),
+    ("f10", 109),
+    ("f11", 103),
+    ("f12", 111),
+    ("left", 123),
+    ("right", 124),
+    ("down", 125),
+    ("up", 126),
+];
+
+/// Name of a shortcut ID, if it is known.
+pub(crate) fn name_of(id: u32) -> Option<&'static str> {
+    KNOWN.iter().find(|(k, _)| *k == id).map(|(_, name)| *name)
+}
+
+/// Parses a shortcut like `cmd+shift+4` into its (character, key code, modifiers) parameters.
+pub(crate) fn parse_shortcut(input: &str) -> Result<(i64, i64, i64)> {
+    let lower = input.to_lowercase();
+    let mut parts: Vec<&str> = lower.split('+').map(str::trim).collect();
+    let key = parts
+        .pop()
+        .filter(|k| !k.is_empty())
+        .context("Shortcut is missing a key")?;
+
+    let mut modifiers = 0;
+    for part in parts {
+        let (_, flag) = MODIFIERS
+            .iter()
+            .find(|(names, _)| names.contains(&part))
+            .with_context(|| format!("Unknown modifier `{part}` in: {input}"))?;
+        modifiers |= flag;
+    }
+
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next())
+        && let Some((_, code)) = CHAR_KEYS.iter().find(|(k, _)| *k == c)
+    {
+        return Ok((c as i64, *code, modifiers));
+    }
+
+    let (name, code) = NAMED_KEYS
+        .iter()
+        .find(|(name, _)| *name == key)
+        .with_context(|| format!("Unknown key `{key}` in: {input}"))?;
+    match *name {
+        "space" => Ok((' ' as i64, *code, modifiers)),
+        "tab" => Ok(('\t' as i64, *code, modifiers)),
+        // Function and arrow keys are reported with the fn flag set.
+        _ => Ok((NO_CHAR, *code, modifiers | 0x800000)),
+    }
+}
+
+fn load() -> Result<HashMap<String, PrefValue>> {
+    if !Preferences::is_set(Domain::User(DOMAIN.to_string()), KEY) {
+        return Ok(HashMap::new());
+    }
+    match Preferences::read(Domain::User(DOMAIN.to_string()), KEY)? {
+        PrefValue::Dictionary(dict) => Ok(dict),
+        _ => bail!("{KEY} in {DOMAIN} is not a dictionary."),
+    }
+}
+
+fn store(hotkeys: HashMap<String, PrefValue>) -> Result<()> {
+    Preferences::write(
+        Domain::User(DOMAIN.to_string()),
+        KEY,
+        PrefValue::Dictionary(hotkeys),
+    )?;
+
+    // Apply the change without logging out; harmless if the tool is unavailable.
+    if run(
+        "/System/Library/PrivateFrameworks/SystemAdministration.framework/Resources/activateSettings",
+        &["-u"],
+    )
+    .is_err()
+    {
+        println!("Log out and back in for the change to take effect.");
+    }
+    Ok(())
+}
+
+/// Returns the shortcut parameters stored in an entry.
+fn parameters(entry: &PrefValue) -> Option<(i64, i64, i64)> {
+    let PrefValue::Dictionary(entry) = entry else {
+        return None;
+    };
+    let Some(PrefValue::Dictionary(value)) = entry.get("value") else {
+        return None;
+    };
+    let Some(PrefValue::Array(params)) = value.get("parameters") else {
+        return None;
+    };
+
+    match params.as_slice() {
+        [
+            PrefValue::Integer(c),
+            PrefValue::Integer(k),
+            PrefValue::Integer(m),
+        ] => Some((*c, *k, *m)),
+        _ => None,
+    }
+}
+
+fn is_enabled(entry: &PrefValue) -> bool {
+    let PrefValue::Dictionary(entry) = entry else {
+        return false;
+    };
+    match entry.get("enabled") {
+        Some(PrefValue::Boolean(b)) => *b,
+        Some(PrefValue::Integer(i)) => *i != 0,
+        _ => false,
+    }
+}
+
+/// Print every known or configured shortcut.
+pub(crate) fn list() -> Result<()> {
+    let hotkeys = load()?;
+
+    let mut ids: Vec<u32> = KNOWN
+        .iter()
+        .map(|(id, _)| *id)
+        .chain(hotkeys.keys().filter_map(|k| k.parse().ok()))
+        .collect();
+    ids.sort();
+    ids.dedup();
+
+    for id in ids {
+        let name = name_of(id).unwrap_or("(unknown)");
+        let state = match hotkeys.get(&id.to_string()) {
+            Some(entry) => {
+                let shortcut = parameters(entry)
+                    .map(|(c, k, m)| describe_shortcut(c, k, m))
+                    .unwrap_or_else(|| "no shortcut".to_string());
+                let enabled = if is_enabled(entry) {
+                    "enabled"
+                } else {
+                    "disabled"
+                };
+                format!("{shortcut} ({enabled})")
+            }
+            None => "system default".to_string(),
+        };
+        println!("{id:>4}  {name:<48} {state}");
+    }
+    Ok(())
+}
+
+/// Enable or disable a shortcut, keeping its key combination.
+pub(crate) fn set_enabled(id: u32, enabled: bool) -> Result<()> {
+    let mut hotkeys = load()?;
+    let entry = hotkeys
+        .entry(id.to_string())
+        .or_insert_with(|| PrefValue::Dictionary(HashMap::new()));
+
+    let PrefValue::Dictionary(entry) = entry else {
+        bail!("Shortcut {id} is not a dictionary.")
+    };
+    if enabled && !entry.contains_key("value") {
+        bail!("Shortcut {id} has no key combination yet; use `drs hotkeys set {id} <shortcut>`.")
+    }
+    let flag = match entry.get("enabled") {
+        Some(PrefValue::Integer(_)) => PrefValue::Integer(enabled as i64),
+        _ => PrefValue::Boolean(enabled),
+    };
+    entry.insert("enabled".to_string(), flag);
+
+    store(hotkeys)
+}
+
+/// Assign a key combination to a shortcut and enable it.
+pub(crate) fn set(id: u32, shortcut: &str) -> Result<()> {
+    let (c, k, m) = parse_shortcut(shortcut)?;
+    let mut hotkeys = load()?;
+
+    let value = HashMap::from([
+        (
+            "parameters".to_string(),
+            PrefValue::Array(vec![
+                PrefValue::Integer(c),
+                PrefValue::Integer(k),
+                PrefValue::Integer(m),
+            ]),
+        ),
+        (
+            "type".to_string(),
+            PrefValue::String("standard".to_string()),
+        ),
+    ]);
+    hotkeys.insert(
+        id.to_string(),
+        PrefValue::Dictionary(HashMap::from([
+            ("enabled".to_string(), PrefValue::Boolean(true)),
+            ("value".to_string(), PrefValue::Dictionary(value)),
+        ])),
+    );
+
+    store(hotkeys)?;
+    println!("{id}: {}", describe_shortcut(c, k, m));
+    Ok(())
+}
@@ -0,0 +1,231 @@
+// SPDX-License-Identifier: MIT
+
+//! `drs login-items`: listing what starts at login, read-only.
+//!
+//! Login items come from several places:
+//!
+//! - launchd agents and daemons: plists in `~/Library/LaunchAgents`, `/Library/LaunchAgents`
+//!   and `/Library/LaunchDaemons`, which drs can edit with `--file`;
+//! - the legacy `AutoLaunchedApplicationDictionary` of `com.apple.loginwindow`, a domain drs
+//!   manages like any other;
+//! - Background Task Management databases (`.btm` files), keyed archives maintained by the
+//!   system. Apps registered through System Settings or `SMAppService` end up here; they are
+//!   decoded where readable but can only be changed in System Settings.
+
+use anyhow::Result;
+use plist::{Dictionary, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{Domain, PrefValue, Preferences};
+
+/// Launchd job folders and the origin reported for their plists.
+const LAUNCHD_DIRS: &[(&str, &str)] = &[
+    ("~/Library/LaunchAgents", "launch agent (user)"),
+    ("/Library/LaunchAgents", "launch agent"),
+    ("/Library/LaunchDaemons", "launch daemon"),
+];
+
+/// Background Task Management databases, from the oldest macOS release to the newest.
+const BTM_FILES: &[&str] = &[
+    "~/Library/Application Support/com.apple.backgroundtaskmanagementagent/backgrounditems.btm",
+    "/private/var/db/com.apple.backgroundtaskmanagement",
+];
+
+/// A login item and where it comes from.
+struct LoginItem {
+    name: String,
+    origin: &'static str,
+    /// The file or domain holding the item, or its identifier for background items.
+    source: String,
+    /// Whether drs can edit the item's source.
+    managed: bool,
+}
+
+fn expand(path: &str) -> Option<PathBuf> {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().map(|home| home.join(rest)),
+        None => Some(PathBuf::from(path)),
+    }
+}
+
+fn launchd_items(items: &mut Vec<LoginItem>) {
+    for (dir, origin) in LAUNCHD_DIRS {
+        let Some(dir) = expand(dir) else { continue };
+        let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "plist"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let job = Value::from_file(&path).ok();
+            let job = job.as_ref().and_then(Value::as_dictionary);
+            let label = job
+                .and_then(|j| j.get("Label"))
+                .and_then(Value::as_string)
+                .map(str::to_string)
+                .or_else(|| path.file_stem().map(|s| s.to_string_lossy().into_owned()))
+                .unwrap_or_default();
+            let disabled = job
+                .and_then(|j| j.get("Disabled"))
+                .and_then(Value::as_boolean)
+                .unwrap_or(false);
+
+            items.push(LoginItem {
+                name: if disabled {
+                    format!("{label} (disabled)")
+                } else {
+                    label
+                },
+                origin,
+                source: path.display().to_string(),
+                managed: true,
+            });
+        }
+    }
+}
+
+fn loginwindow_items(items: &mut Vec<LoginItem>) {
+    let domain = Domain::User("com.apple.loginwindow".to_string());
+    let Ok(PrefValue::Array(apps)) =
+        Preferences::read(domain.clone(), "AutoLaunchedApplicationDictionary")
+    else {
+        return;
+    };
+
+    for app in apps {
+        let PrefValue::Dictionary(app) = app else {
+            continue;
+        };
+        let Some(PrefValue::String(path)) = app.get("Path") else {
+            continue;
+        };
+        items.push(LoginItem {
+            name: Path::new(path)
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.clone()),
+            origin: "login window (legacy)",
+            source: format!("{domain} AutoLaunchedApplicationDictionary"),
+            managed: true,
+        });
+    }
+}
+
+/// The object a keyed archive `$objects` entry refers to, following UIDs.
+fn deref<'a>(objects: &'a [Value], value: &'a Value) -> &'a Value {
+    match value {
+        Value::Uid(uid) => objects
+            .get(uid.get() as usize)
+            .map_or(value, |v| deref(objects, v)),
+        other => other,
+    }
+}
+
+/// A string field of an archived object, looking through `NSString` and `NSURL` wrappers.
+fn archived_string(objects: &[Value], object: &Dictionary, key: &str) -> Option<String> {
+    match deref(objects, object.get(key)?) {
+        Value::String(s) => Some(s.clone()),
+        Value::Dictionary(inner) => ["NS.string", "NS.relative"]
+            .iter()
+            .find_map(|k| archived_string(objects, inner, k)),
+        _ => None,
+    }
+}
+
+/// The items of a Background Task Management database: the archived objects carrying a name and
+/// an identifier or URL.
+fn btm_items(path: &Path, items: &mut Vec<LoginItem>) -> Result<()> {
+    let archive = Value::from_file(path)?;
+    let Some(objects) = archive
+        .as_dictionary()
+        .and_then(|a| a.get("$objects"))
+        .and_then(Value::as_array)
+    else {
+        return Ok(());
+    };
+
+    let mut seen = Vec::new();
+    for object in objects.iter().filter_map(Value::as_dictionary) {
+        let Some(name) = archived_string(objects, object, "name") else {
+            continue;
+        };
+        let detail = ["identifier", "bundleIdentifier", "url", "executablePath"]
+            .iter()
+            .find_map(|k| archived_string(objects, object, k));
+        let Some(detail) = detail else { continue };
+        if seen.contains(&(name.clone(), detail.clone())) {
+            continue;
+        }
+        seen.push((name.clone(), detail.clone()));
+
+        items.push(LoginItem {
+            name,
+            origin: "background item",
+            source: detail,
+            managed: false,
+        });
+    }
+    Ok(())
+}
+
+/// Print every login item found, with its origin.
+pub(crate) fn list() -> Result<()> {
+    let mut items = Vec::new();
+    let mut unreadable = Vec::new();
+
+    launchd_items(&mut items);
+    loginwindow_items(&mut items);
+    for path in BTM_FILES.iter().filter_map(|p| expand(p)) {
+        let files: Vec<PathBuf> = if path.is_dir() {
+            fs::read_dir(&path)
+                .map(|entries| {
+                    entries
+                        .flatten()
+                        .map(|e| e.path())
+                        .filter(|p| p.extension().is_some_and(|e| e == "btm"))
+                        .collect()
+                })
+                .unwrap_or_else(|_| vec![path.clone()])
+        } else if path.exists() {
+            vec![path]
+        } else {
+            Vec::new()
+        };
+        for file in files {
+            if btm_items(&file, &mut items).is_err() {
+                unreadable.push(file);
+            }
+        }
+    }
+
+    let name_w = items
+        .iter()
+        .map(|i| i.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let origin_w = items.iter().map(|i| i.origin.len()).max().unwrap_or(0);
+    for item in &items {
+        let marker = if item.managed { "*" } else { " " };
+        println!(
+            "{marker} {:<name_w$}  {:<origin_w$}  {}",
+            item.name, item.origin, item.source
+        );
+    }
+    if items.iter().any(|i| i.managed) {
+        println!("\n* drs can change this item's source directly.");
+    }
+    for file in unreadable {
+        eprintln!(
+            "Couldn't read {} (run as root or grant Full Disk Access to see its items).",
+            file.display()
+        );
+    }
+    Ok(())
+}
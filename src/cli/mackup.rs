@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: MIT
+
+//! Importer for mackup's application definition format.
+//!
+//! Mackup describes every supported application in a small INI file:
+//!
+//! ```ini
+//! [application]
+//! name = Dock
+//!
+//! [configuration_files]
+//! Library/Preferences/com.apple.dock.plist
+//! ```
+//!
+//! Preference plists listed under `configuration_files` are turned into domains, which lets the
+//! sync subcommands group exports and restores per application.
+
+use anyhow::{Context, Result, bail};
+use std::{fs, path::Path};
+
+/// An application and the preference domains it owns.
+#[derive(Debug)]
+pub(crate) struct AppDefinition {
+    pub name: String,
+    pub domains: Vec<String>,
+}
+
+/// Parse a single mackup application definition.
+pub(crate) fn parse_definition(content: &str) -> Result<AppDefinition> {
+    let mut section = String::new();
+    let mut name = None;
+    let mut domains = Vec::new();
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = header.trim().to_string();
+            continue;
+        }
+
+        match section.as_str() {
+            "application" => {
+                if let Some((k, v)) = line.split_once('=')
+                    && k.trim() == "name"
+                {
+                    name = Some(v.trim().to_string());
+                }
+            }
+            "configuration_files" => {
+                if let Some(domain) = line
+                    .strip_prefix("Library/Preferences/")
+                    .and_then(|f| f.strip_suffix(".plist"))
+                    .filter(|d| !d.contains('/'))
+                {
+                    domains.push(domain.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let Some(name) = name else {
+        bail!("Application definition is missing `name` in its [application] section.")
+    };
+
+    Ok(AppDefinition { name, domains })
+}
+
+/// Load every `.cfg` definition in a directory, skipping applications without preference domains.
+pub(crate) fn load_dir(dir: &Path) -> Result<Vec<AppDefinition>> {
+    let mut apps = Vec::new();
+
+    for entry in fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "cfg") {
+            continue;
+        }
+
+        let content = fs::read_to_string(&path)?;
+        let app = parse_definition(&content)
+            .with_context(|| format!("invalid mackup definition: {}", path.display()))?;
+        if !app.domains.is_empty() {
+            apps.push(app);
+        }
+    }
+
+    apps.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(apps)
+}
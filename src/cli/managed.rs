@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: MIT
+
+//! `drs managed report`: which settings configuration profiles control.
+//!
+//! Keys forced by a profile (usually installed by an organization's MDM) can still be written,
+//! but apps keep seeing the profile's value, so this lists what can't be changed locally.
+
+use anyhow::Result;
+use serde_json::json;
+
+use crate::Preferences;
+
+/// Print every forced key grouped by domain, or as a JSON array with `json`.
+pub(crate) fn report(json: bool) -> Result<()> {
+    let forced = Preferences::forced_keys()?;
+
+    if json {
+        let items: Vec<serde_json::Value> = forced
+            .iter()
+            .map(|f| json!({ "domain": f.domain.to_string(), "key": f.key, "value": f.value.to_json() }))
+            .collect();
+        println!("{}", serde_json::Value::Array(items));
+        return Ok(());
+    }
+
+    if forced.is_empty() {
+        println!("No settings are forced by configuration profiles.");
+        return Ok(());
+    }
+
+    let mut domains = 0;
+    let mut last = None;
+    for item in &forced {
+        if last != Some(&item.domain) {
+            if last.is_some() {
+                println!();
+            }
+            println!("{}", item.domain);
+            domains += 1;
+            last = Some(&item.domain);
+        }
+        println!("  {} = {}", item.key, item.value);
+    }
+    println!(
+        "\n{} key(s) in {domains} domain(s) are set by configuration profiles and can't be changed locally.",
+        forced.len()
+    );
+    Ok(())
+}
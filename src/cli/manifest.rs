@@ -0,0 +1,550 @@
+// SPDX-License-Identifier: MIT
+
+//! Loading the manifests `drs apply` works from.
+//!
+//! A manifest is a plist or typed JSON dictionary mapping domain names to the keys and values to
+//! write. The reserved top-level key `include` lists further manifests to layer underneath it:
+//!
+//! ```json
+//! {
+//!   "include": ["base.json", "https://example.com/dotfiles/work.plist"],
+//!   "com.apple.dock": { "tilesize": 36 }
+//! }
+//! ```
+//!
+//! Includes are resolved relative to the manifest naming them and loaded in the listed order,
+//! before the manifest itself: where several set the same key, the later include wins, and the
+//! including manifest wins over all of them. Remote manifests can only include other HTTPS URLs.
+//!
+//! A domain may also map to an array of dictionaries, each guarded by an optional `$when`
+//! dictionary of facts (see [`crate::facts`]) and the patterns they must match. A block only
+//! applies if every fact matches; later blocks override earlier ones:
+//!
+//! ```json
+//! {
+//!   "com.apple.dock": [
+//!     { "tilesize": 36 },
+//!     { "$when": { "hostname": "mbp-*", "macos": ">=14" }, "tilesize": 48 }
+//!   ]
+//! }
+//! ```
+//!
+//! `hostname`, `hardware_uuid`, `arch` and `user` take globs with `*` and `?`, matched ignoring
+//! case against the values `drs facts` prints. `macos` takes a version, optionally after `=`,
+//! `!=`, `<`, `<=`, `>` or `>=`, compared on the components it gives (`14` matches 14.5).
+//!
+//! Besides `$when`, a block may carry `$after`, domains to apply before this one, and `$restart`,
+//! processes to restart once the domain has changed (`["Dock"]`); see [`super::apply`].
+//!
+//! Strings and dictionary keys in applied blocks may refer to `${env:VAR}`, `${fact:NAME}`,
+//! `${display:NAME}` and `${home}`, such as `"${home}/Pictures/Screenshots"`; write `$${` for a
+//! literal `${`. `${display:builtin}` and `${display:main}` stand for the UUIDs of the built-in and
+//! main displays, for settings keyed per display.
+
+use anyhow::{Context, Result, anyhow, bail};
+use plist::Value;
+use std::{
+    fmt,
+    io::Cursor,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    Domain, PrefValue,
+    core::file,
+    displays,
+    facts::{self, Facts},
+    preferences::convert::plist_to_prefvalue,
+    preferences::hash::sha256,
+};
+
+use super::util::{expand_vars, glob_regex};
+
+/// A domain's values to write, in manifest order, with how to apply them.
+#[derive(Debug, Clone)]
+pub(crate) struct Entry {
+    pub(crate) domain: Domain,
+    pub(crate) values: Vec<(String, PrefValue)>,
+    /// Domains to apply before this one.
+    pub(crate) after: Vec<Domain>,
+    /// Processes to restart once this domain has changed.
+    pub(crate) restart: Vec<String>,
+}
+
+/// Keys and values in manifest order.
+type Pairs = Vec<(String, PrefValue)>;
+
+/// A domain's keys and values as given in one place of a manifest, with its guard.
+struct Block {
+    entry: Entry,
+    when: Option<Condition>,
+}
+
+/// Where a manifest is loaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Source {
+    Url(String),
+    File(PathBuf),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Url(url) => write!(f, "{url}"),
+            Source::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+impl Source {
+    /// A local manifest, made absolute so includes and cycles are tracked reliably.
+    pub(crate) fn file(path: &Path) -> Result<Source> {
+        Ok(Source::File(path.canonicalize().with_context(|| {
+            format!("failed to read {}", path.display())
+        })?))
+    }
+
+    /// The manifest `reference` (as written in an `include`) points to.
+    fn resolve(&self, reference: &str) -> Result<Source> {
+        if reference.starts_with("https://") {
+            return Ok(Source::Url(reference.to_string()));
+        }
+        match self {
+            Source::Url(url) => {
+                let rest = url.strip_prefix("https://").unwrap_or(url);
+                let host = rest.split('/').next().unwrap_or(rest);
+                if reference.starts_with('/') {
+                    Ok(Source::Url(format!("https://{host}{reference}")))
+                } else {
+                    let dir = url.rsplit_once('/').map_or(url.as_str(), |(dir, _)| dir);
+                    Ok(Source::Url(format!("{dir}/{reference}")))
+                }
+            }
+            Source::File(path) => {
+                let dir = path.parent().unwrap_or(Path::new("/"));
+                Source::file(&dir.join(expand_vars(reference)?))
+            }
+        }
+    }
+
+    fn read(&self) -> Result<Vec<u8>> {
+        match self {
+            Source::Url(url) => fetch(url),
+            Source::File(path) => file::read_limited(path),
+        }
+    }
+}
+
+/// A manifest with everything it includes merged in.
+pub(crate) struct Manifest {
+    /// Every manifest loaded, with its SHA-256 and size, in load order.
+    pub(crate) sources: Vec<(Source, String, usize)>,
+    pub(crate) entries: Vec<Entry>,
+    /// Blocks left out because their condition doesn't hold, in load order.
+    pub(crate) skipped: Vec<(Domain, Condition)>,
+}
+
+/// Download `url`, refusing anything but HTTPS, including on redirects.
+fn fetch(url: &str) -> Result<Vec<u8>> {
+    if !url.starts_with("https://") {
+        bail!("Only https:// manifests are accepted.");
+    }
+
+    let limit = file::max_size().to_string();
+    let mut args = vec!["-fsSL", "--proto", "=https", "--proto-redir", "=https"];
+    if file::max_size() > 0 {
+        args.extend(["--max-filesize", &limit]);
+    }
+    let out = Command::new("curl")
+        .args(&args)
+        .arg(url)
+        .output()
+        .context("failed to run curl")?;
+
+    if !out.status.success() {
+        bail!(
+            "Failed to download {url}: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    Ok(out.stdout)
+}
+
+/// A `$when` guard: facts and the patterns they must match.
+#[derive(Debug, Clone)]
+pub(crate) struct Condition(Vec<(String, String)>);
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(|(k, v)| format!("{k}: {v}")).collect();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+impl Condition {
+    fn new(pairs: Vec<(String, String)>) -> Result<Condition> {
+        for (fact, pattern) in &pairs {
+            match fact.as_str() {
+                "macos" => {
+                    version_matches("", pattern)?;
+                }
+                "hostname" | "hardware_uuid" | "arch" | "user" => {}
+                other => {
+                    bail!(
+                        "Unknown fact in $when: {other} (use hostname, macos, hardware_uuid, arch or user)"
+                    )
+                }
+            }
+        }
+        Ok(Condition(pairs))
+    }
+
+    /// Whether every fact matches its pattern.
+    pub(crate) fn holds(&self, facts: &Facts) -> Result<bool> {
+        for (fact, pattern) in &self.0 {
+            let value = facts.get(fact).unwrap_or_default();
+            if fact == "macos" {
+                if !version_matches(value, pattern)? {
+                    return Ok(false);
+                }
+            } else if !glob_regex(&pattern.to_lowercase())?.is_match(&value.to_lowercase()) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Whether the macOS `version` satisfies `spec`: a version optionally preceded by `=`, `!=`, `<`,
+/// `<=`, `>` or `>=`. Only as many components as `spec` gives are compared, so `14` matches 14.5.
+fn version_matches(version: &str, spec: &str) -> Result<bool> {
+    let (op, wanted) = ["!=", ">=", "<=", ">", "<", "="]
+        .iter()
+        .find_map(|op| spec.strip_prefix(op).map(|rest| (*op, rest.trim())))
+        .unwrap_or(("=", spec.trim()));
+    let components = |v: &str| {
+        v.split('.')
+            .map(str::parse::<u64>)
+            .collect::<std::result::Result<Vec<_>, _>>()
+    };
+    let wanted =
+        components(wanted).map_err(|_| anyhow!("Invalid macOS version in $when: {spec}"))?;
+    let Ok(actual) = components(version) else {
+        return Ok(false);
+    };
+    let actual: Vec<u64> = (0..wanted.len())
+        .map(|i| actual.get(i).copied().unwrap_or(0))
+        .collect();
+
+    Ok(match op {
+        "!=" => actual != wanted,
+        ">=" => actual >= wanted,
+        "<=" => actual <= wanted,
+        ">" => actual > wanted,
+        "<" => actual < wanted,
+        _ => actual == wanted,
+    })
+}
+
+/// The domain a manifest names.
+fn domain_of(name: &str) -> Domain {
+    match name {
+        "NSGlobalDomain" | ".GlobalPreferences" | "-g" => Domain::Global,
+        other => Domain::User(other.to_string()),
+    }
+}
+
+/// A string, or the strings of an array of them.
+fn strings(value: &PrefValue) -> Option<Vec<String>> {
+    match value {
+        PrefValue::String(s) => Some(vec![s.clone()]),
+        PrefValue::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                PrefValue::String(s) => Some(s.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+/// The block of domain `name` with the given keys, taking out the `$when`, `$after` and
+/// `$restart` directives.
+fn block(name: &str, pairs: Pairs) -> Result<Block> {
+    let mut entry = Entry {
+        domain: domain_of(name),
+        values: Vec::new(),
+        after: Vec::new(),
+        restart: Vec::new(),
+    };
+    let mut when = None;
+
+    for (key, value) in pairs {
+        match key.as_str() {
+            "$when" => {
+                let bad_when = || {
+                    anyhow!(
+                        "$when of {name} in the manifest must be a dictionary of facts and patterns."
+                    )
+                };
+                let PrefValue::Dictionary(facts) = value else {
+                    return Err(bad_when());
+                };
+                let mut facts = facts
+                    .into_iter()
+                    .map(|(fact, pattern)| match pattern {
+                        PrefValue::String(pattern) => Ok((fact, pattern)),
+                        _ => Err(bad_when()),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                facts.sort();
+                when = Some(Condition::new(facts)?);
+            }
+            "$after" => {
+                entry.after = strings(&value)
+                    .with_context(|| {
+                        format!("$after of {name} in the manifest must be an array of domains.")
+                    })?
+                    .iter()
+                    .map(|d| domain_of(d))
+                    .collect();
+            }
+            "$restart" => {
+                entry.restart = strings(&value).with_context(|| {
+                    format!("$restart of {name} in the manifest must be an array of process names.")
+                })?;
+            }
+            _ => entry.values.push((key, value)),
+        }
+    }
+    Ok(Block { entry, when })
+}
+
+/// The includes and blocks of a single manifest.
+fn parse(data: &[u8]) -> Result<(Vec<String>, Vec<Block>)> {
+    let not_keys = |name: &str| {
+        anyhow!("{name} in the manifest must be a dictionary of keys, or an array of them.")
+    };
+    let mut include = None;
+    let mut root: Vec<(String, Vec<Pairs>)> = Vec::new();
+
+    if data.trim_ascii_start().starts_with(b"{") {
+        let serde_json::Value::Object(object) = serde_json::from_slice(data)? else {
+            bail!("The manifest must be a dictionary of domains.");
+        };
+        for (name, blocks) in object {
+            if name == "include" {
+                include = Some(PrefValue::from_typed_json(&blocks)?);
+                continue;
+            }
+            let blocks = match blocks {
+                serde_json::Value::Array(list) => list,
+                other => vec![other],
+            };
+            let blocks = blocks
+                .into_iter()
+                .map(|block| match block {
+                    serde_json::Value::Object(keys) => keys
+                        .into_iter()
+                        .map(|(k, v)| Ok((k, PrefValue::from_typed_json(&v)?)))
+                        .collect(),
+                    _ => Err(not_keys(&name)),
+                })
+                .collect::<Result<_>>()?;
+            root.push((name, blocks));
+        }
+    } else {
+        let Value::Dictionary(dict) = Value::from_reader(Cursor::new(data))? else {
+            bail!("The manifest must be a dictionary of domains.");
+        };
+        for (name, blocks) in dict {
+            if name == "include" {
+                include = Some(plist_to_prefvalue(&blocks)?);
+                continue;
+            }
+            let blocks = match blocks {
+                Value::Array(list) => list,
+                other => vec![other],
+            };
+            let blocks = blocks
+                .into_iter()
+                .map(|block| match block {
+                    Value::Dictionary(keys) => keys
+                        .into_iter()
+                        .map(|(k, v)| Ok((k, plist_to_prefvalue(&v)?)))
+                        .collect(),
+                    _ => Err(not_keys(&name)),
+                })
+                .collect::<Result<_>>()?;
+            root.push((name, blocks));
+        }
+    }
+
+    let includes = match include {
+        Some(include) => strings(&include)
+            .context("include in the manifest must be an array of paths or URLs.")?,
+        None => Vec::new(),
+    };
+    let blocks = root
+        .into_iter()
+        .flat_map(|(name, blocks)| blocks.into_iter().map(move |pairs| block(&name, pairs)))
+        .collect::<Result<_>>()?;
+    Ok((includes, blocks))
+}
+
+/// The value of a template variable: `env:VAR`, `fact:NAME` or `home`.
+fn variable(name: &str, facts: &Facts) -> Result<String> {
+    if name == "home" {
+        return Ok(dirs::home_dir()
+            .context("Could not determine the home directory.")?
+            .to_string_lossy()
+            .into_owned());
+    }
+    if let Some(var) = name.strip_prefix("env:") {
+        return std::env::var(var).with_context(|| format!("${{{name}}}: {var} is not set."));
+    }
+    if let Some(fact) = name.strip_prefix("fact:") {
+        return facts
+            .get(fact)
+            .map(str::to_string)
+            .with_context(|| format!("${{{name}}}: there is no fact called {fact}."));
+    }
+    if let Some(display) = name.strip_prefix("display:") {
+        return displays::resolve(display)
+            .with_context(|| format!("${{{name}}}: no display called {display} is connected."));
+    }
+    bail!("Unknown template variable ${{{name}}} (use env:VAR, fact:NAME, display:NAME or home).")
+}
+
+/// Expand the template variables in `text`, where `$${` stands for a literal `${`.
+fn expand_template(text: &str, facts: &Facts) -> Result<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            out.push_str(&rest[..start]);
+            out.push('{');
+            rest = &rest[start + 2..];
+            continue;
+        }
+        out.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .with_context(|| format!("Unterminated ${{ in {text}"))?;
+        out.push_str(&variable(&rest[start + 2..start + end], facts)?);
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Expand the template variables in every string and dictionary key within `value`.
+fn interpolate(value: &mut PrefValue, facts: &Facts) -> Result<()> {
+    match value {
+        PrefValue::String(s) if s.contains("${") => *s = expand_template(s, facts)?,
+        PrefValue::Array(items) => {
+            for item in items {
+                interpolate(item, facts)?;
+            }
+        }
+        PrefValue::Dictionary(dict) => {
+            for (key, mut item) in std::mem::take(dict) {
+                interpolate(&mut item, facts)?;
+                let key = if key.contains("${") {
+                    expand_template(&key, facts)?
+                } else {
+                    key
+                };
+                dict.insert(key, item);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Add `from` to `entries`, replacing values of keys already present.
+fn merge(entries: &mut Vec<Entry>, from: Entry) {
+    let Some(into) = entries.iter_mut().find(|e| e.domain == from.domain) else {
+        entries.push(from);
+        return;
+    };
+    for (key, value) in from.values {
+        match into.values.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, old)) => *old = value,
+            None => into.values.push((key, value)),
+        }
+    }
+    for domain in from.after {
+        if !into.after.contains(&domain) {
+            into.after.push(domain);
+        }
+    }
+    for process in from.restart {
+        if !into.restart.contains(&process) {
+            into.restart.push(process);
+        }
+    }
+}
+
+/// Load `source` into `manifest`, its includes first. `stack` holds the manifests including it.
+fn load_into(
+    source: Source,
+    facts: &Facts,
+    stack: &mut Vec<Source>,
+    manifest: &mut Manifest,
+) -> Result<()> {
+    let location = stack.iter().rev().fold(source.to_string(), |acc, s| {
+        format!("{acc} (included from {s})")
+    });
+    if let Some(start) = stack.iter().position(|s| *s == source) {
+        let cycle: Vec<String> = stack[start..].iter().map(Source::to_string).collect();
+        bail!("Manifest include cycle: {} -> {source}", cycle.join(" -> "));
+    }
+
+    let data = source.read().map_err(|e| anyhow!("{location}: {e}"))?;
+    let (includes, blocks) = parse(&data).map_err(|e| anyhow!("{location}: {e}"))?;
+    let digest: String = sha256(&data).iter().map(|b| format!("{b:02x}")).collect();
+
+    stack.push(source.clone());
+    for include in &includes {
+        let included = source
+            .resolve(include)
+            .map_err(|e| anyhow!("{location}: {e}"))?;
+        load_into(included, facts, stack, manifest)?;
+    }
+    stack.pop();
+
+    for Block { mut entry, when } in blocks {
+        match when {
+            Some(when) if !when.holds(facts)? => manifest.skipped.push((entry.domain, when)),
+            _ => {
+                for (key, value) in &mut entry.values {
+                    interpolate(value, facts)
+                        .map_err(|e| anyhow!("{location}: {} {key}: {e}", entry.domain))?;
+                    if key.contains("${") {
+                        *key = expand_template(key, facts)
+                            .map_err(|e| anyhow!("{location}: {} {key}: {e}", entry.domain))?;
+                    }
+                }
+                merge(&mut manifest.entries, entry);
+            }
+        }
+    }
+    manifest.sources.push((source, digest, data.len()));
+    Ok(())
+}
+
+/// Load the manifest at `source` along with everything it includes, leaving out the blocks whose
+/// `$when` doesn't hold on this Mac.
+pub(crate) fn load(source: Source) -> Result<Manifest> {
+    let mut manifest = Manifest {
+        sources: Vec::new(),
+        entries: Vec::new(),
+        skipped: Vec::new(),
+    };
+    load_into(source, &facts::gather(), &mut Vec::new(), &mut manifest)?;
+    Ok(manifest)
+}
@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MIT
+
+//! Keeping drs' backup and export directories out of Spotlight and Time Machine.
+//!
+//! Exports may hold tokens or other sensitive values, so directories drs writes them to can be
+//! excluded from indexing and backups when enabled in the config:
+//!
+//! - `spotlight_exclude` drops a `.metadata_never_index` marker into the directory. drs' own
+//!   backups directory is additionally named `backups.noindex`, which Spotlight always skips.
+//! - `backup_exclude` sets the `com.apple.metadata:com_apple_backup_excludeItem` attribute, the
+//!   same one `tmutil addexclusion` uses.
+
+use anyhow::{Context, Result};
+use std::{fs, path::Path};
+
+use super::{apps::run, config::Config};
+
+const NEVER_INDEX: &str = ".metadata_never_index";
+const BACKUP_EXCLUDE_ATTR: &str = "com.apple.metadata:com_apple_backup_excludeItem";
+/// Marker recording that the attribute was set, so `xattr` isn't spawned on every run.
+const BACKUP_EXCLUDED: &str = ".drs-backup-excluded";
+
+/// Create `dir` if needed and apply the configured exclusions to it.
+pub(crate) fn protect(dir: &Path, config: &Config) -> Result<()> {
+    fs::create_dir_all(dir)?;
+
+    if config.spotlight_exclude {
+        let marker = dir.join(NEVER_INDEX);
+        if !marker.exists() {
+            fs::write(&marker, b"")
+                .with_context(|| format!("failed to create {}", marker.display()))?;
+        }
+    }
+
+    let excluded = dir.join(BACKUP_EXCLUDED);
+    if config.backup_exclude && !excluded.exists() {
+        run(
+            "xattr",
+            &[
+                "-w",
+                BACKUP_EXCLUDE_ATTR,
+                "com.apple.backupd",
+                &dir.to_string_lossy(),
+            ],
+        )
+        .with_context(|| format!("failed to exclude {} from backups", dir.display()))?;
+        fs::write(&excluded, b"")?;
+    }
+
+    Ok(())
+}
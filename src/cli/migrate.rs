@@ -0,0 +1,114 @@
+// SPDX-License-Identifier: MIT
+
+//! `drs migrate-type`: converting the values of matching keys to another type.
+//!
+//! Apps occasionally change the type they expect for a key between versions, e.g. storing
+//! `"42"` where they now read an integer. Every key matching a glob whose value has the source
+//! type and parses as the target type is converted in one transaction.
+
+use anyhow::{Result, bail};
+
+use crate::{Domain, PrefValue, Preferences, Transaction};
+
+use super::util::{glob_regex, parse_bool};
+
+/// The value types `migrate-type` converts between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ValueType {
+    String,
+    Int,
+    Float,
+    Bool,
+}
+
+impl ValueType {
+    pub(crate) fn parse(name: &str) -> Result<Self> {
+        match name {
+            "string" => Ok(ValueType::String),
+            "int" => Ok(ValueType::Int),
+            "float" => Ok(ValueType::Float),
+            "bool" => Ok(ValueType::Bool),
+            other => bail!("Unknown type `{other}`; use string, int, float or bool."),
+        }
+    }
+
+    fn holds(self, value: &PrefValue) -> bool {
+        matches!(
+            (self, value),
+            (ValueType::String, PrefValue::String(_))
+                | (ValueType::Int, PrefValue::Integer(_))
+                | (ValueType::Float, PrefValue::Float(_))
+                | (ValueType::Bool, PrefValue::Boolean(_))
+        )
+    }
+
+    /// `value` converted to this type, if it is representable without loss. `strict_bool` limits
+    /// strings converted to booleans to `true`/`false`.
+    fn convert(self, value: &PrefValue, strict_bool: bool) -> Option<PrefValue> {
+        match (self, value) {
+            (
+                ValueType::String,
+                PrefValue::Integer(_) | PrefValue::Float(_) | PrefValue::Boolean(_),
+            ) => Some(PrefValue::String(value.to_string())),
+            (ValueType::Int, PrefValue::String(s)) => s.trim().parse().ok().map(PrefValue::Integer),
+            (ValueType::Int, PrefValue::Float(f)) if f.fract() == 0.0 => {
+                Some(PrefValue::Integer(*f as i64))
+            }
+            (ValueType::Int, PrefValue::Boolean(b)) => Some(PrefValue::Integer(*b as i64)),
+            (ValueType::Float, PrefValue::String(s)) => s.trim().parse().ok().map(PrefValue::Float),
+            (ValueType::Float, PrefValue::Integer(i)) => Some(PrefValue::Float(*i as f64)),
+            (ValueType::Bool, PrefValue::String(s)) => parse_bool(s.trim(), strict_bool)
+                .ok()
+                .map(PrefValue::Boolean),
+            (ValueType::Bool, PrefValue::Integer(i @ (0 | 1))) => Some(PrefValue::Boolean(*i == 1)),
+            _ => None,
+        }
+    }
+}
+
+/// Convert every key of `domain` matching `glob` from `from` to `to`, or only preview the
+/// conversions with `dry_run`.
+pub(crate) fn migrate_type(
+    domain: &Domain,
+    glob: &str,
+    from: ValueType,
+    to: ValueType,
+    strict_bool: bool,
+    dry_run: bool,
+) -> Result<()> {
+    if from == to {
+        bail!("--from and --to are the same type.");
+    }
+    let regex = glob_regex(glob)?;
+    let PrefValue::Dictionary(dict) = Preferences::read_domain(domain.clone())? else {
+        bail!("{domain} is not a dictionary.");
+    };
+
+    let mut entries: Vec<(String, PrefValue)> = dict
+        .into_iter()
+        .filter(|(k, v)| regex.is_match(k) && from.holds(v))
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut tx = Transaction::new();
+    for (key, old) in &entries {
+        match to.convert(old, strict_bool) {
+            Some(new) => {
+                println!(
+                    "{key}: {old} ({}) -> {new} ({})",
+                    old.get_type(),
+                    new.get_type()
+                );
+                tx.write(domain.clone(), key, new);
+            }
+            None => println!("{key}: skipped, {old} does not convert"),
+        }
+    }
+
+    if tx.is_empty() {
+        println!("No keys to convert.");
+    } else if !dry_run {
+        println!("{}", tx.commit()?.summary());
+    }
+    Ok(())
+}
@@ -0,0 +1,3134 @@
+// SPDX-License-Identifier: MIT
+
+//! CLI definition and argument helpers for defaults-rs.
+//
+// This module is responsible for:
+// - Defining the command-line interface (CLI) structure using clap.
+// - Specifying subcommands, arguments, and their relationships.
+// - Providing helpers for argument parsing and error reporting (if needed).
+//
+// No business logic or backend operations are performed here.
+// All CLI parsing is separated from preferences management and backend details.
+#[cfg(feature = "cli")]
+mod adopt;
+#[cfg(feature = "cli")]
+mod apply;
+#[cfg(feature = "cli")]
+mod apps;
+#[cfg(feature = "cli")]
+mod array;
+#[cfg(feature = "cli")]
+mod bulk;
+#[cfg(feature = "cli")]
+mod changed;
+#[cfg(feature = "cli")]
+mod cleanup;
+#[cfg(feature = "cli")]
+mod config;
+#[cfg(feature = "cli")]
+mod dock;
+#[cfg(feature = "cli")]
+mod enforce;
+#[cfg(feature = "cli")]
+mod handler;
+#[cfg(feature = "cli")]
+mod host;
+#[cfg(feature = "cli")]
+mod hotkeys;
+#[cfg(feature = "cli")]
+mod login_items;
+#[cfg(feature = "cli")]
+mod mackup;
+#[cfg(feature = "cli")]
+mod managed;
+#[cfg(feature = "cli")]
+mod manifest;
+#[cfg(feature = "cli")]
+mod metadata;
+#[cfg(feature = "cli")]
+mod migrate;
+#[cfg(feature = "cli")]
+mod new_domain;
+#[cfg(feature = "cli")]
+mod non_default;
+#[cfg(feature = "cli")]
+mod notes;
+#[cfg(feature = "cli")]
+mod paths;
+#[cfg(feature = "cli")]
+mod pins;
+#[cfg(feature = "cli")]
+mod plist_file;
+#[cfg(feature = "cli")]
+mod progress;
+#[cfg(feature = "cli")]
+pub mod query;
+#[cfg(feature = "cli")]
+mod remote;
+#[cfg(feature = "cli")]
+mod replace;
+#[cfg(feature = "cli")]
+mod screensaver;
+#[cfg(feature = "cli")]
+mod search_list;
+#[cfg(feature = "cli")]
+mod simulator;
+#[cfg(feature = "cli")]
+mod state;
+#[cfg(feature = "cli")]
+pub(crate) mod units;
+#[cfg(feature = "cli")]
+mod util;
+
+#[cfg(feature = "cli")]
+use crate::Domain;
+#[cfg(feature = "cli")]
+use crate::core::file;
+#[cfg(feature = "cli")]
+use crate::prettifier::prettify;
+#[cfg(feature = "cli")]
+use crate::{
+    ApplyReport, DisplayStyle, FindMatch, HashAlgo, KeyPath, NormalizeRule, OnError, PathSegment,
+    PlistFormat, PrefLayer, PrefValue, Preferences, UserScope, UuidMap, WatchOptions, displays,
+    facts, render,
+};
+#[cfg(feature = "cli")]
+use anyhow::{Context, Result, anyhow, bail};
+#[cfg(feature = "cli")]
+use clap::{Arg, ArgMatches, Command};
+#[cfg(feature = "cli")]
+use progress::Progress;
+#[cfg(feature = "cli")]
+use std::collections::HashMap;
+#[cfg(feature = "cli")]
+use std::io::{Cursor, Write};
+#[cfg(feature = "cli")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "cli")]
+use std::time::Duration;
+#[cfg(feature = "cli")]
+use util::{
+    env_flag, expand_value, glob_regex, normalize_path, parse_base64, parse_bool, parse_date,
+    parse_hex, parse_url, parse_uuid, stdin_or,
+};
+
+#[cfg(feature = "cli")]
+pub fn build_cli() -> Command {
+    use clap::{ArgAction, ArgGroup};
+
+    let domain = |req| {
+        let mut a = Arg::new("domain")
+            .help("Domain (e.g. com.example.app / -g / NSGlobalDomain) or a system-recognized plist path")
+            .index(1)
+            .allow_hyphen_values(true);
+        if req {
+            a = a.required(true)
+        }
+        a
+    };
+
+    let key = |req| {
+        let mut a = Arg::new("key").help("Preference key").index(2);
+        if req {
+            a = a.required(true)
+        }
+        a
+    };
+
+    let file = Arg::new("file").long("file").value_name("PATH").help(
+        "Operate on this plist file instead of a domain (positional arguments shift left by one)",
+    );
+
+    let current_host = Arg::new("currentHost")
+        .long("currentHost")
+        .visible_alias("current-host")
+        .help("Use the current host's (ByHost) preferences of the domain")
+        .action(ArgAction::SetTrue)
+        .conflicts_with("file");
+
+    let system = Arg::new("system")
+        .long("system")
+        .help("Use the machine-wide preferences in /Library/Preferences (writing needs root)")
+        .action(ArgAction::SetTrue)
+        .conflicts_with_all(["file", "currentHost"]);
+
+    let container = Arg::new("container")
+        .long("container")
+        .help("Use the plist in the sandboxed app's container, even if one outside it exists")
+        .action(ArgAction::SetTrue)
+        .conflicts_with_all(["file", "currentHost", "system", "group"]);
+
+    let group = Arg::new("group")
+        .long("group")
+        .help("Treat the domain as an App Group suite in ~/Library/Group Containers")
+        .action(ArgAction::SetTrue)
+        .conflicts_with_all(["file", "currentHost", "system"]);
+
+    let normalize = Arg::new("normalize")
+        .long("normalize")
+        .value_name("RULES")
+        .value_delimiter(',')
+        .help("Normalize values first: all, or any of trim-strings, dedup-arrays, normalize-urls");
+
+    let user = Arg::new("user")
+        .long("user")
+        .value_name("NAME|UID")
+        .help("Use another user's preferences, through the plists in their home (needs root)");
+
+    let simulator = Arg::new("simulator")
+        .long("simulator")
+        .value_name("UDID|booted")
+        .help("Use the preferences of an iOS Simulator device")
+        .conflicts_with("user");
+
+    let strict_bool = Arg::new("strict-bool")
+        .long("strict-bool")
+        .help("Only accept true/false as boolean values")
+        .action(ArgAction::SetTrue);
+
+    let front = Arg::new("front")
+        .long("front")
+        .help("Use the frontmost application's domain (positional arguments shift left by one)")
+        .action(ArgAction::SetTrue);
+
+    let app = Arg::new("app")
+        .long("app")
+        .value_name("NAME")
+        .help("Use the domain of this application, by name or path (positional arguments shift left by one)")
+        .conflicts_with_all(["file", "user", "simulator"]);
+
+    let all_domains = Arg::new("all")
+        .long("all")
+        .help("Apply to every domain")
+        .conflicts_with("domain")
+        .required_unless_present("domain")
+        .action(ArgAction::SetTrue);
+
+    let dry_run = Arg::new("dry-run")
+        .long("dry-run")
+        .help("Only show what would be removed")
+        .action(ArgAction::SetTrue);
+
+    let report_output = Arg::new("output")
+        .short('o')
+        .long("output")
+        .value_name("FORMAT")
+        .value_parser(["table", "json"])
+        .default_value("table")
+        .help("Format of the per-key report");
+
+    let handler_target = || {
+        Arg::new("target")
+            .help("Content type (e.g. public.html) or URL scheme (e.g. mailto)")
+            .required(true)
+            .index(1)
+    };
+
+    let no_restart = Arg::new("no-restart")
+        .long("no-restart")
+        .help("Don't restart the Dock afterwards")
+        .action(ArgAction::SetTrue);
+
+    let hotkey_id = || {
+        Arg::new("id")
+            .help("Shortcut ID (see `drs hotkeys list`)")
+            .required(true)
+            .index(1)
+            .value_parser(clap::value_parser!(u32))
+    };
+
+    let array_path = || {
+        Arg::new("keypath")
+            .help("Key path of the array, e.g. persistent-apps or NSToolbar.Items")
+            .required(true)
+            .index(2)
+    };
+
+    let array_index = |id, index| {
+        Arg::new(id)
+            .help("Array index, counting from 0")
+            .required(true)
+            .index(index)
+            .value_parser(clap::value_parser!(usize))
+    };
+
+    let path = Arg::new("path")
+        .help("Path to plist file")
+        .required(true)
+        .index(2);
+
+    Command::new("defaults-rs")
+        .about(env!("CARGO_PKG_DESCRIPTION"))
+        .version(env!("CARGO_PKG_VERSION"))
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .arg(
+            Arg::new("read-only")
+                .long("read-only")
+                .help("Refuse every operation that would modify preferences (also: DRS_READ_ONLY=1)")
+                .global(true)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("progress")
+                .long("progress")
+                .value_name("FORMAT")
+                .value_parser(["json"])
+                .help("Write progress events of long operations to stderr as NDJSON")
+                .global(true),
+        )
+        .arg(
+            Arg::new("max-file-size")
+                .long("max-file-size")
+                .value_name("SIZE")
+                .help("Refuse to load plists larger than this, e.g. 256MB or 0 for no limit (default: 64MiB)")
+                .global(true),
+        )
+        .arg(
+            Arg::new("remote")
+                .long("remote")
+                .value_name("[USER@]HOST")
+                .help("Run the command on another Mac over SSH, which needs drs installed")
+                .global(true),
+        )
+
+        .subcommand(
+            Command::new("read")
+                .about("Read a value")
+                .arg(domain(false))
+                .arg(file.clone().conflicts_with("front"))
+                .arg(&current_host)
+                .arg(&system)
+                .arg(user.clone().conflicts_with_all(["file", "front", "system"]))
+                .arg(simulator.clone().conflicts_with_all(["file", "front", "system"]))
+                .arg(&group)
+                .arg(&container)
+                .arg(
+                    Arg::new("key")
+                        .help("Preference key(s); several keys are read into a dictionary")
+                        .index(2)
+                        .num_args(1..),
+                )
+                .arg(&front)
+                .arg(app.clone().conflicts_with("front"))
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FORMAT")
+                        .value_parser(["apple", "json", "typed"])
+                        .default_value("apple")
+                        .help("Output format; typed shows every value's type unambiguously"),
+                )
+                .arg(
+                    Arg::new("limit")
+                        .long("limit")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .help("Maximum number of entries per page (JSON domain reads only)"),
+                )
+                .arg(
+                    Arg::new("cursor")
+                        .long("cursor")
+                        .value_name("KEY")
+                        .help("Continue after this key, as returned by a previous page (JSON domain reads only)"),
+                )
+                .arg(
+                    Arg::new("human")
+                        .long("human")
+                        .value_name("UNIT")
+                        .value_parser(["seconds", "bytes"])
+                        .conflicts_with("output")
+                        .help("Show a numeric value as a duration or size in the given base unit"),
+                )
+                .arg(
+                    Arg::new("decode-data")
+                        .long("decode-data")
+                        .help("Show data holding JSON or UTF-8 text decoded")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("output-file")
+                        .long("output-file")
+                        .value_name("PATH")
+                        .conflicts_with_all(["output", "human", "limit", "cursor"])
+                        .help("Write the raw bytes of a data value to PATH (- for stdout) instead of printing it"),
+                ),
+        )
+        .subcommand(
+            Command::new("read-type")
+                .about("Show type")
+                .arg(domain(false).required_unless_present("file"))
+                .arg(key(false).required_unless_present("file"))
+                .arg(&file)
+                .arg(user.clone().conflicts_with("file"))
+                .arg(simulator.clone().conflicts_with("file")),
+        )
+        .subcommand(
+            Command::new("search-list")
+                .about("Show the layers a read consults, in order, and what each holds for a key")
+                .arg(domain(true))
+                .arg(key(false)),
+        )
+        .subcommand(
+            Command::new("write")
+                .about("Write value")
+                .arg(domain(false).required_unless_present("file"))
+                .arg(key(false).required_unless_present_any(["front", "app", "file"]))
+                .arg(
+                    Arg::new("value")
+                        .help("Value without a type flag: an integer, float, true/false, yes/no, an old-style plist such as '{ a = 1; }' or a string")
+                        .index(3)
+                        .allow_negative_numbers(true)
+                        .conflicts_with("type"),
+                )
+                .arg(file.clone().conflicts_with("front"))
+                .arg(&current_host)
+                .arg(&system)
+                .arg(user.clone().conflicts_with_all(["file", "front", "system"]))
+                .arg(simulator.clone().conflicts_with_all(["file", "front", "system"]))
+                .arg(&group)
+                .arg(&container)
+                .arg(&front)
+                .arg(app.clone().conflicts_with("front"))
+                .arg(
+                    Arg::new("force")
+                        .short('F')
+                        .long("force")
+                        .help("Disable domain check")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("expand")
+                        .long("expand")
+                        .help("Expand ~, $VARS and relative paths in string values")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("int")
+                        .short('i')
+                        .long("int")
+                        .num_args(1)
+                        .value_name("VALUE")
+                        .help("Write an integer value (units like 2GiB or 5min are accepted)"),
+                )
+                .arg(
+                    Arg::new("float")
+                        .short('f')
+                        .long("float")
+                        .num_args(1)
+                        .value_name("VALUE")
+                        .help("Write a float value (units like 250ms or 1.5MB are accepted)"),
+                )
+                .arg(
+                    Arg::new("bool")
+                        .short('b')
+                        .long("bool")
+                        .num_args(1)
+                        .value_name("VALUE")
+                        .help("Write a boolean value (true/false, yes/no, y/n, on/off, 1/0)"),
+                )
+                .arg(&strict_bool)
+                .arg(
+                    Arg::new("string")
+                        .short('s')
+                        .long("string")
+                        .num_args(1)
+                        .value_name("VALUE")
+                        .help("Write a string value; - reads it from stdin"),
+                )
+                .arg(
+                    Arg::new("array")
+                        .short('a')
+                        .long("array")
+                        .value_name("VALUE")
+                        .num_args(1..)
+                        .help("Write an array value"),
+                )
+                .arg(
+                    Arg::new("array-int")
+                        .long("array-int")
+                        .value_name("VALUE")
+                        .num_args(1..)
+                        .help("Write an array of integers (units are accepted as with --int)"),
+                )
+                .arg(
+                    Arg::new("array-float")
+                        .long("array-float")
+                        .value_name("VALUE")
+                        .num_args(1..)
+                        .help("Write an array of floats (units are accepted as with --float)"),
+                )
+                .arg(
+                    Arg::new("array-bool")
+                        .long("array-bool")
+                        .value_name("VALUE")
+                        .num_args(1..)
+                        .help("Write an array of booleans"),
+                )
+                .arg(
+                    Arg::new("array-add")
+                        .long("array-add")
+                        .value_name("VALUE")
+                        .num_args(1..)
+                        .help("Append values to the array, creating it if missing; values are read as without a type flag, and XML plist fragments as with --plist"),
+                )
+                .arg(
+                    Arg::new("date")
+                        .long("date")
+                        .num_args(1)
+                        .value_name("DATE")
+                        .help("Write a date: ISO 8601 (2024-05-01T12:00:00Z), local 2024-05-01 12:00, 2024-05-01, now or @UNIXTIME"),
+                )
+                .arg(
+                    Arg::new("plist")
+                        .long("plist")
+                        .num_args(1)
+                        .value_name("PLIST")
+                        .help("Write a value given as an XML plist fragment, e.g. '<date>2024-05-01T12:00:00Z</date>', or in the old-style format, e.g. '{ a = 1; }'; - reads it from stdin"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .num_args(1)
+                        .value_name("JSON")
+                        .help("Write a value given as JSON, where {\"$type\": \"date\", \"value\": ...} gives types JSON lacks; - reads it from stdin"),
+                )
+                .arg(
+                    Arg::new("data")
+                        .long("data")
+                        .num_args(1)
+                        .value_name("HEX")
+                        .help("Write data given as hex bytes, e.g. 62706c697374; - reads them from stdin"),
+                )
+                .arg(
+                    Arg::new("base64")
+                        .long("base64")
+                        .num_args(1)
+                        .value_name("BASE64")
+                        .help("Write data given as base64; - reads it from stdin"),
+                )
+                .arg(
+                    Arg::new("url")
+                        .long("url")
+                        .num_args(1)
+                        .value_name("URL")
+                        .help("Write a URL, e.g. https://example.com; absolute paths become file:// URLs"),
+                )
+                .arg(
+                    Arg::new("uuid")
+                        .long("uuid")
+                        .num_args(1)
+                        .value_name("UUID")
+                        .help("Write a UUID, e.g. 01234567-89AB-CDEF-0123-456789ABCDEF"),
+                )
+                .arg(
+                    Arg::new("uid")
+                        .long("uid")
+                        .num_args(1)
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u64))
+                        .help("Write a keyed-archiver UID reference (plist files only)"),
+                )
+                .arg(
+                    Arg::new("dict")
+                        .long("dict")
+                        .value_names(["KEY", "VALUE"])
+                        .num_args(2..)
+                        .allow_hyphen_values(true)
+                        .help("Write a dictionary of key/value pairs; values are strings unless preceded by -int, -float, -bool or -string"),
+                )
+                .arg(
+                    Arg::new("dict-add")
+                        .long("dict-add")
+                        .value_names(["KEY", "VALUE"])
+                        .num_args(2..)
+                        .allow_hyphen_values(true)
+                        .help("Merge key/value pairs, typed as with --dict, into the dictionary, creating it if missing"),
+                )
+                .arg(
+                    Arg::new("number")
+                        .short('n')
+                        .long("number")
+                        .num_args(1)
+                        .value_name("VALUE")
+                        .help("Write a number, keeping the integer/float type of the stored value"),
+                )
+                .arg(
+                    Arg::new("verify")
+                        .long("verify")
+                        .help("Read the value back after writing and report its stored type")
+                        .action(ArgAction::SetTrue),
+                )
+                .group(
+                    ArgGroup::new("type")
+                        .args([
+                            "int",
+                            "float",
+                            "bool",
+                            "string",
+                            "array",
+                            "array-int",
+                            "array-float",
+                            "array-bool",
+                            "array-add",
+                            "date",
+                            "plist",
+                            "json",
+                            "data",
+                            "base64",
+                            "url",
+                            "uuid",
+                            "uid",
+                            "dict",
+                            "dict-add",
+                            "number",
+                        ]),
+                ),
+        )
+        .subcommand(
+            Command::new("delete")
+                .about("Delete key/domain")
+                .arg(domain(false).required_unless_present_any(["file", "app"]))
+                .arg(key(false))
+                .arg(&file)
+                .arg(&current_host)
+                .arg(&system)
+                .arg(user.clone().conflicts_with_all(["file", "system"]))
+                .arg(simulator.clone().conflicts_with_all(["file", "system"]))
+                .arg(&group)
+                .arg(&container)
+                .arg(&app),
+        )
+        .subcommand(
+            Command::new("rename")
+                .about("Rename key")
+                .arg(domain(false).required_unless_present("file"))
+                .arg(file.clone().conflicts_with("pattern"))
+                .arg(user.clone().conflicts_with("file"))
+                .arg(simulator.clone().conflicts_with("file"))
+                .arg(
+                    Arg::new("old_key")
+                        .help("Old/original key name")
+                        .required_unless_present_any(["pattern", "file"])
+                        .index(2),
+                )
+                .arg(
+                    Arg::new("new_key")
+                        .help("New key name")
+                        .required_unless_present_any(["pattern", "file"])
+                        .index(3),
+                )
+                .arg(
+                    Arg::new("pattern")
+                        .long("pattern")
+                        .value_name("s/REGEX/REPLACEMENT/[g]")
+                        .conflicts_with_all(["old_key", "new_key"])
+                        .help("Rename every key matching REGEX; $1, ${name} refer to capture groups"),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .requires("pattern")
+                        .help("Only show which keys would be renamed")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("replace")
+                .about("Replace text in every string inside a value, at any depth")
+                .arg(domain(true))
+                .arg(key(true))
+                .arg(
+                    Arg::new("find")
+                        .long("find")
+                        .value_name("TEXT")
+                        .required(true)
+                        .help("Text to look for"),
+                )
+                .arg(
+                    Arg::new("with")
+                        .long("with")
+                        .value_name("TEXT")
+                        .required(true)
+                        .help("Replacement; with --regex, $1, ${name} refer to capture groups"),
+                )
+                .arg(
+                    Arg::new("regex")
+                        .long("regex")
+                        .help("Treat --find as a regular expression")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Only show what would be replaced")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("rewrite-paths")
+                .about("Rewrite paths under a moved folder, such as an old home folder, in every domain")
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .value_name("FOLDER")
+                        .required(true)
+                        .help("Folder the paths point into now, e.g. /Users/olduser"),
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .value_name("FOLDER")
+                        .required(true)
+                        .help("Folder to point them into instead"),
+                )
+                .arg(
+                    Arg::new("domain")
+                        .long("domain")
+                        .value_name("GLOB")
+                        .help("Only rewrite domains matching GLOB; * and ? are wildcards"),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Only show what would be rewritten")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("migrate-type")
+                .about("Convert the values of matching keys to another type")
+                .arg(domain(true))
+                .arg(
+                    Arg::new("glob")
+                        .help("Keys to convert; * and ? are wildcards")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .value_parser(["string", "int", "float", "bool"])
+                        .required(true)
+                        .help("Only convert values of this type"),
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .value_parser(["string", "int", "float", "bool"])
+                        .required(true)
+                        .help("Type to convert to"),
+                )
+                .arg(dry_run.clone().help("Only show the conversions"))
+                .arg(&strict_bool),
+        )
+        .subcommand(
+            Command::new("hash")
+                .about("Print a canonical content hash of a domain")
+                .arg(domain(true))
+                .arg(&user)
+                .arg(&simulator)
+                .arg(
+                    Arg::new("algo")
+                        .long("algo")
+                        .value_parser(["sha256", "fnv1a64"])
+                        .default_value("sha256")
+                        .help("Hash algorithm"),
+                ),
+        )
+        .subcommand(
+            Command::new("watch")
+                .about("Print changed keys whenever one of the given domains changes")
+                .arg(
+                    Arg::new("domain")
+                        .help("Domains or plist paths to watch; * and ? match several domains")
+                        .required_unless_present("front")
+                        .index(1)
+                        .num_args(1..)
+                        .allow_hyphen_values(true),
+                )
+                .arg(
+                    Arg::new("interval")
+                        .long("interval")
+                        .value_name("MS")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .default_value("100")
+                        .help("Polling interval right after a change"),
+                )
+                .arg(
+                    Arg::new("max-interval")
+                        .long("max-interval")
+                        .value_name("MS")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .default_value("2000")
+                        .help("Polling interval the watcher backs off to while nothing changes"),
+                )
+                .arg(
+                    Arg::new("debounce")
+                        .long("debounce")
+                        .value_name("MS")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("500")
+                        .help("Quiet period after which a burst of changes is reported"),
+                )
+                .arg(
+                    Arg::new("front")
+                        .long("front")
+                        .help("Also watch the frontmost application's domain")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("restore-pins")
+                        .long("restore-pins")
+                        .help("Restore pinned values of the domain when they change")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("changed")
+                .about("List domains and keys modified recently")
+                .arg(
+                    Arg::new("since")
+                        .long("since")
+                        .value_name("DURATION")
+                        .help("How far back to look, e.g. 30m, 2h or 1d")
+                        .required(true),
+                )
+                .arg(domain(false)),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Import plist")
+                .arg(domain(true))
+                .arg(&user)
+                .arg(&simulator)
+                .arg(&path)
+                .arg(&normalize)
+                .arg(&report_output),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Export plist")
+                .arg(domain(false).required_unless_present("all"))
+                .arg(&user)
+                .arg(&simulator)
+                .arg(
+                    path.clone()
+                        .help("Path to plist file, or - for stdout")
+                        .required(false)
+                        .required_unless_present("all"),
+                )
+                .arg(
+                    Arg::new("non-default")
+                        .long("non-default")
+                        .help("Leave out values known to be the macOS defaults")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .help("With --non-default, export every domain keyed by its name (the path becomes the first positional argument)")
+                        .requires("non-default")
+                        .conflicts_with_all(["user", "simulator"])
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(["binary", "xml", "json"])
+                        .help("Plist format, or typed JSON which keeps every value exactly (default: binary for files, xml for stdout)"),
+                )
+                .arg(
+                    Arg::new("force-binary")
+                        .long("force-binary")
+                        .help("Allow writing a binary plist to stdout")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("export-all")
+                .about("Export every domain into a directory")
+                .arg(
+                    Arg::new("dir")
+                        .help("Destination directory")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("jobs")
+                        .short('j')
+                        .long("jobs")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .default_value("4")
+                        .help("Number of domains exported concurrently"),
+                )
+                .arg(
+                    Arg::new("resume")
+                        .long("resume")
+                        .help("Skip domains left unchanged since an interrupted previous run")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("import-all")
+                .about("Import every domain from a directory written by export-all")
+                .arg(
+                    Arg::new("dir")
+                        .help("Directory previously written by `export-all`")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("map-uuid")
+                        .long("map-uuid")
+                        .value_name("FROM=TO")
+                        .action(ArgAction::Append)
+                        .help("Translate a display or host UUID of the exporting Mac to one of this Mac"),
+                )
+                .arg(&report_output),
+        )
+        .subcommand(
+            Command::new("apply")
+                .about("Preview a manifest and apply it after confirmation")
+                .arg(
+                    Arg::new("manifest")
+                        .value_name("FILE")
+                        .help("Plist or JSON manifest mapping domains to keys and values")
+                        .required_unless_present("from-url")
+                        .conflicts_with("from-url")
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("from-url")
+                        .long("from-url")
+                        .value_name("URL")
+                        .help("Download the manifest from this HTTPS URL instead"),
+                )
+                .arg(&normalize)
+                .arg(dry_run.clone().help("Only show the preview"))
+                .arg(
+                    no_restart
+                        .clone()
+                        .help("Don't restart the processes named in $restart afterwards"),
+                )
+                .arg(
+                    Arg::new("on-error")
+                        .long("on-error")
+                        .value_name("POLICY")
+                        .value_parser(["continue", "abort", "rollback"])
+                        .default_value("rollback")
+                        .help("What to do when a change fails: apply the rest anyway, stop, or restore everything changed so far"),
+                ),
+        )
+        .subcommand(
+            Command::new("adopt")
+                .about("Add the current values of keys to a manifest")
+                .arg(domain(true))
+                .arg(
+                    Arg::new("key")
+                        .help("Preference keys to adopt")
+                        .index(2)
+                        .num_args(1..)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("into")
+                        .long("into")
+                        .value_name("FILE")
+                        .help("Plist or JSON manifest to add the values to, created if missing")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("enforce")
+                .about("Keep a manifest applied, re-applying managed keys when they drift")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("run")
+                        .about("Watch the manifest's domains until interrupted")
+                        .arg(
+                            Arg::new("manifest")
+                                .value_name("FILE")
+                                .help("Plist or JSON manifest mapping domains to keys and values")
+                                .required_unless_present("from-url")
+                                .conflicts_with("from-url")
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::new("from-url")
+                                .long("from-url")
+                                .value_name("URL")
+                                .help("Download the manifest from this HTTPS URL instead"),
+                        )
+                        .arg(
+                            Arg::new("cooloff")
+                                .long("cooloff")
+                                .value_name("DURATION")
+                                .default_value("60s")
+                                .help("Leave a key alone for this long if it drifts again right after being enforced"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("status")
+                .about("Summarize how the domains managed by the last apply drifted since"),
+        )
+        .subcommand(
+            Command::new("managed")
+                .about("Settings controlled by configuration profiles (MDM)")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("report")
+                        .about("List every key a configuration profile forces, by domain")
+                        .arg(&report_output),
+                ),
+        )
+        .subcommand(
+            Command::new("facts")
+                .about("Print the facts about this Mac which manifest conditions match against"),
+        )
+        .subcommand(
+            Command::new("displays")
+                .about("List the connected displays and the UUIDs their settings are keyed by")
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FORMAT")
+                        .value_parser(["text", "json"])
+                        .default_value("text")
+                        .help("Output format"),
+                ),
+        )
+        .subcommand(
+            Command::new("domains")
+                .about("List domains")
+                .arg(
+                    Arg::new("no-fuzzy")
+                        .short('n')
+                        .long("no-fuzzy")
+                        .help("Disable fuzzy-picker")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FORMAT")
+                        .value_parser(["json"])
+                        .help("Print every domain with its owning app's name, version and Team ID as JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("sync")
+                .about("Per-application export/restore using mackup application definitions")
+                .subcommand_required(true)
+                .arg(
+                    Arg::new("apps")
+                        .long("apps")
+                        .value_name("DIR")
+                        .default_value("~/.mackup")
+                        .global(true)
+                        .help("Directory containing mackup application definitions (*.cfg)"),
+                )
+                .subcommand(
+                    Command::new("plan")
+                        .about("Show which domains belong to which application"),
+                )
+                .subcommand(
+                    Command::new("export")
+                        .about("Export every application's domains into per-app folders")
+                        .arg(
+                            Arg::new("dir")
+                                .help("Destination directory")
+                                .required(true)
+                                .index(1),
+                        ),
+                )
+                .subcommand(
+                    Command::new("restore")
+                        .about("Import every application's domains from per-app folders")
+                        .arg(
+                            Arg::new("dir")
+                                .help("Directory previously written by `sync export`")
+                                .required(true)
+                                .index(1),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("gc")
+                .about("Clean up leftover temp/lock files from interrupted runs")
+                .arg(
+                    Arg::new("dirs")
+                        .help("Additional directories to clean (e.g. export destinations)")
+                        .value_name("DIR")
+                        .num_args(0..),
+                ),
+        )
+        .subcommand(
+            Command::new("hotkeys")
+                .about("List and edit system keyboard shortcuts (com.apple.symbolichotkeys)")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("List known and configured shortcuts"))
+                .subcommand(
+                    Command::new("enable")
+                        .about("Enable a shortcut")
+                        .arg(hotkey_id()),
+                )
+                .subcommand(
+                    Command::new("disable")
+                        .about("Disable a shortcut")
+                        .arg(hotkey_id()),
+                )
+                .subcommand(
+                    Command::new("set")
+                        .about("Assign a key combination to a shortcut")
+                        .arg(hotkey_id())
+                        .arg(
+                            Arg::new("shortcut")
+                                .help("Key combination, e.g. cmd+shift+4, ctrl+f2 or ctrl+left")
+                                .required(true)
+                                .index(2),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("dock")
+                .about("List, add and remove Dock apps")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("List the apps in the Dock"))
+                .subcommand(
+                    Command::new("add-app")
+                        .about("Add an application to the Dock")
+                        .arg(
+                            Arg::new("app")
+                                .help("Path to the application, e.g. /Applications/Safari.app")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::new("position")
+                                .long("position")
+                                .value_name("N")
+                                .help("Position to add it at, counting from 1 (default: the end)")
+                                .value_parser(clap::value_parser!(usize)),
+                        )
+                        .arg(&no_restart),
+                )
+                .subcommand(
+                    Command::new("remove-app")
+                        .about("Remove an application from the Dock")
+                        .arg(
+                            Arg::new("app")
+                                .help("Position, name, bundle identifier or path of the app")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(&no_restart),
+                ),
+        )
+        .subcommand(
+            Command::new("login-items")
+                .about("List login items and background items with their origins (read-only)"),
+        )
+        .subcommand(
+            Command::new("screensaver")
+                .about("Show and change the screen saver's idle time (stored per Mac)")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("show").about("Show the idle time, screen saver and clock"),
+                )
+                .subcommand(
+                    Command::new("idle")
+                        .about("Set how long the Mac must be idle before the screen saver starts")
+                        .arg(
+                            Arg::new("time")
+                                .help("Duration such as 10m, 1h or 600 (seconds), or never")
+                                .required(true)
+                                .index(1),
+                        ),
+                )
+                .subcommand(
+                    Command::new("clock")
+                        .about("Show or hide the clock on the screen saver")
+                        .arg(
+                            Arg::new("state")
+                                .value_parser(["on", "off"])
+                                .required(true)
+                                .index(1),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("handler")
+                .about("Show and change default applications (LaunchServices handlers)")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("List handler overrides"))
+                .subcommand(
+                    Command::new("get")
+                        .about("Show the handler of a content type or URL scheme")
+                        .arg(handler_target()),
+                )
+                .subcommand(
+                    Command::new("set")
+                        .about("Set the handler of a content type or URL scheme")
+                        .arg(handler_target())
+                        .arg(
+                            Arg::new("bundle_id")
+                                .help("Bundle identifier of the application, e.g. com.google.Chrome")
+                                .required(true)
+                                .index(2),
+                        )
+                        .arg(
+                            Arg::new("no-refresh")
+                                .long("no-refresh")
+                                .help("Don't rebuild the LaunchServices database afterwards")
+                                .action(ArgAction::SetTrue),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("q")
+                .about("Print a single value quickly, formatted by a template (for status bars)")
+                .arg(Arg::new("domain").required(true).index(1))
+                .arg(Arg::new("key").required(true).index(2))
+                .arg(
+                    Arg::new("format")
+                        .short('f')
+                        .long("format")
+                        .value_name("TEMPLATE")
+                        .help("Output template using {value}, {type} and {changed_ago}"),
+                ),
+        )
+        .subcommand(
+            Command::new("clean-window-state")
+                .about("Remove remembered window frames, panel and split view state")
+                .arg(domain(false))
+                .arg(&all_domains)
+                .arg(
+                    Arg::new("saved-state")
+                        .long("saved-state")
+                        .help("Also delete the app's Saved Application State folder")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(&dry_run),
+        )
+        .subcommand(
+            Command::new("scrub-recents")
+                .about("Clear recent documents and places lists")
+                .arg(
+                    Arg::new("app")
+                        .long("app")
+                        .value_name("BUNDLE_ID")
+                        .help("Only clear the recents of this application")
+                        .conflicts_with("all")
+                        .required_unless_present("all"),
+                )
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .help("Clear every application's recents and the system-wide lists")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(&dry_run),
+        )
+        .subcommand(
+            Command::new("note")
+                .about("Attach notes to keys, shown when they are read")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add")
+                        .about("Attach a note to a key, replacing any previous one")
+                        .arg(domain(true))
+                        .arg(key(true))
+                        .arg(Arg::new("note").help("Note text").required(true).index(3)),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Remove the note of a key")
+                        .arg(domain(true))
+                        .arg(key(true)),
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("List notes, optionally of a single domain")
+                        .arg(domain(false)),
+                ),
+        )
+        .subcommand(
+            Command::new("pin")
+                .about("Pin a key's current value and report when it drifts")
+                .args_conflicts_with_subcommands(true)
+                .arg(domain(false))
+                .arg(key(false))
+                .subcommand(
+                    Command::new("check")
+                        .about("Report pinned values which changed")
+                        .arg(domain(false))
+                        .arg(
+                            Arg::new("restore")
+                                .long("restore")
+                                .help("Write the pinned values back")
+                                .action(ArgAction::SetTrue),
+                        ),
+                )
+                .subcommand(Command::new("list").about("List pinned values"))
+                .subcommand(
+                    Command::new("remove")
+                        .about("Unpin a key")
+                        .arg(domain(true))
+                        .arg(key(true)),
+                ),
+        )
+        .subcommand(
+            Command::new("array")
+                .about("Insert, remove and reorder elements of array values")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("insert")
+                        .about("Insert an element before the given index")
+                        .arg(domain(true))
+                        .arg(array_path())
+                        .arg(array_index("index", 3))
+                        .arg(&user)
+                        .arg(&simulator)
+                        .arg(
+                            Arg::new("dict-json")
+                                .long("dict-json")
+                                .value_name("JSON")
+                                .help("Insert a dictionary, given as a JSON object"),
+                        )
+                        .arg(
+                            Arg::new("string")
+                                .short('s')
+                                .long("string")
+                                .value_name("VALUE")
+                                .help("Insert a string"),
+                        )
+                        .arg(
+                            Arg::new("int")
+                                .short('i')
+                                .long("int")
+                                .value_name("VALUE")
+                                .help("Insert an integer"),
+                        )
+                        .arg(
+                            Arg::new("float")
+                                .short('f')
+                                .long("float")
+                                .value_name("VALUE")
+                                .help("Insert a float"),
+                        )
+                        .arg(
+                            Arg::new("bool")
+                                .short('b')
+                                .long("bool")
+                                .value_name("VALUE")
+                                .help("Insert a boolean"),
+                        )
+                        .arg(&strict_bool)
+                        .group(
+                            ArgGroup::new("type")
+                                .args(["dict-json", "string", "int", "float", "bool"])
+                                .required(true),
+                        ),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Remove the element at the given index")
+                        .arg(domain(true))
+                        .arg(array_path())
+                        .arg(array_index("index", 3))
+                        .arg(&user)
+                        .arg(&simulator),
+                )
+                .subcommand(
+                    Command::new("move")
+                        .about("Move an element so it ends up at another index")
+                        .arg(domain(true))
+                        .arg(array_path())
+                        .arg(array_index("from", 3))
+                        .arg(array_index("to", 4))
+                        .arg(&user)
+                        .arg(&simulator),
+                ),
+        )
+        .subcommand(Command::new("doctor").about("Show drs configuration and runtime state"))
+        .subcommand(Command::new("paths").about("Show where drs keeps its files"))
+        .subcommand(
+            Command::new("find")
+                .about("Search all domains")
+                .arg(
+                    Arg::new("word")
+                        .help("Word to search for (case-insensitive), or a query such as \"dock AND autohide -launchpad\"")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("pick")
+                        .short('p')
+                        .long("pick")
+                        .help("Pick a match with the fuzzy-picker and read it")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FORMAT")
+                        .value_parser(["text", "json"])
+                        .default_value("text")
+                        .conflicts_with_all(["pick", "file"])
+                        .help("Output format; json lists every match with its domain, kind, type and value"),
+                )
+                .arg(
+                    Arg::new("limit")
+                        .long("limit")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .conflicts_with_all(["pick", "file"])
+                        .help("Show at most N matches in total (or N domains with --domains-only)"),
+                )
+                .arg(
+                    Arg::new("per-domain")
+                        .long("per-domain")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .conflicts_with_all(["pick", "file", "domains-only"])
+                        .help("Show at most N matches per domain"),
+                )
+                .arg(
+                    Arg::new("domains-only")
+                        .long("domains-only")
+                        .conflicts_with_all(["pick", "file"])
+                        .help("Only list the domains containing matches, with how many each has")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("decode-data")
+                        .long("decode-data")
+                        .help("Show data holding JSON or UTF-8 text decoded")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(file.conflicts_with("pick")),
+        )
+}
+
+/// Rewrites Apple's single-dash `-app NAME`, `-array-add`, `-data`, `-dict` and `-dict-add` to
+/// their `--` spelling, as `defaults` accepts them.
+///
+/// Only tokens in place of an option are rewritten: values of options taking hyphenated values
+/// (such as the pairs after `--dict`) and everything after `--` are left alone.
+#[cfg(feature = "cli")]
+pub fn apple_flags(args: impl IntoIterator<Item = std::ffi::OsString>) -> Vec<std::ffi::OsString> {
+    let mut cli = build_cli();
+    cli.build();
+    let mut cmd = &cli;
+    // Tokens still taken by the previous option, or all of them.
+    let mut values: usize = 0;
+    let mut rest = false;
+
+    let mut out = Vec::new();
+    for (i, arg) in args.into_iter().enumerate() {
+        let token = match arg.to_str() {
+            Some(token) if i > 0 && !rest && values == 0 => token,
+            _ => {
+                values = values.saturating_sub(1);
+                out.push(arg);
+                continue;
+            }
+        };
+        let token = match token {
+            "-app" | "-array-add" | "-data" | "-dict" | "-dict-add" => format!("-{token}"),
+            "--" => {
+                rest = true;
+                token.to_string()
+            }
+            _ => token.to_string(),
+        };
+
+        let option = if let Some(long) = token.strip_prefix("--").filter(|l| !l.contains('=')) {
+            cmd.get_arguments().find(|a| {
+                a.get_long() == Some(long)
+                    || a.get_all_aliases()
+                        .is_some_and(|aliases| aliases.contains(&long))
+            })
+        } else if let Some(short) = token.strip_prefix('-').filter(|s| s.chars().count() == 1) {
+            cmd.get_arguments()
+                .find(|a| a.get_short() == short.chars().next())
+        } else {
+            if !token.starts_with('-')
+                && let Some(sub) = cmd.find_subcommand(&token)
+            {
+                cmd = sub;
+            }
+            None
+        };
+        if let Some(range) = option
+            .filter(|a| a.is_allow_hyphen_values_set())
+            .and_then(|a| a.get_num_args())
+            .filter(|range| range.takes_values())
+        {
+            match range.max_values() {
+                usize::MAX => rest = true,
+                max => values = max,
+            }
+        }
+        out.push(token.into());
+    }
+    out
+}
+
+/// Domains along with the name, version, path and code-signing Team ID of the app owning them.
+#[cfg(feature = "cli")]
+fn domains_json(domains: impl IntoIterator<Item = Domain>) -> serde_json::Value {
+    let apps = apps::installed_apps();
+    let mut domains: Vec<Domain> = domains.into_iter().collect();
+    domains.sort_by_key(|d| d.to_string());
+
+    let entries = domains
+        .iter()
+        .map(|domain| {
+            let app = apps.get(&domain.get_cf_name().to_lowercase()).map(|app| {
+                serde_json::json!({
+                    "name": app.name,
+                    "version": app.version,
+                    "path": app.path.display().to_string(),
+                    "team_id": apps::team_id(&app.path),
+                })
+            });
+            serde_json::json!({ "domain": domain.to_string(), "app": app })
+        })
+        .collect();
+    serde_json::Value::Array(entries)
+}
+
+/// Returns a domain object based on the kind of the argument that is passed.
+#[cfg(feature = "cli")]
+fn parse_domain_or_path(sub_m: &ArgMatches, force: bool) -> Result<Domain> {
+    let domain = sub_m
+        .get_one::<String>("domain")
+        .context("domain argument is required")?;
+    if let Ok(Some(device)) = sub_m.try_get_one::<String>("simulator") {
+        let domain = simulator::domain(device, &parse_domain_str(domain, true)?)?;
+        if !force && !domain.plist_path().is_some_and(|p| p.is_file()) {
+            bail!("Domain '{domain}' not found on simulator {device}!.")
+        }
+        return Ok(domain);
+    }
+    if let Ok(Some(user)) = sub_m.try_get_one::<String>("user") {
+        // Another user's domains aren't listed for us, so look for their plists instead.
+        let user = UserScope::lookup(user)?;
+        let domain = user.domain(&scoped(sub_m, parse_domain_str(domain, true)?))?;
+        if !force && !domain.plist_path().is_some_and(|p| p.is_file()) {
+            bail!("Domain '{domain}' not found for user {}!.", user.name())
+        }
+        return Ok(domain);
+    }
+    if !["currentHost", "system", "group", "container"]
+        .iter()
+        .any(|id| has_flag(sub_m, id))
+    {
+        return parse_domain_str(domain, force);
+    }
+
+    // A domain may only have ByHost, system, App Group or container preferences, so look for those
+    // instead.
+    let scoped = scoped(sub_m, parse_domain_str(domain, true)?);
+    if !force && !scoped.plist_path().is_some_and(|p| p.is_file()) {
+        bail!("Domain '{scoped}' not found!.")
+    }
+    Ok(scoped)
+}
+
+/// The domain picked with `--front` or `--app`, which shift the positional arguments left by one.
+#[cfg(feature = "cli")]
+fn app_domain(sub_m: &ArgMatches) -> Result<Option<Domain>> {
+    let id = if has_flag(sub_m, "front") {
+        apps::frontmost_bundle_id()?
+    } else if let Ok(Some(app)) = sub_m.try_get_one::<String>("app") {
+        apps::bundle_id_for(app)?
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(scoped(sub_m, Domain::User(id))))
+}
+
+/// Whether the flag `id` was passed to a subcommand which supports it.
+#[cfg(feature = "cli")]
+fn has_flag(sub_m: &ArgMatches, id: &str) -> bool {
+    matches!(sub_m.try_get_one::<bool>(id), Ok(Some(true)))
+}
+
+/// `domain`, or its counterpart with `--currentHost`, `--system` or `--group`.
+#[cfg(feature = "cli")]
+fn scoped(sub_m: &ArgMatches, domain: Domain) -> Domain {
+    if has_flag(sub_m, "currentHost") {
+        domain.current_host()
+    } else if has_flag(sub_m, "system") {
+        domain.system()
+    } else if has_flag(sub_m, "group") {
+        Domain::Group(domain.get_cf_name())
+    } else if has_flag(sub_m, "container") {
+        Domain::Container(domain.get_cf_name())
+    } else {
+        domain
+    }
+}
+
+/// The domains `watch` was given, expanding `*`/`?` patterns against every known domain, and the
+/// frontmost application's domain with `--front`.
+#[cfg(feature = "cli")]
+fn watch_targets(sub_m: &ArgMatches) -> Result<Vec<Domain>> {
+    let mut domains: Vec<Domain> = app_domain(sub_m)?.into_iter().collect();
+    let mut known: Option<Vec<Domain>> = None;
+
+    for arg in sub_m.get_many::<String>("domain").into_iter().flatten() {
+        if !arg.contains(['*', '?']) {
+            domains.push(parse_domain_str(arg, false)?);
+            continue;
+        }
+
+        let known = match &mut known {
+            Some(known) => known,
+            None => known.insert(cleanup::target_domains(None, true)?),
+        };
+        let pattern = glob_regex(arg)?;
+        let matched: Vec<Domain> = known
+            .iter()
+            .filter(|d| pattern.is_match(&d.to_string()) || pattern.is_match(&d.get_cf_name()))
+            .cloned()
+            .collect();
+        if matched.is_empty() {
+            bail!("No domain matches {arg}");
+        }
+        domains.extend(matched);
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    domains.retain(|d| seen.insert(d.clone()));
+    Ok(domains)
+}
+
+/// Returns a domain object for a domain name or plist path.
+#[cfg(feature = "cli")]
+fn parse_domain_str(domain: &str, force: bool) -> Result<Domain> {
+    let home_dir = dirs::home_dir().ok_or_else(|| anyhow!("could not resolve home directory"))?;
+    let mut domain = domain.to_string();
+
+    // filepath check
+    if let Ok(path) = normalize_path(&domain).and_then(|p| Ok(p.canonicalize()?))
+        && path.is_file()
+        && (path.starts_with(format!(
+            "{}/Library/Preferences/",
+            home_dir.to_string_lossy()
+        )) || path.starts_with("/Library/Preferences/")
+            || path.starts_with("/System/Library/Preferences/"))
+    {
+        domain = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow!("could not get file stem"))?
+            .to_string();
+    }
+
+    // domain check
+    match domain.strip_suffix(".plist").unwrap_or(&domain) {
+        "-g" | "NSGlobalDomain" | "-globalDomain" | ".GlobalPreferences" => Ok(Domain::Global),
+        other => {
+            if other.contains("..")
+                || other.contains('/')
+                || other.contains('\\')
+                || !other
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '.' || c == '_' || c == '-')
+            {
+                bail!("Invalid domain or plist path: {other}");
+            }
+
+            let user = Domain::User(other.to_string());
+
+            // Listing every domain is slow, so only fall back to it for domains without a plist
+            // in the user's preferences folder.
+            if !force
+                && !user.plist_path().is_some_and(|p| p.is_file())
+                && !Preferences::list_domains()?
+                    .iter()
+                    .any(|dom| dom.to_string() == other)
+            {
+                bail!("Domain '{domain}' not found!.")
+            }
+
+            Ok(user)
+        }
+    }
+}
+
+/// Extract the proper PrefValue to be writtem from the passed typeflag.
+///
+/// This is primarily used in the write command for determining types.
+#[cfg(feature = "cli")]
+fn extract_prefvalue_from_args(sub_m: &ArgMatches) -> Result<PrefValue> {
+    if let Some(val) = sub_m.get_one::<String>("int") {
+        Ok(PrefValue::Integer(units::parse_int(val)?))
+    } else if let Some(val) = sub_m.get_one::<String>("float") {
+        Ok(PrefValue::Float(units::parse_float(val)?))
+    } else if let Some(val) = sub_m.get_one::<String>("bool") {
+        Ok(PrefValue::Boolean(parse_bool(
+            val,
+            sub_m.get_flag("strict-bool"),
+        )?))
+    } else if let Some(val) = sub_m.get_many::<String>("array") {
+        let val: Vec<PrefValue> = val
+            .into_iter()
+            .map(|f| Ok(PrefValue::String(string_arg(sub_m, f)?)))
+            .collect::<Result<_>>()?;
+
+        Ok(PrefValue::Array(val))
+    } else if let Some(val) = sub_m.get_many::<String>("array-int") {
+        Ok(PrefValue::Array(
+            val.map(|v| Ok(PrefValue::Integer(units::parse_int(v)?)))
+                .collect::<Result<_>>()?,
+        ))
+    } else if let Some(val) = sub_m.get_many::<String>("array-float") {
+        Ok(PrefValue::Array(
+            val.map(|v| Ok(PrefValue::Float(units::parse_float(v)?)))
+                .collect::<Result<_>>()?,
+        ))
+    } else if let Some(val) = sub_m.get_many::<String>("array-bool") {
+        let strict = sub_m.get_flag("strict-bool");
+        Ok(PrefValue::Array(
+            val.map(|v| Ok(PrefValue::Boolean(parse_bool(v, strict)?)))
+                .collect::<Result<_>>()?,
+        ))
+    } else if let Some(val) = sub_m.get_one::<String>("string") {
+        Ok(PrefValue::String(string_arg(sub_m, &stdin_or(val)?)?))
+    } else if let Some(val) = sub_m.get_one::<String>("date") {
+        Ok(PrefValue::Date(parse_date(val)?))
+    } else if let Some(val) = sub_m.get_one::<String>("plist") {
+        let text = stdin_or(val)?;
+        if text.trim_start().starts_with('<') {
+            PrefValue::from_plist_fragment(&text)
+        } else {
+            PrefValue::from_openstep(&text)
+        }
+    } else if let Some(val) = sub_m.get_one::<String>("json") {
+        let json = serde_json::from_str(&stdin_or(val)?).context("invalid JSON value")?;
+        PrefValue::from_typed_json(&json)
+    } else if let Some(val) = sub_m.get_one::<String>("data") {
+        Ok(PrefValue::Data(parse_hex(&stdin_or(val)?)?.into()))
+    } else if let Some(val) = sub_m.get_one::<String>("base64") {
+        Ok(PrefValue::Data(parse_base64(&stdin_or(val)?)?.into()))
+    } else if let Some(val) = sub_m.get_one::<String>("url") {
+        Ok(PrefValue::Url(parse_url(&string_arg(sub_m, val)?)?))
+    } else if let Some(val) = sub_m.get_one::<String>("uuid") {
+        Ok(PrefValue::Uuid(parse_uuid(val)?))
+    } else if let Some(&val) = sub_m.get_one::<u64>("uid") {
+        Ok(PrefValue::Uid(val))
+    } else if sub_m.contains_id("dict") {
+        Ok(PrefValue::Dictionary(dict_pairs(sub_m, "dict")?))
+    } else if let Some(raw) = untyped_value(sub_m) {
+        untyped_literal(sub_m, raw)
+    } else {
+        bail!(
+            "You must give a value or specify one of --int, --float, --bool, --array, --array-int, --array-float, --array-bool, --date, --data, --base64, --json, --url, --uuid, --uid, --dict, --plist or --string for the value type."
+        )
+    }
+}
+
+/// A value given without a type flag: old-style (OpenStep) dictionaries and arrays are parsed,
+/// other values inferred with [`infer_value`].
+#[cfg(feature = "cli")]
+fn untyped_literal(sub_m: &ArgMatches, raw: &str) -> Result<PrefValue> {
+    if raw.trim_start().starts_with(['{', '(']) {
+        return PrefValue::from_openstep(raw);
+    }
+    Ok(match infer_value(raw) {
+        PrefValue::String(_) => PrefValue::String(string_arg(sub_m, raw)?),
+        inferred => inferred,
+    })
+}
+
+/// The values passed to `--array-add`: XML plist fragments as with `--plist`, anything else as
+/// without a type flag.
+#[cfg(feature = "cli")]
+fn array_add_values(sub_m: &ArgMatches) -> Result<Vec<PrefValue>> {
+    sub_m
+        .get_many::<String>("array-add")
+        .into_iter()
+        .flatten()
+        .map(|v| {
+            if v.trim_start().starts_with('<') {
+                PrefValue::from_plist_fragment(v)
+            } else {
+                untyped_literal(sub_m, v)
+            }
+        })
+        .collect()
+}
+
+/// The value given without a type flag. With `--app`, `--front` or `--file` there is no domain
+/// argument, so it is the second positional argument rather than the third.
+#[cfg(feature = "cli")]
+fn untyped_value(sub_m: &ArgMatches) -> Option<&str> {
+    let shifted = has_flag(sub_m, "front")
+        || matches!(sub_m.try_get_one::<String>("app"), Ok(Some(_)))
+        || matches!(sub_m.try_get_one::<String>("file"), Ok(Some(_)));
+    let id = if shifted { "key" } else { "value" };
+    sub_m
+        .try_get_one::<String>(id)
+        .ok()
+        .flatten()
+        .map(String::as_str)
+}
+
+/// Infers the type of a value given without a type flag: integers, floats and booleans as
+/// [`parse_bool`] accepts them are written as such, and anything else as a string.
+#[cfg(feature = "cli")]
+fn infer_value(raw: &str) -> PrefValue {
+    if let Ok(i) = raw.parse::<i64>() {
+        return PrefValue::Integer(i);
+    }
+    if raw.contains(|c: char| c.is_ascii_digit())
+        && let Ok(f) = raw.parse::<f64>()
+        && f.is_finite()
+    {
+        return PrefValue::Float(f);
+    }
+    match parse_bool(raw, false) {
+        Ok(b) => PrefValue::Boolean(b),
+        Err(_) => PrefValue::String(raw.to_string()),
+    }
+}
+
+/// The key/value pairs passed to `--dict` or `--dict-add`.
+///
+/// As with `defaults`, a value is a string unless preceded by `-int`, `-float`, `-bool` or
+/// `-string` (or their `-integer`/`-boolean` spellings).
+#[cfg(feature = "cli")]
+fn dict_pairs(sub_m: &ArgMatches, id: &str) -> Result<HashMap<String, PrefValue>> {
+    let mut tokens = sub_m.get_many::<String>(id).into_iter().flatten();
+    let mut dict = HashMap::new();
+    while let Some(key) = tokens.next() {
+        let missing = || anyhow!("--{id} takes key/value pairs, but {key} has no value.");
+        let token = tokens.next().ok_or_else(missing)?;
+        let value = match token.as_str() {
+            "-int" | "-integer" => {
+                PrefValue::Integer(units::parse_int(tokens.next().ok_or_else(missing)?)?)
+            }
+            "-float" => PrefValue::Float(units::parse_float(tokens.next().ok_or_else(missing)?)?),
+            "-bool" | "-boolean" => PrefValue::Boolean(parse_bool(
+                tokens.next().ok_or_else(missing)?,
+                sub_m.get_flag("strict-bool"),
+            )?),
+            "-string" => PrefValue::String(string_arg(sub_m, tokens.next().ok_or_else(missing)?)?),
+            _ => PrefValue::String(string_arg(sub_m, token)?),
+        };
+        dict.insert(key.clone(), value);
+    }
+    Ok(dict)
+}
+
+/// Parses a number for `--number`, keeping the integer/float type of the `existing` value.
+///
+/// Without an existing numeric value, integers are preferred over floats.
+#[cfg(feature = "cli")]
+fn number_like(existing: Option<&PrefValue>, raw: &str) -> Result<PrefValue> {
+    units::ensure_plain_number(raw)?;
+    let float = || {
+        raw.parse::<f64>()
+            .map_err(|e| anyhow!("Failed to parse number: {e}"))
+    };
+
+    match existing {
+        Some(PrefValue::Float(_)) => Ok(PrefValue::Float(float()?)),
+        Some(PrefValue::Uid(_)) => raw.parse::<u64>().map(PrefValue::Uid).map_err(|_| {
+            anyhow!("{raw} cannot be stored as a UID, which is the type of the current value.")
+        }),
+        Some(PrefValue::Integer(_)) => match raw.parse::<i64>() {
+            Ok(i) => Ok(PrefValue::Integer(i)),
+            Err(_) => {
+                let f = float()?;
+                if f.fract() != 0.0 || f < i64::MIN as f64 || f > i64::MAX as f64 {
+                    bail!(
+                        "{raw} cannot be stored as an integer, which is the type of the current value."
+                    )
+                }
+                Ok(PrefValue::Integer(f as i64))
+            }
+        },
+        _ => match raw.parse::<i64>() {
+            Ok(i) => Ok(PrefValue::Integer(i)),
+            Err(_) => Ok(PrefValue::Float(float()?)),
+        },
+    }
+}
+
+/// Parses a sed-style `s/REGEX/REPLACEMENT/[g]` pattern into its regex, replacement and
+/// whether to replace globally.
+#[doc(hidden)]
+#[cfg(feature = "cli")]
+pub fn parse_rename_pattern(pattern: &str) -> Result<(regex::Regex, String, bool)> {
+    let mut chars = pattern.chars();
+    let (Some('s'), Some(delim)) = (chars.next(), chars.next()) else {
+        bail!("Expected a pattern like s/REGEX/REPLACEMENT/, got: {pattern}")
+    };
+
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.clone().next() == Some(delim) => {
+                chars.next();
+                part.push(delim);
+            }
+            c if c == delim => parts.push(std::mem::take(&mut part)),
+            c => part.push(c),
+        }
+    }
+    parts.push(part);
+
+    let [regex, replacement, flags] = parts.as_slice() else {
+        bail!("Expected a pattern like s/REGEX/REPLACEMENT/, got: {pattern}")
+    };
+    let global = match flags.as_str() {
+        "" => false,
+        "g" => true,
+        other => bail!("Unsupported pattern flags: {other}"),
+    };
+    let regex = regex::Regex::new(regex).context("invalid pattern regex")?;
+
+    Ok((regex, replacement.clone(), global))
+}
+
+/// Computes the renames described by a sed-style `s/REGEX/REPLACEMENT/[g]` pattern.
+#[cfg(feature = "cli")]
+fn rename_plan(domain: &Domain, pattern: &str) -> Result<Vec<(String, String)>> {
+    let (regex, replacement, global) = parse_rename_pattern(pattern)?;
+
+    let PrefValue::Dictionary(dict) = Preferences::read_domain(domain.clone())? else {
+        bail!("Cannot rename keys in a domain which is not a dictionary.")
+    };
+    let mut keys: Vec<String> = dict.into_keys().collect();
+    keys.sort();
+
+    Ok(keys
+        .into_iter()
+        .filter_map(|key| {
+            let new = if global {
+                regex.replace_all(&key, replacement.as_str())
+            } else {
+                regex.replace(&key, replacement.as_str())
+            }
+            .into_owned();
+            (new != key).then_some((key, new))
+        })
+        .collect())
+}
+
+/// Returns the key path a key argument refers to, treating unparsable keys as a single key.
+#[cfg(feature = "cli")]
+fn key_path_of(key: &str) -> KeyPath {
+    KeyPath::parse(key).unwrap_or_else(|_| KeyPath::default().key(key))
+}
+
+/// `key` with every `${display:NAME}` replaced by the UUID of that display, so per-display
+/// settings can be read as e.g. `DisplayPrefs.${display:builtin}`.
+#[cfg(feature = "cli")]
+fn display_key(key: &str) -> Result<String> {
+    let mut out = String::with_capacity(key.len());
+    let mut rest = key;
+    while let Some(start) = rest.find("${display:") {
+        let end = rest[start..]
+            .find('}')
+            .with_context(|| format!("Unterminated ${{ in {key}"))?;
+        let name = &rest[start + "${display:".len()..start + end];
+        out.push_str(&rest[..start]);
+        out.push_str(
+            &displays::resolve(name)
+                .with_context(|| format!("No display called {name} is connected."))?,
+        );
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Explains why a value written to `domain` isn't seen by a sandboxed app, which reads its
+/// container plist: a plist outside the container exists too, so CFPreferences wrote there, or
+/// `--container` was given but the value is missing from the container plist.
+#[cfg(feature = "cli")]
+fn container_redirect(domain: &Domain, key: &str) -> Option<String> {
+    let sandboxed = domain.sandbox_plist()?;
+    let top = match key_path_of(key).segments().first() {
+        Some(PathSegment::Key(k)) => k.clone(),
+        _ => key.to_string(),
+    };
+    let held = crate::core::plist_file::read_pref(&sandboxed, &top).ok();
+    match domain {
+        Domain::Container(_) if held.is_none() => Some(format!(
+            "Wrote {key}, but it is missing from {}; cfprefsd may not have written it yet.",
+            sandboxed.display()
+        )),
+        Domain::User(name) if !domain.in_container() => {
+            let seen = held.map_or("unset".to_string(), |v| v.to_string());
+            Some(format!(
+                "Wrote {key} to {}, but {name} is sandboxed and reads {}, where {top} is {seen}. Use --container to write there.",
+                domain.plist_path()?.display(),
+                sandboxed.display()
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Reads a key, reporting keys which aren't stored at any layer as "not set (app default)".
+#[cfg(feature = "cli")]
+fn read_or_explain(domain: &Domain, key: &str) -> Result<PrefValue> {
+    Preferences::read(domain.clone(), key).map_err(|e| {
+        let top = match key_path_of(key).segments().first() {
+            Some(PathSegment::Key(k)) => k.clone(),
+            _ => key.to_string(),
+        };
+        if Preferences::is_set(domain.clone(), &top) {
+            e
+        } else {
+            anyhow!("{key} in {domain}: not set (app default)")
+        }
+    })
+}
+
+/// Returns a string value to be written, expanding it if `--expand` was passed.
+#[cfg(feature = "cli")]
+fn string_arg(sub_m: &ArgMatches, val: &str) -> Result<String> {
+    if sub_m.get_flag("expand") {
+        expand_value(val)
+    } else {
+        Ok(val.to_string())
+    }
+}
+
+/// Returns a required argument from the CLI.
+#[cfg(feature = "cli")]
+fn get_required_arg<'a>(sub_m: &'a clap::ArgMatches, name: &str) -> &'a str {
+    sub_m
+        .get_one::<String>(name)
+        .map(String::as_str)
+        .unwrap_or_else(|| {
+            eprintln!("Error: {name} required");
+            std::process::exit(1);
+        })
+}
+
+/// Fuzzy-picking helper for the CLI.
+#[cfg(feature = "cli")]
+fn pick_one(prompt: &str, items: &[String]) -> Result<Option<String>> {
+    use skim::prelude::*;
+
+    let item_reader = SkimItemReader::default();
+    let skim_items = item_reader.of_bufread(Cursor::new(items.join("\n")));
+
+    let options = SkimOptionsBuilder::default()
+        .prompt(prompt.to_string())
+        .color(Some("bw".to_string()))
+        .case(CaseMatching::Smart)
+        .multi(false)
+        .build()
+        .context("Failed to build fuzzy-picker options; internal error in pick_one().")?;
+
+    let out = Skim::run_with(&options, Some(skim_items));
+
+    let out = match out {
+        Some(o) if !o.is_abort => o,
+        _ => return Ok(None),
+    };
+
+    Ok(out
+        .selected_items
+        .first()
+        .map(|item| item.output().to_string()))
+}
+
+/// Handles the `sync` subcommand family.
+#[cfg(feature = "cli")]
+fn handle_sync(sub_m: &ArgMatches, config: &config::Config) -> Result<()> {
+    let apps = mackup::load_dir(&normalize_path(get_required_arg(sub_m, "apps"))?)?;
+    let existing: Vec<String> = Preferences::list_domains()?
+        .iter()
+        .map(|d| d.to_string())
+        .collect();
+
+    match sub_m.subcommand() {
+        Some(("plan", _)) => {
+            for app in apps {
+                println!("{}:", app.name);
+                for domain in app.domains {
+                    let state = if existing.contains(&domain) {
+                        "present"
+                    } else {
+                        "missing"
+                    };
+                    println!("    {domain} ({state})");
+                }
+            }
+            Ok(())
+        }
+        Some(("export", sub_m)) => {
+            let dir = normalize_path(get_required_arg(sub_m, "dir"))?;
+            metadata::protect(&dir, config)?;
+
+            let total = apps
+                .iter()
+                .flat_map(|app| &app.domains)
+                .filter(|d| existing.contains(*d))
+                .count();
+            let events = Progress::start("sync-export", total);
+            for app in apps {
+                let app_dir = dir.join(&app.name);
+                for domain in app.domains.iter().filter(|d| existing.contains(d)) {
+                    std::fs::create_dir_all(&app_dir)?;
+                    let path = app_dir.join(format!("{domain}.plist"));
+                    let exported =
+                        Preferences::export(Domain::User(domain.clone()), &path.to_string_lossy());
+                    events.step(domain, exported.is_ok());
+                    exported?;
+                    println!("{}: exported {domain}", app.name);
+                }
+            }
+            events.finish();
+            Ok(())
+        }
+        Some(("restore", sub_m)) => {
+            let dir = normalize_path(get_required_arg(sub_m, "dir"))?;
+
+            let files: Vec<(&str, &String, PathBuf)> = apps
+                .iter()
+                .flat_map(|app| {
+                    app.domains.iter().map(|domain| {
+                        let path = dir.join(&app.name).join(format!("{domain}.plist"));
+                        (app.name.as_str(), domain, path)
+                    })
+                })
+                .filter(|(_, _, path)| path.is_file())
+                .collect();
+            let events = Progress::start("sync-restore", files.len());
+            for (app, domain, path) in files {
+                let restored =
+                    Preferences::import(Domain::User(domain.clone()), &path.to_string_lossy())
+                        .and_then(ApplyReport::into_result);
+                events.step(domain, restored.is_ok());
+                restored?;
+                println!("{app}: restored {domain}");
+            }
+            events.finish();
+            Ok(())
+        }
+        _ => bail!("Not a proper sync subcommand."),
+    }
+}
+
+/// Print an apply report in the requested format, failing if any key could not be applied.
+#[cfg(feature = "cli")]
+fn print_report(report: ApplyReport, sub_m: &ArgMatches) -> Result<()> {
+    match sub_m.get_one::<String>("output").map(String::as_str) {
+        Some("json") => println!("{}", report.to_json()),
+        _ => println!("{}", report.table()),
+    }
+    report.into_result().map(|_| ())
+}
+
+/// The manifest given as a file or with `--from-url`.
+#[cfg(feature = "cli")]
+fn manifest_source(sub_m: &ArgMatches) -> Result<manifest::Source> {
+    match sub_m.get_one::<String>("from-url") {
+        Some(url) => Ok(manifest::Source::Url(url.clone())),
+        None => manifest::Source::file(&normalize_path(get_required_arg(sub_m, "manifest"))?),
+    }
+}
+
+/// The rules passed to `--normalize`, if any.
+#[cfg(feature = "cli")]
+fn normalize_rules(sub_m: &ArgMatches) -> Result<Vec<NormalizeRule>> {
+    let mut rules = Vec::new();
+    for name in sub_m.get_many::<String>("normalize").into_iter().flatten() {
+        match name.as_str() {
+            "all" => rules.extend(NormalizeRule::ALL),
+            name => rules.push(NormalizeRule::parse(name)?),
+        }
+    }
+    Ok(rules)
+}
+
+/// Function to handle subcommand runs.
+#[cfg(feature = "cli")]
+pub fn handle_subcommand(cmd: &str, sub_m: &ArgMatches) -> Result<()> {
+    if let Some(host) = sub_m.get_one::<String>("remote") {
+        return remote::run(host);
+    }
+    if sub_m.get_flag("read-only") || env_flag("DRS_READ_ONLY") {
+        Preferences::set_read_only(true);
+    }
+    progress::set_json(
+        sub_m
+            .get_one::<String>("progress")
+            .is_some_and(|p| p == "json"),
+    );
+
+    if let Some(size) = sub_m.get_one::<String>("max-file-size") {
+        let bytes = units::parse_int(size)?;
+        if bytes < 0 {
+            bail!("--max-file-size must not be negative.");
+        }
+        Preferences::set_max_file_size(bytes as u64);
+    }
+
+    if has_flag(sub_m, "decode-data") {
+        render::register_renderer("*", "*", Box::new(render::decode_data));
+    }
+
+    if let Ok(Some(file)) = sub_m.try_get_one::<String>("file") {
+        return plist_file::handle(cmd, sub_m, &normalize_path(file)?);
+    }
+
+    let config = config::Config::load()?;
+    if config.backup_on_write {
+        let dir = paths::backup_dir(config.spotlight_exclude)?;
+        metadata::protect(&dir, &config)?;
+        Preferences::set_backup_dir(Some(dir));
+    }
+    Preferences::set_cache_capacity(config.cache_capacity);
+
+    match cmd {
+        "apply" => apply::run(
+            manifest_source(sub_m)?,
+            &normalize_rules(sub_m)?,
+            sub_m.get_flag("dry-run"),
+            !sub_m.get_flag("no-restart"),
+            OnError::parse(get_required_arg(sub_m, "on-error"))?,
+        ),
+        "adopt" => {
+            let keys: Vec<&str> = sub_m
+                .get_many::<String>("key")
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+                .collect();
+            adopt::run(
+                &parse_domain_or_path(sub_m, false)?,
+                &keys,
+                &normalize_path(get_required_arg(sub_m, "into"))?,
+            )
+        }
+        "enforce" => match sub_m.subcommand() {
+            Some(("run", m)) => {
+                let cooloff = units::parse_float(get_required_arg(m, "cooloff"))?;
+                if !cooloff.is_finite() || cooloff < 0.0 {
+                    bail!("--cooloff must be a positive duration.");
+                }
+                enforce::run(manifest_source(m)?, Duration::from_secs_f64(cooloff))
+            }
+            _ => bail!("Not a proper enforce subcommand."),
+        },
+        "status" => state::status(),
+        "managed" => match sub_m.subcommand() {
+            Some(("report", m)) => {
+                managed::report(m.get_one::<String>("output").is_some_and(|o| o == "json"))
+            }
+            _ => bail!("Not a proper managed subcommand."),
+        },
+        "facts" => {
+            println!("{:#}", facts::gather().to_json());
+            Ok(())
+        }
+        "displays" => {
+            let online = displays::online();
+            if sub_m
+                .get_one::<String>("output")
+                .is_some_and(|o| o == "json")
+            {
+                let items: Vec<serde_json::Value> = online
+                    .iter()
+                    .map(|d| {
+                        serde_json::json!({
+                            "uuid": d.uuid,
+                            "name": d.name,
+                            "builtin": d.builtin,
+                            "main": d.main,
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::Value::Array(items));
+                return Ok(());
+            }
+            if online.is_empty() {
+                println!("No displays are connected.");
+            }
+            for d in &online {
+                let roles: Vec<&str> = [(d.builtin, "built-in"), (d.main, "main")]
+                    .into_iter()
+                    .filter_map(|(is, role)| is.then_some(role))
+                    .collect();
+                if roles.is_empty() {
+                    println!("{}  {}", d.uuid, d.name);
+                } else {
+                    println!("{}  {} ({})", d.uuid, d.name, roles.join(", "));
+                }
+            }
+            Ok(())
+        }
+        "domains" => {
+            let domains = Preferences::list_domains()?;
+            let domains_str: Vec<String> = domains.iter().map(|f| f.to_string()).collect();
+
+            if sub_m.contains_id("output") {
+                println!("{}", domains_json(domains));
+            } else if sub_m.get_flag("no-fuzzy") {
+                for dom in domains {
+                    println!("{dom}");
+                }
+            } else {
+                let picker = pick_one(
+                    "Viewing list of domains. Use arrow keys to navigate: ",
+                    &domains_str,
+                )?;
+
+                if let Some(picked_domain) = picker {
+                    println!("Domain: {picked_domain} (is {})", {
+                        match domains
+                            .iter()
+                            .find(|d| d.to_string() == picked_domain)
+                            .context("Domain-type checker failed.")?
+                        {
+                            Domain::User(_) => "user domain",
+                            Domain::Global => "global domain",
+                            Domain::ByHost(_) => "current-host domain",
+                            Domain::System(_) => "system domain",
+                            Domain::Container(_) => "container domain",
+                            Domain::Group(_) => "app group domain",
+                            Domain::Path(_) => "plist file",
+                        }
+                    })
+                }
+            }
+
+            Ok(())
+        }
+        "sync" => handle_sync(sub_m, &config),
+        "clean-window-state" => {
+            let all = sub_m.get_flag("all");
+            let domain = if all {
+                None
+            } else {
+                Some(parse_domain_or_path(sub_m, false)?)
+            };
+            cleanup::clean_window_state(
+                cleanup::target_domains(domain, all)?,
+                sub_m.get_flag("saved-state"),
+                sub_m.get_flag("dry-run"),
+            )
+        }
+        "scrub-recents" => cleanup::scrub_recents(
+            sub_m.get_one::<String>("app").map(String::as_str),
+            sub_m.get_flag("dry-run"),
+        ),
+        "note" => match sub_m.subcommand() {
+            Some(("add", m)) => notes::add(
+                &parse_domain_or_path(m, true)?,
+                get_required_arg(m, "key"),
+                get_required_arg(m, "note"),
+            ),
+            Some(("remove", m)) => {
+                notes::remove(&parse_domain_or_path(m, true)?, get_required_arg(m, "key"))
+            }
+            Some(("list", m)) => match m.get_one::<String>("domain") {
+                Some(_) => notes::list(Some(&parse_domain_or_path(m, true)?)),
+                None => notes::list(None),
+            },
+            _ => bail!("Not a proper note subcommand."),
+        },
+        "pin" => match sub_m.subcommand() {
+            Some(("check", m)) => {
+                let domain = match m.get_one::<String>("domain") {
+                    Some(_) => Some(parse_domain_or_path(m, false)?),
+                    None => None,
+                };
+                let drifted = pins::check(domain.as_ref(), m.get_flag("restore"))?;
+                if drifted > 0 {
+                    bail!(
+                        "{drifted} pinned value(s) drifted; run `drs pin check --restore` to restore."
+                    );
+                }
+                Ok(())
+            }
+            Some(("list", _)) => pins::list(),
+            Some(("remove", m)) => {
+                pins::remove(&parse_domain_or_path(m, true)?, get_required_arg(m, "key"))
+            }
+            _ => pins::add(
+                &parse_domain_or_path(sub_m, false)?,
+                get_required_arg(sub_m, "key"),
+            ),
+        },
+        "array" => {
+            let Some((op, m)) = sub_m.subcommand() else {
+                bail!("Not a proper array subcommand.");
+            };
+            let domain = parse_domain_or_path(m, false)?;
+            let path = KeyPath::parse(get_required_arg(m, "keypath"))?;
+            let index = |id| m.get_one::<usize>(id).copied().context("index is required");
+            match op {
+                "insert" => {
+                    Preferences::array_insert(domain, &path, index("index")?, array::value(m)?)
+                }
+                "remove" => Preferences::array_remove(domain, &path, index("index")?).map(drop),
+                "move" => Preferences::array_move(domain, &path, index("from")?, index("to")?),
+                _ => bail!("Not a proper array subcommand."),
+            }
+        }
+        "q" => {
+            let mut args = vec![
+                get_required_arg(sub_m, "domain").to_string(),
+                get_required_arg(sub_m, "key").to_string(),
+            ];
+            if let Some(format) = sub_m.get_one::<String>("format") {
+                args.extend(["--format".to_string(), format.clone()]);
+            }
+            query::run(&args)
+        }
+        "dock" => match sub_m.subcommand() {
+            Some(("list", _)) => dock::list(),
+            Some(("add-app", m)) => dock::add_app(
+                Path::new(get_required_arg(m, "app")),
+                m.get_one::<usize>("position").copied(),
+                !m.get_flag("no-restart"),
+            ),
+            Some(("remove-app", m)) => {
+                dock::remove_app(get_required_arg(m, "app"), !m.get_flag("no-restart"))
+            }
+            _ => bail!("Not a proper dock subcommand."),
+        },
+        "login-items" => login_items::list(),
+        "screensaver" => match sub_m.subcommand() {
+            Some(("show", _)) => screensaver::show(),
+            Some(("idle", m)) => screensaver::set_idle(get_required_arg(m, "time")),
+            Some(("clock", m)) => screensaver::set_clock(get_required_arg(m, "state") == "on"),
+            _ => bail!("Not a proper screensaver subcommand."),
+        },
+        "handler" => match sub_m.subcommand() {
+            Some(("list", _)) => handler::list(),
+            Some(("get", m)) => handler::get(get_required_arg(m, "target")),
+            Some(("set", m)) => handler::set(
+                get_required_arg(m, "target"),
+                get_required_arg(m, "bundle_id"),
+                !m.get_flag("no-refresh"),
+            ),
+            _ => bail!("Not a proper handler subcommand."),
+        },
+        "hotkeys" => {
+            let id = |m: &ArgMatches| m.get_one::<u32>("id").copied().context("id is required");
+            match sub_m.subcommand() {
+                Some(("list", _)) => hotkeys::list(),
+                Some(("enable", m)) => hotkeys::set_enabled(id(m)?, true),
+                Some(("disable", m)) => hotkeys::set_enabled(id(m)?, false),
+                Some(("set", m)) => hotkeys::set(id(m)?, get_required_arg(m, "shortcut")),
+                _ => bail!("Not a proper hotkeys subcommand."),
+            }
+        }
+        "export-all" => {
+            let dir = normalize_path(get_required_arg(sub_m, "dir"))?;
+            let jobs = sub_m.get_one::<u64>("jobs").copied().unwrap_or(4) as usize;
+
+            metadata::protect(&dir, &config)?;
+            bulk::export_all(&dir, jobs, sub_m.get_flag("resume"))
+        }
+        "import-all" => {
+            let mut uuids = UuidMap::new();
+            for pair in sub_m.get_many::<String>("map-uuid").into_iter().flatten() {
+                let (from, to) = pair
+                    .split_once('=')
+                    .with_context(|| format!("--map-uuid takes FROM=TO, not {pair}."))?;
+                uuids = uuids.map(from.trim(), to.trim());
+            }
+            print_report(
+                bulk::import_all(&normalize_path(get_required_arg(sub_m, "dir"))?, uuids)?,
+                sub_m,
+            )
+        }
+        "gc" => {
+            let mut dirs = vec![paths::runtime_dir()?];
+            if let Some(extra) = sub_m.get_many::<String>("dirs") {
+                for dir in extra {
+                    dirs.push(normalize_path(dir)?);
+                }
+            }
+
+            for dir in dirs {
+                for removed in file::clean_stale_files(&dir)? {
+                    println!("Removed {}", removed.display());
+                }
+            }
+            Ok(())
+        }
+        "doctor" => {
+            let config_file = paths::config_file()?;
+            let stats = Preferences::cache_stats();
+
+            println!("drs {}", env!("CARGO_PKG_VERSION"));
+            println!(
+                "Config file: {} ({})",
+                config_file.display(),
+                if config_file.is_file() {
+                    "found"
+                } else {
+                    "not found"
+                }
+            );
+            println!(
+                "Read-only mode: {}",
+                if Preferences::is_read_only() {
+                    "on"
+                } else {
+                    "off"
+                }
+            );
+            match Preferences::backup_dir() {
+                Some(dir) => println!("Backups: {}", dir.display()),
+                None => println!("Backups: disabled"),
+            }
+            if stats.capacity == 0 {
+                println!("Domain cache: disabled");
+            } else {
+                println!(
+                    "Domain cache: {}/{} domains, {} hits, {} misses",
+                    stats.entries, stats.capacity, stats.hits, stats.misses
+                );
+            }
+            println!("Domains: {}", Preferences::list_domains()?.len());
+            Ok(())
+        }
+        "paths" => {
+            for (name, path) in paths::all(config.spotlight_exclude)? {
+                println!("{name:<8} {}", path.display());
+            }
+            Ok(())
+        }
+        "find" => {
+            let word = get_required_arg(sub_m, "word");
+            let results = Preferences::find(word)?;
+
+            if sub_m.get_flag("pick") {
+                let mut entries: Vec<(String, Domain, String)> = results
+                    .into_iter()
+                    .flat_map(|(domain, matches)| {
+                        matches
+                            .into_iter()
+                            .map(move |m| (format!("{domain} {}", m.key), domain.clone(), m.key))
+                    })
+                    .collect();
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+                let lines: Vec<String> = entries.iter().map(|e| e.0.clone()).collect();
+                let Some(picked) = pick_one("Select a match to read: ", &lines)? else {
+                    bail!("No match selected.")
+                };
+                let (_, domain, key) = entries
+                    .into_iter()
+                    .find(|e| e.0 == picked)
+                    .context("Unexpected match mismatch here.")?;
+
+                let val = Preferences::read(domain.clone(), &key)?;
+                println!("{}", prettify(&domain, &key_path_of(&key), &val, 0));
+                return Ok(());
+            }
+
+            let json = sub_m
+                .get_one::<String>("output")
+                .is_some_and(|o| o == "json");
+            let limit = sub_m
+                .get_one::<u64>("limit")
+                .map_or(usize::MAX, |&n| n as usize);
+            let mut results: Vec<(Domain, Vec<FindMatch>)> = results.into_iter().collect();
+            results.sort_by_key(|(domain, _)| domain.to_string());
+
+            if sub_m.get_flag("domains-only") {
+                let shown = &results[..results.len().min(limit)];
+                if json {
+                    let domains: Vec<serde_json::Value> = shown
+                        .iter()
+                        .map(|(domain, matches)| {
+                            serde_json::json!({ "domain": domain.to_string(), "matches": matches.len() })
+                        })
+                        .collect();
+                    println!("{}", serde_json::Value::Array(domains));
+                } else {
+                    for (domain, matches) in shown {
+                        println!("{domain} ({} matches)", matches.len());
+                    }
+                }
+                if shown.len() < results.len() {
+                    eprintln!(
+                        "Showing {} of {} domains; raise --limit to see more.",
+                        shown.len(),
+                        results.len()
+                    );
+                }
+                return Ok(());
+            }
+
+            // Keep the first matches by key path, so the same ones are shown every time.
+            let per_domain = sub_m
+                .get_one::<u64>("per-domain")
+                .map_or(usize::MAX, |&n| n as usize);
+            let mut remaining = limit;
+            let mut counts = Vec::with_capacity(results.len());
+            for (_, matches) in &mut results {
+                counts.push(matches.len());
+                matches.sort_by(|a, b| a.key.cmp(&b.key));
+                matches.truncate(per_domain.min(remaining));
+                remaining -= matches.len();
+            }
+            let total: usize = counts.iter().sum();
+            let shown = limit.saturating_sub(remaining);
+
+            if json {
+                let matches: Vec<&FindMatch> =
+                    results.iter().flat_map(|(_, matches)| matches).collect();
+                println!("{}", serde_json::to_string(&matches)?);
+            } else {
+                for ((domain, matches), count) in results.iter().zip(counts) {
+                    if matches.is_empty() {
+                        continue;
+                    }
+                    if matches.len() < count {
+                        println!(
+                            "Found {count} matches for domain `{domain}`, showing {}:",
+                            matches.len()
+                        );
+                    } else {
+                        println!("Found {count} matches for domain `{domain}`:");
+                    }
+                    for m in matches {
+                        println!("    {} = {}", m.key, m.snippet);
+                    }
+                    println!();
+                }
+            }
+            if shown < total {
+                eprintln!(
+                    "Showing {shown} of {total} matches; raise --limit or --per-domain to see more."
+                );
+            }
+            Ok(())
+        }
+        "write" => {
+            let force = sub_m.get_flag("force");
+
+            let (domain, key) = if let Some(domain) = app_domain(sub_m)? {
+                (domain, get_required_arg(sub_m, "domain"))
+            } else if sub_m.contains_id("user") || sub_m.contains_id("simulator") {
+                (
+                    parse_domain_or_path(sub_m, force)?,
+                    get_required_arg(sub_m, "key"),
+                )
+            } else if let Ok(val) = parse_domain_or_path(sub_m, force) {
+                (val, get_required_arg(sub_m, "key"))
+            } else {
+                bail!("Could not write to non-existing domain. If intentional, use -F/--force.")
+            };
+            if force {
+                new_domain::preview(&domain)?;
+            }
+
+            if sub_m.contains_id("array-add") {
+                return Preferences::array_append(domain, key, array_add_values(sub_m)?);
+            }
+            if sub_m.contains_id("dict-add") {
+                return Preferences::dict_merge(domain, key, dict_pairs(sub_m, "dict-add")?);
+            }
+
+            let value = if let Some(raw) = sub_m.get_one::<String>("number") {
+                number_like(Preferences::read(domain.clone(), key).ok().as_ref(), raw)?
+            } else {
+                extract_prefvalue_from_args(sub_m)?
+            };
+            if matches!(value, PrefValue::Uid(_)) && domain.file().is_none() {
+                bail!("UIDs only exist in plist files; write them with --file or to a plist path.");
+            }
+            let written_type = value.get_type();
+            Preferences::write(domain.clone(), key, value)?;
+
+            if sub_m.get_flag("verify") || sub_m.contains_id("number") {
+                let stored_type = Preferences::read_type(domain.clone(), key)?;
+                if stored_type != written_type {
+                    bail!("Wrote {key} as {written_type}, but it was stored as {stored_type}.")
+                }
+                println!("Verified: {key} is stored as {stored_type}");
+            }
+            if sub_m.get_flag("verify")
+                && let Some(problem) = container_redirect(&domain, key)
+            {
+                bail!("{problem}");
+            }
+            Ok(())
+        }
+        "read" => {
+            let input_domain = sub_m.get_one::<String>("domain");
+            let input_key = sub_m.get_one::<String>("key");
+
+            let app = app_domain(sub_m)?;
+            let shifted = app.is_some();
+            let domain: Domain = if let Some(domain) = app {
+                domain
+            } else if sub_m.contains_id("user") || sub_m.contains_id("simulator") {
+                parse_domain_or_path(sub_m, false)?
+            } else if let Ok(val) = parse_domain_or_path(sub_m, false) {
+                val
+            } else if input_domain.is_none() && input_key.is_none() {
+                let domains = Preferences::list_domains()?;
+                let domains_str: Vec<String> = domains.iter().map(|f| f.to_string()).collect();
+
+                let chosen = pick_one(
+                    "Select a proper domain to read. Use arrow keys to navigate: ",
+                    &domains_str,
+                )?;
+
+                if let Some(chosen) = chosen {
+                    let picked = domains
+                        .into_iter()
+                        .find(|d| d.to_string() == chosen)
+                        .context("Unexpected domain mismatch here.")?;
+                    scoped(sub_m, picked)
+                } else {
+                    bail!("No domain selected.")
+                }
+            } else {
+                bail!(
+                    "Invalid domain passed: {:?}. Please provide a valid domain name (e.g., 'com.example.app'), or use the fuzzy picker by omitting both domain and key arguments.",
+                    input_domain
+                )
+            };
+
+            let keys: Vec<String> = input_domain
+                .filter(|_| shifted)
+                .into_iter()
+                .chain(sub_m.get_many::<String>("key").into_iter().flatten())
+                .map(|key| display_key(key))
+                .collect::<Result<_>>()?;
+            let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+            let json = sub_m
+                .get_one::<String>("output")
+                .is_some_and(|o| o == "json");
+            let limit = sub_m.get_one::<u64>("limit").copied();
+            let cursor = sub_m.get_one::<String>("cursor").map(String::as_str);
+
+            if limit.is_some() || cursor.is_some() {
+                if !json || !keys.is_empty() {
+                    bail!(
+                        "--limit and --cursor are only supported for domain reads with --output json."
+                    )
+                }
+
+                let page = Preferences::read_domain_page(
+                    domain,
+                    cursor,
+                    limit.map_or(usize::MAX, |l| l as usize),
+                )?;
+                let entries: serde_json::Map<String, serde_json::Value> = page
+                    .entries
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.to_json()))
+                    .collect();
+
+                println!(
+                    "{}",
+                    serde_json::json!({ "entries": entries, "next_cursor": page.next_cursor })
+                );
+                return Ok(());
+            }
+
+            let (val, path) = match keys.as_slice() {
+                [] => (
+                    Preferences::read_domain(domain.clone())?,
+                    KeyPath::default(),
+                ),
+                [key] => (read_or_explain(&domain, key)?, key_path_of(key)),
+                keys => (
+                    PrefValue::Dictionary(Preferences::read_batch(domain.clone(), keys)?),
+                    KeyPath::default(),
+                ),
+            };
+
+            if let Some(target) = sub_m.get_one::<String>("output-file") {
+                let PrefValue::Data(bytes) = &val else {
+                    bail!(
+                        "--output-file only applies to data, got {}.",
+                        val.get_type()
+                    );
+                };
+                if target == "-" {
+                    std::io::stdout().write_all(bytes)?;
+                } else {
+                    let target = normalize_path(target)?;
+                    file::write_atomic(&target, bytes)?;
+                    eprintln!("Wrote {} bytes to {}", bytes.len(), target.display());
+                }
+                return Ok(());
+            }
+
+            if let Some(unit) = sub_m.get_one::<String>("human") {
+                let unit = match unit.as_str() {
+                    "bytes" => units::Unit::Bytes,
+                    _ => units::Unit::Seconds,
+                };
+                let number = match val {
+                    PrefValue::Integer(i) => i as f64,
+                    PrefValue::Float(f) => f,
+                    other => bail!("--human only applies to numbers, got {}.", other.get_type()),
+                };
+                println!("{}", units::humanize(number, unit));
+            } else if json {
+                println!("{}", val.to_json());
+            } else if sub_m
+                .get_one::<String>("output")
+                .is_some_and(|o| o == "typed")
+            {
+                println!("{}", val.to_display_string(DisplayStyle::Typed));
+            } else {
+                println!("{}", prettify(&domain, &path, &val, 0));
+                notes::show(&domain, &keys);
+            }
+            Ok(())
+        }
+        "read-type" => {
+            let domain: Domain = parse_domain_or_path(sub_m, false)?;
+            let key = get_required_arg(sub_m, "key");
+            let val = read_or_explain(&domain, key)?;
+
+            println!("Type is {}", val.get_type());
+            Ok(())
+        }
+        "search-list" => {
+            search_list::print(
+                &parse_domain_or_path(sub_m, false)?,
+                sub_m.get_one::<String>("key").map(String::as_str),
+            );
+            Ok(())
+        }
+        "delete" => {
+            let (domain, key) = match app_domain(sub_m)? {
+                Some(domain) => (domain, sub_m.get_one::<String>("domain")),
+                None => (
+                    parse_domain_or_path(sub_m, false)?,
+                    sub_m.get_one::<String>("key"),
+                ),
+            };
+
+            if let Some(key) = key {
+                let layers = Preferences::value_layers(domain.clone(), key);
+                if layers.is_empty() {
+                    eprintln!("{key} in {domain}: not set (app default), nothing to delete.");
+                } else if !layers.contains(&PrefLayer::written_by(&domain)) {
+                    eprintln!(
+                        "{key} in {domain} is only stored in other layers, which delete leaves alone:"
+                    );
+                    for layer in &layers {
+                        eprintln!("  {layer}");
+                    }
+                }
+                Preferences::delete(domain, key)
+            } else {
+                Preferences::delete_domain(domain)
+            }
+        }
+        "rename" => {
+            let domain: Domain = parse_domain_or_path(sub_m, false)?;
+
+            if let Some(pattern) = sub_m.get_one::<String>("pattern") {
+                let renames = rename_plan(&domain, pattern)?;
+                if renames.is_empty() {
+                    println!("No keys match {pattern}");
+                    return Ok(());
+                }
+
+                for (old, new) in &renames {
+                    println!("{old} -> {new}");
+                }
+                if sub_m.get_flag("dry-run") {
+                    return Ok(());
+                }
+
+                Preferences::rename_many(domain, &renames)?;
+                println!("Renamed {} key(s).", renames.len());
+                return Ok(());
+            }
+
+            let old_key = get_required_arg(sub_m, "old_key");
+            let new_key = get_required_arg(sub_m, "new_key");
+
+            Preferences::rename(domain, old_key, new_key)
+        }
+        "replace" => {
+            let find = get_required_arg(sub_m, "find");
+            let find = if sub_m.get_flag("regex") {
+                replace::Pattern::Regex(regex::Regex::new(find).context("invalid --find regex")?)
+            } else {
+                replace::Pattern::Literal(find.to_string())
+            };
+            replace::run(
+                &parse_domain_or_path(sub_m, false)?,
+                get_required_arg(sub_m, "key"),
+                &find,
+                get_required_arg(sub_m, "with"),
+                sub_m.get_flag("dry-run"),
+            )
+        }
+        "rewrite-paths" => replace::rewrite_paths(
+            get_required_arg(sub_m, "from"),
+            get_required_arg(sub_m, "to"),
+            sub_m.get_one::<String>("domain").map(String::as_str),
+            sub_m.get_flag("dry-run"),
+        ),
+        "migrate-type" => migrate::migrate_type(
+            &parse_domain_or_path(sub_m, false)?,
+            get_required_arg(sub_m, "glob"),
+            migrate::ValueType::parse(get_required_arg(sub_m, "from"))?,
+            migrate::ValueType::parse(get_required_arg(sub_m, "to"))?,
+            sub_m.get_flag("strict-bool"),
+            sub_m.get_flag("dry-run"),
+        ),
+        "hash" => {
+            let domain: Domain = parse_domain_or_path(sub_m, false)?;
+            let algo = match get_required_arg(sub_m, "algo") {
+                "fnv1a64" => HashAlgo::Fnv1a64,
+                _ => HashAlgo::Sha256,
+            };
+
+            println!("{}", Preferences::hash_domain(domain, algo)?);
+            Ok(())
+        }
+        "watch" => {
+            let domains = watch_targets(sub_m)?;
+            let ms = |name| Duration::from_millis(sub_m.get_one::<u64>(name).copied().unwrap_or(0));
+            let opts = WatchOptions {
+                min_interval: ms("interval"),
+                max_interval: ms("max-interval").max(ms("interval")),
+                debounce: ms("debounce"),
+            };
+
+            let restore = sub_m.get_flag("restore-pins");
+
+            let names: Vec<String> = domains.iter().map(Domain::to_string).collect();
+            println!("Watching {} (Ctrl-C to stop)", names.join(", "));
+            Preferences::watch_many(domains, &opts, |change| {
+                println!(
+                    "[{}] {}: {}",
+                    chrono::Local::now().format("%H:%M:%S"),
+                    change.domain,
+                    change.keys.join(", ")
+                );
+                if let Err(e) = pins::check(Some(&change.domain), restore) {
+                    eprintln!("Error: {e}");
+                }
+                true
+            })
+        }
+        "changed" => {
+            let window = units::parse_float(get_required_arg(sub_m, "since"))?;
+            if !window.is_finite() || window < 0.0 {
+                bail!("--since must be a positive duration.");
+            }
+            let domain = if sub_m.contains_id("domain") {
+                Some(parse_domain_or_path(sub_m, false)?)
+            } else {
+                None
+            };
+            changed::run(
+                cleanup::target_domains(domain.clone(), domain.is_none())?,
+                Duration::from_secs_f64(window),
+                &paths::backup_dir(config.spotlight_exclude)?,
+            )
+        }
+        "import" => {
+            let domain: Domain = parse_domain_or_path(sub_m, false)?;
+            let path = normalize_path(get_required_arg(sub_m, "path"))?;
+
+            print_report(
+                Preferences::import_with(
+                    domain,
+                    &path.to_string_lossy(),
+                    &normalize_rules(sub_m)?,
+                )?,
+                sub_m,
+            )
+        }
+        "export" => {
+            // With --all there is no domain, so the path is the first positional argument.
+            let all = sub_m.get_flag("all");
+            let path = if all {
+                if sub_m.contains_id("path") {
+                    bail!("--all exports every domain; give only a path.");
+                }
+                sub_m.get_one::<String>("domain")
+            } else {
+                sub_m.get_one::<String>("path")
+            }
+            .context("path argument is required")?;
+            let pref = if all {
+                non_default::all_non_default()?
+            } else {
+                let domain: Domain = parse_domain_or_path(sub_m, false)?;
+                if sub_m.get_flag("non-default") {
+                    non_default::non_default(&domain)?
+                } else {
+                    Preferences::read_domain(domain)?
+                }
+            };
+            let to_stdout = path == "-";
+            let format = match sub_m.get_one::<String>("format").map(String::as_str) {
+                Some("binary") => PlistFormat::Binary,
+                Some("json") => PlistFormat::Json,
+                Some(_) => PlistFormat::Xml,
+                None if to_stdout => PlistFormat::Xml,
+                None => PlistFormat::Binary,
+            };
+
+            if !to_stdout {
+                let path = normalize_path(path)?;
+                let mut buf = Vec::new();
+                Preferences::write_to(&pref, &mut buf, format)?;
+                return file::write_atomic(&path, &buf);
+            }
+
+            if format == PlistFormat::Binary && !sub_m.get_flag("force-binary") {
+                bail!("Refusing to write a binary plist to stdout; pass --force-binary to do so.");
+            }
+            let mut stdout = std::io::stdout().lock();
+            Preferences::write_to(&pref, &mut stdout, format)?;
+            stdout.flush()?;
+            Ok(())
+        }
+        _ => bail!("Not a proper subcommand."),
+    }
+}
+
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use super::*;
+
+    /// The matches of the `write` subcommand for `args`.
+    fn write_matches(args: &[&str]) -> Result<ArgMatches> {
+        let matches = build_cli().try_get_matches_from(["drs", "write"].iter().chain(args))?;
+        let (_, sub_m) = matches.subcommand().context("no subcommand")?;
+        Ok(sub_m.clone())
+    }
+
+    #[test]
+    fn array_add_parses_values() -> Result<()> {
+        let sub_m = write_matches(&[
+            "d",
+            "k",
+            "--array-add",
+            "1",
+            "x",
+            "{ tile-type = spacer-tile; }",
+            "<dict><key>a</key><true/></dict>",
+        ])?;
+        let dict = |key: &str, value| PrefValue::Dictionary(HashMap::from([(key.into(), value)]));
+        assert_eq!(
+            array_add_values(&sub_m)?,
+            vec![
+                PrefValue::Integer(1),
+                PrefValue::String("x".into()),
+                dict("tile-type", PrefValue::String("spacer-tile".into())),
+                dict("a", PrefValue::Boolean(true)),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn watch_takes_front_instead_of_domains() -> Result<()> {
+        let cli = build_cli();
+        cli.clone()
+            .try_get_matches_from(["drs", "watch", "--front"])?;
+        cli.clone()
+            .try_get_matches_from(["drs", "watch", "--front", "com.apple.dock"])?;
+        assert!(cli.try_get_matches_from(["drs", "watch"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_rename_patterns() -> Result<()> {
+        let (regex, replacement, global) = parse_rename_pattern("s/^old/new/g")?;
+        assert_eq!(
+            (regex.as_str(), replacement.as_str(), global),
+            ("^old", "new", true)
+        );
+        let (regex, replacement, global) = parse_rename_pattern(r"s|a\|b|c|")?;
+        assert_eq!(
+            (regex.as_str(), replacement.as_str(), global),
+            ("a|b", "c", false)
+        );
+
+        for invalid in ["", "x/a/b/", "s/a/b", "s/a/b/c/", "s/a/b/i", "s/(/b/"] {
+            assert!(parse_rename_pattern(invalid).is_err(), "{invalid}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parses_dict_pairs() -> Result<()> {
+        let sub_m = write_matches(&[
+            "d", "k", "--dict", "a", "1", "b", "-int", "2", "c", "-float", "0.5", "d", "-bool",
+            "yes", "e", "-string", "-int",
+        ])?;
+        assert_eq!(
+            dict_pairs(&sub_m, "dict")?,
+            HashMap::from([
+                ("a".into(), PrefValue::String("1".into())),
+                ("b".into(), PrefValue::Integer(2)),
+                ("c".into(), PrefValue::Float(0.5)),
+                ("d".into(), PrefValue::Boolean(true)),
+                ("e".into(), PrefValue::String("-int".into())),
+            ])
+        );
+
+        let missing = write_matches(&["d", "k", "--dict", "a", "-int"])?;
+        assert!(dict_pairs(&missing, "dict").is_err());
+        let strict = write_matches(&["d", "k", "--strict-bool", "--dict", "a", "-bool", "yes"])?;
+        assert!(dict_pairs(&strict, "dict").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rewrites_apple_flags() {
+        let rewrite = |args: &[&str]| -> Vec<String> {
+            apple_flags(args.iter().map(Into::into))
+                .into_iter()
+                .map(|a| a.to_string_lossy().into_owned())
+                .collect()
+        };
+        assert_eq!(
+            rewrite(&["drs", "write", "d", "k", "-array-add", "x"]),
+            ["drs", "write", "d", "k", "--array-add", "x"]
+        );
+        assert_eq!(
+            rewrite(&["drs", "read", "-app", "Safari", "k"]),
+            ["drs", "read", "--app", "Safari", "k"]
+        );
+        // Pairs after --dict and everything after -- are values, not options.
+        assert_eq!(
+            rewrite(&["drs", "write", "d", "k", "-dict", "-data", "-dict-add"]),
+            ["drs", "write", "d", "k", "--dict", "-data", "-dict-add"]
+        );
+        assert_eq!(
+            rewrite(&["drs", "write", "d", "--", "-data"]),
+            ["drs", "write", "d", "--", "-data"]
+        );
+        // The program name is never rewritten.
+        assert_eq!(rewrite(&["-app"]), ["-app"]);
+    }
+}
@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: MIT
+
+//! Previews of forced writes to domains which don't exist yet.
+//!
+//! CFPreferences happily creates a plist for any domain name it is given, so a typo in
+//! `drs write --force` leaves a stray file behind. Before such a write, drs shows which file will
+//! be created and for which scope, and suggests existing domains with a similar name. On a
+//! terminal, the write only goes ahead once confirmed.
+
+use anyhow::{Result, bail};
+use std::io::{BufRead, IsTerminal, Write};
+
+use crate::{Domain, Preferences};
+
+/// The Levenshtein distance between `a` and `b`, ignoring case.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Who a domain's preferences apply to.
+fn scope(domain: &Domain) -> &'static str {
+    match domain {
+        Domain::User(_) => "the current user, on any Mac",
+        Domain::Global => "every app of the current user",
+        Domain::ByHost(_) => "the current user, on this Mac only",
+        Domain::System(_) => "every user of this Mac",
+        Domain::Container(_) => "the current user, inside the app's sandbox container",
+        Domain::Group(_) => "the current user, shared by the apps of an app group",
+        Domain::Path(_) => "whoever reads the file",
+    }
+}
+
+/// Describe what writing to `domain` will create if it doesn't exist yet, and ask for
+/// confirmation on a terminal. Fails if the user declines.
+pub(crate) fn preview(domain: &Domain) -> Result<()> {
+    let existing = Preferences::list_domains()?;
+    if domain.plist_path().is_some_and(|p| p.is_file()) || existing.contains(domain) {
+        return Ok(());
+    }
+
+    eprintln!("{domain} doesn't exist yet. Writing to it creates:");
+    match domain.plist_path() {
+        Some(path) => eprintln!("  File:  {}", path.display()),
+        None => eprintln!("  File:  (decided by cfprefsd)"),
+    }
+    eprintln!("  Scope: {}", scope(domain));
+
+    let name = domain.get_cf_name();
+    let threshold = (name.chars().count() / 8).max(2);
+    let mut similar: Vec<(usize, String)> = existing
+        .iter()
+        .map(|d| d.get_cf_name())
+        .map(|other| (distance(&name, &other), other))
+        .filter(|(d, _)| *d <= threshold)
+        .collect();
+    similar.sort();
+    if let Some((_, first)) = similar.first() {
+        let rest: Vec<&str> = similar
+            .iter()
+            .skip(1)
+            .take(2)
+            .map(|(_, s)| s.as_str())
+            .collect();
+        if rest.is_empty() {
+            eprintln!("  Did you mean {first}?");
+        } else {
+            eprintln!("  Did you mean {first} (or {})?", rest.join(", "));
+        }
+    }
+
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() || !std::io::stderr().is_terminal() {
+        return Ok(());
+    }
+    eprint!("Create it? [y/N] ");
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    stdin.lock().read_line(&mut answer)?;
+    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+        bail!("Aborted; nothing was written.");
+    }
+    Ok(())
+}
@@ -0,0 +1,133 @@
+// SPDX-License-Identifier: MIT
+
+//! `drs export --non-default`: export only the values that differ from the macOS defaults.
+//!
+//! Defaults are looked up in a small built-in table of well-known keys and the value macOS uses
+//! until they are set. Keys missing from the table are always exported, since there is no telling
+//! whether they hold a default.
+
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+
+use crate::{Domain, PrefValue, Preferences, ScreenSaver};
+
+/// The value macOS uses for a key until it is set.
+enum Known {
+    Bool(bool),
+    Int(i64),
+    Str(&'static str),
+}
+
+impl Known {
+    fn matches(&self, value: &PrefValue) -> bool {
+        match (self, value) {
+            (Known::Bool(b), PrefValue::Boolean(v)) => b == v,
+            // Booleans are often written as 0 or 1.
+            (Known::Bool(b), PrefValue::Integer(v)) => i64::from(*b) == *v,
+            (Known::Int(i), PrefValue::Integer(v)) => i == v,
+            (Known::Int(i), PrefValue::Float(v)) => *i as f64 == *v,
+            (Known::Str(s), PrefValue::String(v)) => s == v,
+            _ => false,
+        }
+    }
+}
+
+/// Known defaults, by CoreFoundation domain name and key.
+const KNOWN: &[(&str, &[(&str, Known)])] = &[
+    (
+        ".GlobalPreferences",
+        &[
+            ("AppleShowAllExtensions", Known::Bool(false)),
+            ("AppleShowScrollBars", Known::Str("Automatic")),
+            ("ApplePressAndHoldEnabled", Known::Bool(true)),
+            ("NSAutomaticCapitalizationEnabled", Known::Bool(true)),
+            ("NSAutomaticDashSubstitutionEnabled", Known::Bool(true)),
+            ("NSAutomaticPeriodSubstitutionEnabled", Known::Bool(true)),
+            ("NSAutomaticQuoteSubstitutionEnabled", Known::Bool(true)),
+            ("NSAutomaticSpellingCorrectionEnabled", Known::Bool(true)),
+            ("NSDocumentSaveNewDocumentsToCloud", Known::Bool(true)),
+            ("NSNavPanelExpandedStateForSaveMode", Known::Bool(false)),
+            ("com.apple.swipescrolldirection", Known::Bool(true)),
+        ],
+    ),
+    (
+        "com.apple.dock",
+        &[
+            ("autohide", Known::Bool(false)),
+            ("magnification", Known::Bool(false)),
+            ("mineffect", Known::Str("genie")),
+            ("minimize-to-application", Known::Bool(false)),
+            ("mru-spaces", Known::Bool(true)),
+            ("orientation", Known::Str("bottom")),
+            ("show-process-indicators", Known::Bool(true)),
+            ("show-recents", Known::Bool(true)),
+            ("static-only", Known::Bool(false)),
+        ],
+    ),
+    (
+        "com.apple.finder",
+        &[
+            ("AppleShowAllFiles", Known::Bool(false)),
+            ("FXEnableExtensionChangeWarning", Known::Bool(true)),
+            ("FXPreferredViewStyle", Known::Str("icnv")),
+            ("QuitMenuItem", Known::Bool(false)),
+            ("ShowPathbar", Known::Bool(false)),
+            ("ShowStatusBar", Known::Bool(false)),
+            ("_FXShowPosixPathInTitle", Known::Bool(false)),
+        ],
+    ),
+    (
+        "com.apple.screencapture",
+        &[
+            ("disable-shadow", Known::Bool(false)),
+            ("show-thumbnail", Known::Bool(true)),
+            ("type", Known::Str("png")),
+        ],
+    ),
+    (
+        "com.apple.screensaver",
+        &[
+            (
+                "idleTime",
+                Known::Int(ScreenSaver::DEFAULT_IDLE_TIME.as_secs() as i64),
+            ),
+            ("showClock", Known::Bool(false)),
+        ],
+    ),
+];
+
+/// Whether `value` is the known default of `key` in `domain`.
+fn is_default(domain: &Domain, key: &str, value: &PrefValue) -> bool {
+    let name = domain.get_cf_name();
+    KNOWN
+        .iter()
+        .filter(|(d, _)| *d == name)
+        .flat_map(|(_, keys)| keys.iter())
+        .any(|(k, known)| *k == key && known.matches(value))
+}
+
+/// The values of `domain` that aren't known to be defaults.
+pub(crate) fn non_default(domain: &Domain) -> Result<PrefValue> {
+    let PrefValue::Dictionary(mut dict) = Preferences::read_domain(domain.clone())? else {
+        bail!("{domain} is not a dictionary.")
+    };
+    dict.retain(|key, value| !is_default(domain, key, value));
+    Ok(PrefValue::Dictionary(dict))
+}
+
+/// The values of every domain that aren't known to be defaults, keyed by domain. Domains left
+/// with nothing are omitted.
+pub(crate) fn all_non_default() -> Result<PrefValue> {
+    let mut all = HashMap::new();
+    for domain in Preferences::list_domains()?
+        .into_iter()
+        .chain([Domain::Global])
+    {
+        if let PrefValue::Dictionary(dict) = non_default(&domain)?
+            && !dict.is_empty()
+        {
+            all.insert(domain.to_string(), PrefValue::Dictionary(dict));
+        }
+    }
+    Ok(PrefValue::Dictionary(all))
+}
@@ -0,0 +1,118 @@
+// SPDX-License-Identifier: MIT
+
+//! Notes attached to domain/key pairs.
+//!
+//! Notes live in `notes.tsv` in the drs config directory, one `domain<TAB>key<TAB>note` line per
+//! note, with tabs, newlines and backslashes escaped. `read` shows the notes of the keys it prints
+//! on stderr, so piped output stays unchanged.
+
+use anyhow::{Context, Result, bail};
+use std::{collections::BTreeMap, fs};
+
+use crate::{Domain, core::file};
+
+use super::paths;
+
+/// Notes keyed by (domain, key).
+pub(crate) type Notes = BTreeMap<(String, String), String>;
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('t')) => out.push('\t'),
+            ('\\', Some('n')) => out.push('\n'),
+            ('\\', Some('\\')) => out.push('\\'),
+            _ => {
+                out.push(c);
+                continue;
+            }
+        }
+        chars.next();
+    }
+    out
+}
+
+/// Load all notes; a missing file means there are none.
+pub(crate) fn load() -> Result<Notes> {
+    let path = paths::notes_file()?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Notes::new()),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+
+    let mut notes = Notes::new();
+    for line in content.lines().filter(|l| !l.is_empty()) {
+        let mut fields = line.splitn(3, '\t');
+        let (Some(domain), Some(key), Some(note)) = (fields.next(), fields.next(), fields.next())
+        else {
+            bail!("Malformed line in {}: {line}", path.display());
+        };
+        notes.insert((unescape(domain), unescape(key)), unescape(note));
+    }
+    Ok(notes)
+}
+
+fn save(notes: &Notes) -> Result<()> {
+    fs::create_dir_all(paths::config_dir()?)?;
+
+    let content: String = notes
+        .iter()
+        .map(|((domain, key), note)| {
+            format!("{}\t{}\t{}\n", escape(domain), escape(key), escape(note))
+        })
+        .collect();
+    file::write_atomic(&paths::notes_file()?, content.as_bytes())
+}
+
+/// Attach a note to a key, replacing any previous note.
+pub(crate) fn add(domain: &Domain, key: &str, note: &str) -> Result<()> {
+    let mut notes = load()?;
+    notes.insert((domain.to_string(), key.to_string()), note.to_string());
+    save(&notes)
+}
+
+/// Remove the note of a key.
+pub(crate) fn remove(domain: &Domain, key: &str) -> Result<()> {
+    let mut notes = load()?;
+    if notes
+        .remove(&(domain.to_string(), key.to_string()))
+        .is_none()
+    {
+        bail!("No note for {key} in {domain}.");
+    }
+    save(&notes)
+}
+
+/// Print all notes, or only those of one domain.
+pub(crate) fn list(domain: Option<&Domain>) -> Result<()> {
+    let domain = domain.map(Domain::to_string);
+    for ((d, key), note) in load()? {
+        if domain.as_ref().is_none_or(|wanted| *wanted == d) {
+            println!("{d} {key}: {note}");
+        }
+    }
+    Ok(())
+}
+
+/// Show the notes of `keys` in `domain` (every key of the domain if `keys` is empty) on stderr.
+pub(crate) fn show(domain: &Domain, keys: &[&str]) {
+    let Ok(notes) = load() else {
+        return;
+    };
+    let domain = domain.to_string();
+
+    for ((d, key), note) in &notes {
+        if *d == domain && (keys.is_empty() || keys.contains(&key.as_str())) {
+            eprintln!("# {key}: {note}");
+        }
+    }
+}
@@ -0,0 +1,86 @@
+// SPDX-License-Identifier: MIT
+
+//! Locations of drs-owned state on disk.
+//!
+//! Everything drs stores lives under a `drs` folder in the macOS base directories
+//! (`~/Library/Application Support` for config and data, `~/Library/Caches` for runtime files).
+//! `XDG_CONFIG_HOME`, `XDG_DATA_HOME` and `XDG_CACHE_HOME` take precedence when set to an absolute
+//! path, for users who keep their dotfiles in XDG locations.
+
+use anyhow::{Result, anyhow};
+use std::{env, path::PathBuf};
+
+/// `$<xdg_var>/drs` if the variable holds an absolute path, otherwise `<fallback>/drs`.
+fn base_dir(xdg_var: &str, fallback: Option<PathBuf>, kind: &str) -> Result<PathBuf> {
+    env::var_os(xdg_var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or(fallback)
+        .map(|dir| dir.join("drs"))
+        .ok_or_else(|| anyhow!("could not resolve {kind} directory"))
+}
+
+/// Directory for runtime files (temp and lock files) owned by drs.
+pub(crate) fn runtime_dir() -> Result<PathBuf> {
+    base_dir("XDG_CACHE_HOME", dirs::cache_dir(), "cache")
+}
+
+/// Directory holding the user's drs configuration, notes and pins.
+pub(crate) fn config_dir() -> Result<PathBuf> {
+    base_dir("XDG_CONFIG_HOME", dirs::config_dir(), "config")
+}
+
+/// Directory holding data drs accumulates, such as backups.
+pub(crate) fn data_dir() -> Result<PathBuf> {
+    base_dir("XDG_DATA_HOME", dirs::data_dir(), "data")
+}
+
+/// The drs config file.
+pub(crate) fn config_file() -> Result<PathBuf> {
+    Ok(config_dir()?.join("config.toml"))
+}
+
+/// Notes attached to keys (`drs note`).
+pub(crate) fn notes_file() -> Result<PathBuf> {
+    Ok(config_dir()?.join("notes.tsv"))
+}
+
+/// Pinned values (`drs pin`).
+pub(crate) fn pins_file() -> Result<PathBuf> {
+    Ok(config_dir()?.join("pins.plist"))
+}
+
+/// The state of the last applied manifest (`drs apply`, `drs status`).
+pub(crate) fn state_file() -> Result<PathBuf> {
+    Ok(data_dir()?.join("state.plist"))
+}
+
+/// Log of every key `drs enforce run` wrote back or backed off from.
+pub(crate) fn journal_file() -> Result<PathBuf> {
+    Ok(data_dir()?.join("journal.log"))
+}
+
+/// Directory holding automatic domain backups, named `backups.noindex` when it should be hidden
+/// from Spotlight.
+pub(crate) fn backup_dir(noindex: bool) -> Result<PathBuf> {
+    let name = if noindex {
+        "backups.noindex"
+    } else {
+        "backups"
+    };
+    Ok(data_dir()?.join(name))
+}
+
+/// Every location drs reads or writes, labelled for `drs paths`.
+pub(crate) fn all(noindex: bool) -> Result<Vec<(&'static str, PathBuf)>> {
+    Ok(vec![
+        ("config", config_file()?),
+        ("notes", notes_file()?),
+        ("pins", pins_file()?),
+        ("data", data_dir()?),
+        ("state", state_file()?),
+        ("journal", journal_file()?),
+        ("backups", backup_dir(noindex)?),
+        ("runtime", runtime_dir()?),
+    ])
+}
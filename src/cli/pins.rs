@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: MIT
+
+//! Pinned values which are checked for drift.
+//!
+//! `drs pin <domain> <key>` records the key's current value in `pins.plist` in the drs config
+//! directory. `drs pin check` and `drs watch` compare the live values against the pins, reporting
+//! (and optionally restoring) any that changed.
+
+use anyhow::{Context, Result, bail};
+use plist::Value;
+use std::{collections::BTreeMap, fs, io::Cursor};
+
+use crate::{
+    Domain, PrefValue, Preferences,
+    core::file,
+    preferences::convert::{plist_to_prefvalue, prefvalue_to_plist},
+};
+
+use super::paths;
+
+/// Pinned values by domain name and key.
+type Pins = BTreeMap<String, BTreeMap<String, PrefValue>>;
+
+fn load() -> Result<Pins> {
+    let path = paths::pins_file()?;
+    let data = match fs::read(&path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Pins::new()),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+
+    let Value::Dictionary(root) = Value::from_reader(Cursor::new(&data))? else {
+        bail!("{} must hold a dictionary.", path.display());
+    };
+    let mut pins = Pins::new();
+    for (domain, keys) in root {
+        let Value::Dictionary(keys) = keys else {
+            continue;
+        };
+        let keys = keys
+            .into_iter()
+            .map(|(k, v)| Ok((k, plist_to_prefvalue(&v)?)))
+            .collect::<Result<_>>()?;
+        pins.insert(domain, keys);
+    }
+    Ok(pins)
+}
+
+fn save(pins: &Pins) -> Result<()> {
+    fs::create_dir_all(paths::config_dir()?)?;
+
+    let root: plist::Dictionary = pins
+        .iter()
+        .filter(|(_, keys)| !keys.is_empty())
+        .map(|(domain, keys)| {
+            let keys: plist::Dictionary = keys
+                .iter()
+                .map(|(k, v)| (k.clone(), prefvalue_to_plist(v)))
+                .collect();
+            (domain.clone(), Value::Dictionary(keys))
+        })
+        .collect();
+
+    let mut buf = Vec::new();
+    Value::Dictionary(root).to_writer_xml(&mut buf)?;
+    file::write_atomic(&paths::pins_file()?, &buf)
+}
+
+/// Pin the current value of a key.
+pub(crate) fn add(domain: &Domain, key: &str) -> Result<()> {
+    let value = Preferences::read(domain.clone(), key)?;
+    let mut pins = load()?;
+    pins.entry(domain.get_cf_name())
+        .or_default()
+        .insert(key.to_string(), value.clone());
+    save(&pins)?;
+    println!("Pinned {domain} {key} = {value}");
+    Ok(())
+}
+
+/// Stop checking a key.
+pub(crate) fn remove(domain: &Domain, key: &str) -> Result<()> {
+    let mut pins = load()?;
+    if pins
+        .get_mut(&domain.get_cf_name())
+        .and_then(|keys| keys.remove(key))
+        .is_none()
+    {
+        bail!("{key} in {domain} is not pinned.");
+    }
+    save(&pins)
+}
+
+/// Print every pin.
+pub(crate) fn list() -> Result<()> {
+    for (domain, keys) in load()? {
+        for (key, value) in keys {
+            println!("{domain} {key} = {value}");
+        }
+    }
+    Ok(())
+}
+
+fn domain_of(name: &str) -> Domain {
+    match name {
+        ".GlobalPreferences" => Domain::Global,
+        other => Domain::User(other.to_string()),
+    }
+}
+
+/// Report pinned keys of `domain` (or of every domain) whose value drifted, restoring them with
+/// `restore`. Returns the number of drifted keys which were left unrestored.
+pub(crate) fn check(domain: Option<&Domain>, restore: bool) -> Result<usize> {
+    let pins = load()?;
+    let mut drifted = 0;
+
+    for (name, keys) in &pins {
+        let pinned_domain = domain_of(name);
+        if domain.is_some_and(|d| *d != pinned_domain) {
+            continue;
+        }
+
+        for (key, pinned) in keys {
+            let current = Preferences::read(pinned_domain.clone(), key).ok();
+            if current.as_ref() == Some(pinned) {
+                continue;
+            }
+
+            let now = current.map_or_else(|| "(missing)".to_string(), |v| v.to_string());
+            println!("DRIFT {pinned_domain} {key}: pinned {pinned}, now {now}");
+            if restore {
+                Preferences::write(pinned_domain.clone(), key, pinned.clone())?;
+                println!("      restored {pinned_domain} {key}");
+            } else {
+                drifted += 1;
+            }
+        }
+    }
+    Ok(drifted)
+}
@@ -0,0 +1,162 @@
+// SPDX-License-Identifier: MIT
+
+//! `--file`: operating on any plist file instead of a preferences domain.
+//!
+//! The file is read and written directly by the [`Domain::Path`] backend rather than through
+//! CFPreferences, so this works for plists anywhere: inside app bundles, in backups or on other
+//! volumes. Running apps don't see changes made this way to a domain's own plist, since cfprefsd
+//! keeps serving its cached copy.
+
+use anyhow::{Context, Result, bail};
+use clap::ArgMatches;
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    DisplayStyle, Domain, FindQuery, KeyPath, PrefValue, Preferences,
+    core::plist_file::{load, update},
+    prettifier::prettify,
+};
+
+use super::{extract_prefvalue_from_args, key_path_of, number_like};
+
+/// The domain the file's name suggests, used to pick value renderers.
+fn render_domain(path: &Path) -> Domain {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match stem.as_str() {
+        ".GlobalPreferences" => Domain::Global,
+        _ => Domain::User(stem),
+    }
+}
+
+/// The value of `key`, which may be a key path, in `root`.
+fn get(root: &HashMap<String, PrefValue>, path: &Path, key: &str) -> Result<PrefValue> {
+    if let Some(value) = root.get(key) {
+        return Ok(value.clone());
+    }
+    key_path_of(key)
+        .resolve(&PrefValue::Dictionary(root.clone()))
+        .cloned()
+        .with_context(|| format!("Key not found in {}: {key}", path.display()))
+}
+
+/// Positional arguments of `sub_m` in order; with `--file` there is no domain, so whatever was
+/// parsed as the domain is really the first key.
+fn positionals<'a>(sub_m: &'a ArgMatches, ids: &[&str]) -> Vec<&'a str> {
+    ids.iter()
+        .filter_map(|id| sub_m.try_get_many::<String>(id).ok().flatten())
+        .flatten()
+        .map(String::as_str)
+        .collect()
+}
+
+/// Run `cmd` against the plist at `path`.
+pub(crate) fn handle(cmd: &str, sub_m: &ArgMatches, path: &Path) -> Result<()> {
+    match cmd {
+        "read" => {
+            let (root, _) = load(path)?;
+            let keys = positionals(sub_m, &["domain", "key"]);
+            let (val, key_path) = match keys.as_slice() {
+                [] => (PrefValue::Dictionary(root.clone()), KeyPath::default()),
+                [key] => (get(&root, path, key)?, key_path_of(key)),
+                keys => (
+                    PrefValue::Dictionary(
+                        keys.iter()
+                            .filter_map(|k| get(&root, path, k).ok().map(|v| (k.to_string(), v)))
+                            .collect(),
+                    ),
+                    KeyPath::default(),
+                ),
+            };
+
+            match sub_m.get_one::<String>("output").map(String::as_str) {
+                Some("json") => println!("{}", val.to_json()),
+                Some("typed") => println!("{}", val.to_display_string(DisplayStyle::Typed)),
+                _ => println!("{}", prettify(&render_domain(path), &key_path, &val, 0)),
+            }
+            Ok(())
+        }
+        "read-type" => {
+            let (root, _) = load(path)?;
+            let [key] = positionals(sub_m, &["domain"])[..] else {
+                bail!("read-type --file takes a single key.");
+            };
+            println!("Type is {}", get(&root, path, key)?.get_type());
+            Ok(())
+        }
+        "write" => {
+            let [key] = positionals(sub_m, &["domain"])[..] else {
+                bail!("write --file takes a single key.");
+            };
+            update(path, |root| {
+                let value = if let Some(raw) = sub_m.get_one::<String>("number") {
+                    number_like(root.get(key), raw)?
+                } else {
+                    extract_prefvalue_from_args(sub_m)?
+                };
+                root.insert(key.to_string(), value);
+                Ok(())
+            })
+        }
+        "delete" => {
+            let keys = positionals(sub_m, &["domain"]);
+            if keys.len() > 1 {
+                bail!("delete --file takes at most one key.");
+            }
+            // Unlike write, deleting needs the file to exist.
+            load(path)?;
+            update(path, |root| {
+                match keys[..] {
+                    [key] if root.remove(key).is_none() => {
+                        bail!("Key not found in {}: {key}", path.display())
+                    }
+                    [_] => {}
+                    _ => root.clear(),
+                }
+                Ok(())
+            })
+        }
+        "rename" => {
+            let [old_key, new_key] = positionals(sub_m, &["domain", "old_key"])[..] else {
+                bail!("rename --file takes the old and the new key.");
+            };
+            // Unlike write, renaming needs the file to exist.
+            load(path)?;
+            update(path, |root| {
+                if root.contains_key(new_key) {
+                    bail!("{new_key} already exists in {}.", path.display());
+                }
+                let value = root
+                    .remove(old_key)
+                    .with_context(|| format!("Key not found in {}: {old_key}", path.display()))?;
+                root.insert(new_key.to_string(), value);
+                Ok(())
+            })
+        }
+        "find" => {
+            let (root, _) = load(path)?;
+            let word = sub_m
+                .get_one::<String>("word")
+                .context("word argument is required")?;
+            let mut matches = Vec::new();
+            Preferences::find_in_value(
+                &render_domain(path),
+                &PrefValue::Dictionary(root),
+                &FindQuery::parse(word)?,
+                KeyPath::default(),
+                &mut matches,
+            );
+
+            println!("Found {} matches in `{}`:", matches.len(), path.display());
+            for m in matches {
+                println!("    {} = {}", m.key, m.snippet);
+            }
+            Ok(())
+        }
+        other => bail!(
+            "{other} does not support --file; use read, read-type, write, delete, rename or find."
+        ),
+    }
+}
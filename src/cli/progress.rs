@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: MIT
+
+//! Machine-readable progress events for long-running commands.
+//!
+//! With `--progress json`, commands working through many domains write one JSON object per line
+//! to stderr, so wrappers can draw progress bars without parsing the human-readable output:
+//!
+//! ```text
+//! {"event":"start","phase":"export","total":312}
+//! {"current":1,"domain":"com.apple.dock","event":"progress","ok":true,"phase":"export","total":312}
+//! {"current":312,"event":"finish","failed":0,"phase":"export","total":312}
+//! ```
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable NDJSON progress events.
+pub(crate) fn set_json(enabled: bool) {
+    JSON.store(enabled, Ordering::SeqCst);
+}
+
+fn emit(event: serde_json::Value) {
+    if JSON.load(Ordering::SeqCst) {
+        eprintln!("{event}");
+    }
+}
+
+/// Progress of one phase of a command, such as exporting every domain.
+pub(crate) struct Progress {
+    phase: &'static str,
+    total: usize,
+    current: AtomicUsize,
+    failed: AtomicUsize,
+}
+
+impl Progress {
+    /// Start a phase covering `total` domains.
+    pub(crate) fn start(phase: &'static str, total: usize) -> Self {
+        emit(serde_json::json!({ "event": "start", "phase": phase, "total": total }));
+        Self {
+            phase,
+            total,
+            current: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+        }
+    }
+
+    /// Record that a domain has been processed. Safe to call from several threads.
+    pub(crate) fn step(&self, domain: &str, ok: bool) {
+        let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+        if !ok {
+            self.failed.fetch_add(1, Ordering::SeqCst);
+        }
+        emit(serde_json::json!({
+            "event": "progress",
+            "phase": self.phase,
+            "domain": domain,
+            "ok": ok,
+            "current": current,
+            "total": self.total,
+        }));
+    }
+
+    /// End the phase.
+    pub(crate) fn finish(self) {
+        emit(serde_json::json!({
+            "event": "finish",
+            "phase": self.phase,
+            "current": self.current.into_inner(),
+            "failed": self.failed.into_inner(),
+            "total": self.total,
+        }));
+    }
+}
@@ -0,0 +1,108 @@
+// SPDX-License-Identifier: MIT
+
+//! `drs q`: a minimal one-shot read for status bar widgets.
+//!
+//! Widgets such as SketchyBar or Übersicht poll values many times a minute, so this path skips
+//! everything `read` does up front: clap, the config file, backups, the domain cache and the
+//! existence check which lists every domain. `main` dispatches here before building the CLI.
+//!
+//! The output is shaped by a template where `{value}`, `{type}` and `{changed_ago}` (time since
+//! the domain's plist was last modified, e.g. `5m`) are substituted; `{{` and `}}` produce
+//! literal braces.
+
+use anyhow::{Result, bail};
+use std::{fs, time::SystemTime};
+
+use crate::{Domain, PrefValue, Preferences};
+
+use super::key_path_of;
+
+const USAGE: &str = "Usage: drs q <domain> <key> [--format <template>]";
+
+/// Run `drs q` with the arguments following `q`.
+pub fn run(args: &[String]) -> Result<()> {
+    let mut positional = Vec::new();
+    let mut template = "{value}";
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-f" | "--format" => match args.next() {
+                Some(t) => template = t,
+                None => bail!("--format requires a template.\n{USAGE}"),
+            },
+            "-h" | "--help" => {
+                println!("{USAGE}\n\nTemplate fields: {{value}}, {{type}}, {{changed_ago}}");
+                return Ok(());
+            }
+            other => positional.push(other),
+        }
+    }
+
+    let [domain, key] = positional[..] else {
+        bail!("{USAGE}");
+    };
+    let domain = match domain.strip_suffix(".plist").unwrap_or(domain) {
+        "-g" | "NSGlobalDomain" | "-globalDomain" | ".GlobalPreferences" => Domain::Global,
+        other => Domain::User(other.to_string()),
+    };
+
+    let value = Preferences::read_path(domain.clone(), &key_path_of(key))?;
+    println!("{}", expand(template, &domain, &value)?);
+    Ok(())
+}
+
+/// Fill in a template for a value read from `domain`.
+fn expand(template: &str, domain: &Domain, value: &PrefValue) -> Result<String> {
+    let mut out = String::new();
+    let mut rest = template;
+
+    while let Some(pos) = rest.find(['{', '}']) {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+
+        if let Some(after) = rest.strip_prefix("{{") {
+            out.push('{');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("}}") {
+            out.push('}');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('{') {
+            let Some((field, after)) = after.split_once('}') else {
+                bail!("Unclosed `{{` in template: {template}");
+            };
+            match field {
+                "value" => out.push_str(&value.to_string()),
+                "type" => out.push_str(value.get_type()),
+                "changed_ago" => out.push_str(&changed_ago(domain)),
+                other => bail!("Unknown template field `{other}`; use value, type or changed_ago."),
+            }
+            rest = after;
+        } else {
+            bail!("Unmatched `}}` in template: {template}");
+        }
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Time since the domain's plist was modified, in its largest whole unit.
+fn changed_ago(domain: &Domain) -> String {
+    let Some(secs) = domain
+        .plist_path()
+        .and_then(|path| fs::metadata(path).ok())
+        .and_then(|meta| meta.modified().ok())
+        .and_then(|mtime| SystemTime::now().duration_since(mtime).ok())
+        .map(|age| age.as_secs())
+    else {
+        return "unknown".to_string();
+    };
+
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: MIT
+
+//! `--remote`: running a drs command on another Mac over SSH.
+//!
+//! The command line is passed on unchanged apart from `--remote` itself, and the remote drs
+//! inherits this process' standard streams, so output formats, piping and exit codes work as they
+//! do locally. Paths in arguments refer to files on the remote Mac.
+
+use anyhow::{Context, Result, bail};
+use std::{
+    ffi::{OsStr, OsString},
+    io::IsTerminal,
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    process::Command,
+};
+
+/// Where drs is usually installed, for non-interactive shells which don't load the user's PATH.
+const REMOTE_PATH: &str = "$PATH:/opt/homebrew/bin:/usr/local/bin:$HOME/.cargo/bin";
+
+/// `arg` quoted for a POSIX shell, keeping bytes which aren't valid UTF-8.
+fn quote(arg: &OsStr) -> Vec<u8> {
+    let mut quoted = vec![b'\''];
+    for &b in arg.as_bytes() {
+        match b {
+            b'\'' => quoted.extend_from_slice(br"'\''"),
+            b => quoted.push(b),
+        }
+    }
+    quoted.push(b'\'');
+    quoted
+}
+
+/// This process' arguments without `--remote` and its value.
+fn forwarded_args() -> Vec<OsString> {
+    let mut args = Vec::new();
+    let mut iter = std::env::args_os().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--remote" {
+            iter.next();
+        } else if !arg.as_bytes().starts_with(b"--remote=") {
+            args.push(arg);
+        }
+    }
+    args
+}
+
+/// Run this drs invocation on `host` (`[user@]host`, as understood by ssh).
+pub(crate) fn run(host: &str) -> Result<()> {
+    // ssh would read a host starting with - as one of its own options, e.g. -oProxyCommand=...
+    if host.starts_with('-') {
+        bail!("Invalid host {host}: hosts can't start with -.");
+    }
+    let command = std::iter::once(format!("PATH=\"{REMOTE_PATH}\" drs").into_bytes())
+        .chain(forwarded_args().iter().map(|a| quote(a)))
+        .collect::<Vec<_>>()
+        .join(&b' ');
+    let command = OsString::from_vec(command);
+
+    let mut ssh = Command::new("ssh");
+    // Interactive commands such as the fuzzy picker need a terminal on the other end.
+    if std::io::stdin().is_terminal() && std::io::stdout().is_terminal() {
+        ssh.arg("-t");
+    }
+    let status = ssh
+        .args([OsStr::new(host), OsStr::new("--"), &command])
+        .status()
+        .context("failed to run ssh")?;
+
+    match status.code() {
+        Some(0) => Ok(()),
+        Some(255) => bail!("Couldn't connect to {host} over SSH."),
+        Some(code) => bail!("drs on {host} exited with status {code}."),
+        None => bail!("ssh was interrupted."),
+    }
+}
@@ -0,0 +1,180 @@
+// SPDX-License-Identifier: MIT
+
+//! `drs replace` and `drs rewrite-paths`: search and replace in strings inside values.
+//!
+//! Arrays and dictionaries are descended into at any depth, so a path that moved can be updated
+//! in every entry of a nested value at once. Each replacement site is shown with its key path
+//! before values are written back as a whole. `rewrite-paths` does this for a moved home folder
+//! across every domain, in one transaction.
+
+use anyhow::{Result, bail};
+use regex::Regex;
+
+use crate::{Domain, KeyPath, PrefValue, Preferences, Transaction};
+
+use super::util::glob_regex;
+
+/// What to look for in strings.
+pub(crate) enum Pattern {
+    Literal(String),
+    Regex(Regex),
+    /// A folder, matched only where it is followed by `/` or the end of the string, so
+    /// `/Users/ann` doesn't match in `/Users/anna`.
+    Path(String),
+}
+
+impl Pattern {
+    /// `text` with every match replaced by `with`, or `None` if nothing matched.
+    ///
+    /// Regex replacements may refer to capture groups as `$1` or `${name}`.
+    fn replace(&self, text: &str, with: &str) -> Option<String> {
+        match self {
+            Pattern::Literal(find) if !find.is_empty() && text.contains(find.as_str()) => {
+                Some(text.replace(find.as_str(), with))
+            }
+            Pattern::Regex(re) if re.is_match(text) => {
+                Some(re.replace_all(text, with).into_owned())
+            }
+            Pattern::Path(folder) if !folder.is_empty() => {
+                let mut out = String::with_capacity(text.len());
+                let mut last = 0;
+                for (start, _) in text.match_indices(folder.as_str()) {
+                    let end = start + folder.len();
+                    if end == text.len() || text[end..].starts_with('/') {
+                        out.push_str(&text[last..start]);
+                        out.push_str(with);
+                        last = end;
+                    }
+                }
+                (last > 0).then(|| out + &text[last..])
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Replace matches in every string and URL below `value`, recording each site changed.
+fn replace_in(
+    value: &mut PrefValue,
+    path: &KeyPath,
+    find: &Pattern,
+    with: &str,
+    sites: &mut Vec<(KeyPath, String, String)>,
+) {
+    match value {
+        PrefValue::String(text) | PrefValue::Url(text) => {
+            if let Some(replaced) = find.replace(text, with) {
+                sites.push((path.clone(), text.clone(), replaced.clone()));
+                *text = replaced;
+            }
+        }
+        PrefValue::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                replace_in(item, &path.index(i), find, with, sites);
+            }
+        }
+        PrefValue::Dictionary(dict) => {
+            let mut keys: Vec<String> = dict.keys().cloned().collect();
+            keys.sort();
+            for key in keys {
+                if let Some(item) = dict.get_mut(&key) {
+                    replace_in(item, &path.key(&key), find, with, sites);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replace `find` with `with` throughout the value of `key` in `domain`, listing every site.
+///
+/// With `dry_run`, the sites are only listed.
+pub(crate) fn run(
+    domain: &Domain,
+    key: &str,
+    find: &Pattern,
+    with: &str,
+    dry_run: bool,
+) -> Result<()> {
+    let mut value = Preferences::read(domain.clone(), key)?;
+    let mut sites = Vec::new();
+    replace_in(
+        &mut value,
+        &KeyPath::default().key(key),
+        find,
+        with,
+        &mut sites,
+    );
+
+    for (path, old, new) in &sites {
+        println!("{path}: {old} -> {new}");
+    }
+    if sites.is_empty() {
+        println!("Nothing in {domain} {key} matches.");
+        return Ok(());
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    Preferences::write(domain.clone(), key, value)?;
+    println!("Replaced {} value(s).", sites.len());
+    Ok(())
+}
+
+/// Rewrite every string and URL under the folder `from` to the same place under `to`, across all
+/// domains or those matching `glob`. With `dry_run`, the rewrites are only listed.
+pub(crate) fn rewrite_paths(from: &str, to: &str, glob: Option<&str>, dry_run: bool) -> Result<()> {
+    let from = from.trim_end_matches('/');
+    let to = to.trim_end_matches('/');
+    if from.is_empty() {
+        bail!("--from must name a folder other than /.");
+    }
+    let find = Pattern::Path(from.to_string());
+    let regex = glob.map(glob_regex).transpose()?;
+
+    let mut domains: Vec<Domain> = Preferences::list_domains()?
+        .into_iter()
+        .chain([Domain::Global])
+        .filter(|d| regex.as_ref().is_none_or(|r| r.is_match(&d.get_cf_name())))
+        .collect();
+    domains.sort_by_key(|d| d.get_cf_name());
+
+    let mut tx = Transaction::new();
+    let mut count = 0;
+    for domain in domains {
+        let Ok(PrefValue::Dictionary(dict)) = Preferences::read_domain(domain.clone()) else {
+            continue;
+        };
+        let mut keys: Vec<String> = dict.keys().cloned().collect();
+        keys.sort();
+        for key in keys {
+            let Some(mut value) = dict.get(&key).cloned() else {
+                continue;
+            };
+            let mut sites = Vec::new();
+            replace_in(
+                &mut value,
+                &KeyPath::default().key(&key),
+                &find,
+                to,
+                &mut sites,
+            );
+            for (path, old, new) in &sites {
+                println!("{domain} {path}: {old} -> {new}");
+            }
+            if !sites.is_empty() {
+                count += sites.len();
+                tx.write(domain.clone(), &key, value);
+            }
+        }
+    }
+
+    if tx.is_empty() {
+        println!("No values refer to {from}.");
+    } else if !dry_run {
+        println!("{}", tx.commit()?.summary());
+        println!("Rewrote {count} path(s).");
+    }
+    Ok(())
+}
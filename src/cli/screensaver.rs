@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: MIT
+
+//! `drs screensaver`: the screen saver's idle time and clock, which are stored per Mac.
+
+use anyhow::{Result, bail};
+use std::time::Duration;
+
+use crate::ScreenSaver;
+
+use super::units::{self, Unit};
+
+/// Print the idle time, selected screen saver and clock setting.
+pub(crate) fn show() -> Result<()> {
+    let idle = match ScreenSaver::idle_time()? {
+        Some(idle) => units::humanize(idle.as_secs() as f64, Unit::Seconds),
+        None => "never".to_string(),
+    };
+    println!("Idle time:    {idle}");
+    println!(
+        "Screen saver: {}",
+        ScreenSaver::module()?.as_deref().unwrap_or("(default)")
+    );
+    let clock = if ScreenSaver::show_clock()? {
+        "on"
+    } else {
+        "off"
+    };
+    println!("Clock:        {clock}");
+    Ok(())
+}
+
+/// Set the idle time from a duration such as `10m` or `600`, or `never`.
+pub(crate) fn set_idle(raw: &str) -> Result<()> {
+    let idle = match raw {
+        "never" | "0" => None,
+        raw => {
+            let secs = units::parse_int(raw)?;
+            if secs <= 0 {
+                bail!("The idle time must be positive, or never.");
+            }
+            Some(Duration::from_secs(secs as u64))
+        }
+    };
+    ScreenSaver::set_idle_time(idle)?;
+    match idle {
+        Some(idle) => println!(
+            "The screen saver starts after {} of inactivity.",
+            units::humanize(idle.as_secs() as f64, Unit::Seconds)
+        ),
+        None => println!("The screen saver never starts."),
+    }
+    Ok(())
+}
+
+/// Show or hide the clock on the screen saver.
+pub(crate) fn set_clock(on: bool) -> Result<()> {
+    ScreenSaver::set_show_clock(on)?;
+    println!(
+        "The screen saver clock is {}.",
+        if on { "on" } else { "off" }
+    );
+    Ok(())
+}
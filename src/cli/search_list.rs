@@ -0,0 +1,73 @@
+// SPDX-License-Identifier: MIT
+
+//! `drs search-list`: the layers a read from a domain consults, in order.
+
+use std::env;
+
+use crate::{Domain, PrefLayer, Preferences};
+
+use super::host;
+
+/// Where the values of `layer` live on disk.
+fn location(domain: &Domain, layer: &PrefLayer, uuid: Option<&str>) -> String {
+    let name = if layer.global {
+        Domain::Global.get_cf_name()
+    } else {
+        domain.get_cf_name()
+    };
+
+    if layer.managed {
+        let user = env::var("USER").unwrap_or_else(|_| "$USER".to_string());
+        return match layer.any_user {
+            true => format!("/Library/Managed Preferences/{name}.plist"),
+            false => format!("/Library/Managed Preferences/{user}/{name}.plist"),
+        };
+    }
+
+    if *layer == PrefLayer::DEFAULT
+        && domain.in_container()
+        && let Some(path) = domain.plist_path()
+    {
+        return path.display().to_string();
+    }
+
+    let base = if layer.any_user {
+        "/Library/Preferences"
+    } else {
+        "~/Library/Preferences"
+    };
+    match layer.current_host {
+        true => format!("{base}/ByHost/{name}.{}.plist", uuid.unwrap_or("*")),
+        false => format!("{base}/{name}.plist"),
+    }
+}
+
+/// Print the search list of `domain`, along with the value each layer holds for `key`.
+pub(crate) fn print(domain: &Domain, key: Option<&str>) {
+    let uuid = host::hardware_uuid().ok();
+    let mut effective = false;
+
+    for (i, layer) in Preferences::search_list(domain).iter().enumerate() {
+        println!(
+            "{:>2}. {:<38} {}",
+            i + 1,
+            layer.to_string(),
+            location(domain, layer, uuid.as_deref())
+        );
+
+        let Some(key) = key else {
+            continue;
+        };
+        if let Some(value) = Preferences::read_layer(domain, key, layer) {
+            let marker = if effective { "" } else { " (effective)" };
+            effective = true;
+            println!("    {key} = {value}{marker}");
+        }
+    }
+
+    if let Some(key) = key
+        && !effective
+    {
+        println!("{key}: not set (app default)");
+    }
+}
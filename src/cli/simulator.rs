@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: MIT
+
+//! `--simulator`: preferences of iOS Simulator devices.
+//!
+//! Each device keeps its data under `~/Library/Developer/CoreSimulator/Devices/<UDID>/data`.
+//! System domains live in its `Library/Preferences`, while installed apps keep theirs in their
+//! data container. Domains are mapped to those plists as [`Domain::Path`]s, which are read and
+//! written directly; apps running in the simulator pick up changes when they are relaunched.
+
+use anyhow::{Context, Result, bail};
+use plist::Value;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::Domain;
+
+/// `state` of a booted device in its `device.plist`.
+const BOOTED: u64 = 3;
+
+fn devices_dir() -> Result<PathBuf> {
+    Ok(dirs::home_dir()
+        .context("Could not determine the home directory.")?
+        .join("Library/Developer/CoreSimulator/Devices"))
+}
+
+/// The value of `key` in the plist dictionary at `path`.
+fn field(path: &Path, key: &str) -> Option<Value> {
+    Value::from_file(path).ok()?.into_dictionary()?.remove(key)
+}
+
+/// The data directory of the device with the given UDID, or of the booted device for `booted`.
+fn data_dir(device: &str) -> Result<PathBuf> {
+    let devices = devices_dir()?;
+    if device != "booted" {
+        let dir = devices.join(device).join("data");
+        if !dir.is_dir() {
+            bail!("No such simulator device: {device}");
+        }
+        return Ok(dir);
+    }
+
+    let booted: Vec<PathBuf> = fs::read_dir(&devices)
+        .with_context(|| format!("failed to read {}", devices.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|dir| {
+            field(&dir.join("device.plist"), "state").and_then(|s| s.as_unsigned_integer())
+                == Some(BOOTED)
+        })
+        .collect();
+    match booted.as_slice() {
+        [dir] => Ok(dir.join("data")),
+        [] => bail!("No simulator device is booted."),
+        _ => bail!("More than one simulator device is booted; pass its UDID instead."),
+    }
+}
+
+/// The plist of the app `bundle_id` in its data container on the device, if it is installed.
+fn container_plist(data: &Path, bundle_id: &str) -> Option<PathBuf> {
+    fs::read_dir(data.join("Containers/Data/Application"))
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|dir| {
+            field(
+                &dir.join(".com.apple.mobile_container_manager.metadata.plist"),
+                "MCMMetadataIdentifier",
+            )
+            .is_some_and(|id| id.as_string() == Some(bundle_id))
+        })
+        .map(|dir| dir.join(format!("Library/Preferences/{bundle_id}.plist")))
+}
+
+/// `domain` on the simulator `device` (a UDID or `booted`), as a [`Domain::Path`].
+pub(crate) fn domain(device: &str, domain: &Domain) -> Result<Domain> {
+    let data = data_dir(device)?;
+    let name = match domain {
+        Domain::User(name) | Domain::Container(name) => name.clone(),
+        Domain::Global => domain.get_cf_name(),
+        other => bail!("{other} can't be used with --simulator."),
+    };
+
+    let own = data.join(format!("Library/Preferences/{name}.plist"));
+    if own.is_file() {
+        return Ok(Domain::Path(own));
+    }
+    Ok(Domain::Path(container_plist(&data, &name).unwrap_or(own)))
+}
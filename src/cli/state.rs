@@ -0,0 +1,217 @@
+// SPDX-License-Identifier: MIT
+
+//! The state left by the last successful `drs apply`.
+//!
+//! After a manifest applies cleanly, `state.plist` in the drs data directory records its sources
+//! with their SHA-256, and for every domain it manages the values it set and when the domain's
+//! plist was last modified. While a plist stays untouched, its recorded values are known to be
+//! current: the next `drs apply` skips them without reading the domain, and `drs status` reports
+//! the domain as clean. Only domains whose plist changed are read again, to tell changes to
+//! managed keys from changes elsewhere in the domain.
+
+use anyhow::{Context, Result, bail};
+use plist::Value;
+use std::{collections::BTreeMap, fs, io::Cursor, time::UNIX_EPOCH};
+
+use crate::{
+    Domain, PrefValue, Preferences,
+    core::file,
+    preferences::convert::{plist_to_prefvalue, prefvalue_to_plist},
+};
+
+use super::paths;
+
+/// The values drs manages in one domain.
+#[derive(Debug, Clone, Default)]
+struct Managed {
+    /// Modification time of the domain's plist after applying, in nanoseconds since the epoch.
+    modified: Option<i64>,
+    values: BTreeMap<String, PrefValue>,
+}
+
+/// The recorded state of the last apply.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct State {
+    /// Each manifest source applied, with its SHA-256.
+    sources: Vec<(String, String)>,
+    /// Managed values by domain name.
+    domains: BTreeMap<String, Managed>,
+}
+
+/// When the plist backing `domain` was last modified, if it exists.
+fn modified(domain: &Domain) -> Option<i64> {
+    let time = fs::metadata(domain.plist_path()?).ok()?.modified().ok()?;
+    Some(time.duration_since(UNIX_EPOCH).ok()?.as_nanos() as i64)
+}
+
+fn domain_of(name: &str) -> Domain {
+    match name {
+        ".GlobalPreferences" => Domain::Global,
+        other => Domain::User(other.to_string()),
+    }
+}
+
+impl State {
+    /// Start recording a new apply of the given sources, forgetting everything recorded before.
+    pub(crate) fn new(sources: Vec<(String, String)>) -> State {
+        State {
+            sources,
+            domains: BTreeMap::new(),
+        }
+    }
+
+    /// Read the state file, or an empty state if nothing was applied yet.
+    pub(crate) fn load() -> Result<State> {
+        let path = paths::state_file()?;
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(State::default()),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+
+        let Value::Dictionary(mut root) = Value::from_reader(Cursor::new(&data))? else {
+            bail!("{} must hold a dictionary.", path.display());
+        };
+        let mut state = State::default();
+        if let Some(Value::Array(sources)) = root.remove("sources") {
+            for source in sources {
+                let Value::Dictionary(source) = source else {
+                    continue;
+                };
+                let field = |key: &str| source.get(key).and_then(Value::as_string);
+                if let (Some(name), Some(digest)) = (field("source"), field("sha256")) {
+                    state.sources.push((name.to_string(), digest.to_string()));
+                }
+            }
+        }
+        if let Some(Value::Dictionary(domains)) = root.remove("domains") {
+            for (name, managed) in domains {
+                let Value::Dictionary(mut managed) = managed else {
+                    continue;
+                };
+                let values = match managed.remove("values") {
+                    Some(Value::Dictionary(values)) => values
+                        .into_iter()
+                        .map(|(k, v)| Ok((k, plist_to_prefvalue(&v)?)))
+                        .collect::<Result<_>>()?,
+                    _ => BTreeMap::new(),
+                };
+                let modified = managed.get("modified").and_then(Value::as_signed_integer);
+                state.domains.insert(name, Managed { modified, values });
+            }
+        }
+        Ok(state)
+    }
+
+    /// Write the state file.
+    pub(crate) fn save(&self) -> Result<()> {
+        fs::create_dir_all(paths::data_dir()?)?;
+
+        let sources: Vec<Value> = self
+            .sources
+            .iter()
+            .map(|(source, digest)| {
+                let mut dict = plist::Dictionary::new();
+                dict.insert("source".into(), Value::String(source.clone()));
+                dict.insert("sha256".into(), Value::String(digest.clone()));
+                Value::Dictionary(dict)
+            })
+            .collect();
+        let domains: plist::Dictionary = self
+            .domains
+            .iter()
+            .map(|(name, managed)| {
+                let values: plist::Dictionary = managed
+                    .values
+                    .iter()
+                    .map(|(k, v)| (k.clone(), prefvalue_to_plist(v)))
+                    .collect();
+                let mut dict = plist::Dictionary::new();
+                if let Some(modified) = managed.modified {
+                    dict.insert("modified".into(), Value::Integer(modified.into()));
+                }
+                dict.insert("values".into(), Value::Dictionary(values));
+                (name.clone(), Value::Dictionary(dict))
+            })
+            .collect();
+
+        let mut root = plist::Dictionary::new();
+        root.insert("sources".into(), Value::Array(sources));
+        root.insert("domains".into(), Value::Dictionary(domains));
+        let mut buf = Vec::new();
+        Value::Dictionary(root).to_writer_xml(&mut buf)?;
+        file::write_atomic(&paths::state_file()?, &buf)
+    }
+
+    /// Whether `key` in `domain` is known to still hold `value`: drs set it last time, and the
+    /// domain's plist hasn't been modified since.
+    pub(crate) fn holds(&self, domain: &Domain, key: &str, value: &PrefValue) -> bool {
+        self.domains
+            .get(&domain.get_cf_name())
+            .filter(|managed| managed.modified.is_some() && managed.modified == modified(domain))
+            .and_then(|managed| managed.values.get(key))
+            .is_some_and(|recorded| recorded == value)
+    }
+
+    /// Record the values applied to `domain`, once they were written.
+    pub(crate) fn record(&mut self, domain: &Domain, values: &[(String, PrefValue)]) {
+        let managed = self.domains.entry(domain.get_cf_name()).or_default();
+        managed.modified = modified(domain);
+        managed
+            .values
+            .extend(values.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+}
+
+/// Summarize how the domains managed by the last apply drifted since.
+///
+/// Domains whose plist is untouched are reported as clean without being read.
+pub(crate) fn status() -> Result<()> {
+    let state = State::load()?;
+    if state.domains.is_empty() {
+        println!("Nothing was applied yet.");
+        return Ok(());
+    }
+
+    for (source, digest) in &state.sources {
+        println!("Applied {source} (SHA-256 {digest})");
+    }
+    println!();
+
+    let (mut clean, mut drifted, mut unmanaged) = (0, 0, 0);
+    for (name, managed) in &state.domains {
+        let domain = domain_of(name);
+        if managed.modified.is_some() && managed.modified == modified(&domain) {
+            println!("  {domain}: clean");
+            clean += 1;
+            continue;
+        }
+
+        let keys: Vec<&str> = managed.values.keys().map(String::as_str).collect();
+        let live = Preferences::read_batch(domain.clone(), &keys).unwrap_or_default();
+        let changes: Vec<String> = managed
+            .values
+            .iter()
+            .filter_map(|(key, value)| match live.get(key) {
+                Some(now) if now == value => None,
+                Some(now) => Some(format!("    {key}: {value} -> {now}")),
+                None => Some(format!("    {key}: {value} -> (deleted)")),
+            })
+            .collect();
+
+        if changes.is_empty() {
+            println!("  {domain}: changed outside the manifest");
+            unmanaged += 1;
+        } else {
+            println!("~ {domain}: {} managed key(s) drifted", changes.len());
+            for change in changes {
+                println!("{change}");
+            }
+            drifted += 1;
+        }
+    }
+
+    println!();
+    println!("{clean} clean, {drifted} drifted, {unmanaged} changed outside the manifest");
+    Ok(())
+}
@@ -0,0 +1,155 @@
+// SPDX-License-Identifier: MIT
+
+//! Durations and sizes written with units, e.g. `250ms`, `1.5h` or `2GiB`.
+//!
+//! Durations are stored in seconds and sizes in bytes, which is what preference keys holding
+//! either conventionally use. Decimal size suffixes (`KB`, `MB`, ...) are powers of 1000, binary
+//! ones (`KiB`, `MiB`, ...) powers of 1024.
+//!
+//! Numbers are always parsed with `.` as the decimal separator, independent of the user's locale.
+
+use anyhow::{Result, anyhow, bail};
+
+/// What a numeric preference value measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Unit {
+    /// A duration in seconds.
+    Seconds,
+    /// A size in bytes.
+    Bytes,
+}
+
+/// Unit suffixes and their factor to seconds or bytes.
+const SUFFIXES: &[(&str, f64)] = &[
+    ("ns", 1e-9),
+    ("us", 1e-6),
+    ("ms", 1e-3),
+    ("s", 1.0),
+    ("m", 60.0),
+    ("min", 60.0),
+    ("h", 3600.0),
+    ("d", 86400.0),
+    ("B", 1.0),
+    ("KB", 1e3),
+    ("MB", 1e6),
+    ("GB", 1e9),
+    ("TB", 1e12),
+    ("KiB", 1024.0),
+    ("MiB", 1048576.0),
+    ("GiB", 1073741824.0),
+    ("TiB", 1099511627776.0),
+];
+
+/// Rejects numbers using `,` as a decimal or thousands separator, which would be ambiguous.
+pub(crate) fn ensure_plain_number(raw: &str) -> Result<()> {
+    if raw.contains(',') {
+        bail!(
+            "Ambiguous number `{raw}`: use `.` as the decimal separator and leave out thousands separators."
+        )
+    }
+    Ok(())
+}
+
+/// Splits `raw` into its number and the factor of its unit suffix.
+fn split_unit(raw: &str) -> Result<(&str, f64)> {
+    let number = raw.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let suffix = &raw[number.len()..];
+
+    let (_, factor) = SUFFIXES
+        .iter()
+        .find(|(s, _)| *s == suffix)
+        .ok_or_else(|| anyhow!("Unknown unit `{suffix}` in: {raw}"))?;
+
+    Ok((number.trim_end(), *factor))
+}
+
+/// Parses an integer, optionally followed by a duration or size unit.
+pub fn parse_int(raw: &str) -> Result<i64> {
+    ensure_plain_number(raw)?;
+    if let Ok(i) = raw.parse::<i64>() {
+        return Ok(i);
+    }
+
+    let (number, factor) = split_unit(raw).map_err(|e| anyhow!("Failed to parse int: {e}"))?;
+
+    if let Ok(i) = number.parse::<i64>()
+        && factor.fract() == 0.0
+    {
+        return i
+            .checked_mul(factor as i64)
+            .ok_or_else(|| anyhow!("{raw} is too large for an integer"));
+    }
+
+    let value = number
+        .parse::<f64>()
+        .map_err(|e| anyhow!("Failed to parse int: {e}"))?
+        * factor;
+    if value.fract() != 0.0 || value < i64::MIN as f64 || value > i64::MAX as f64 {
+        bail!("{raw} is not a whole number in its base unit (seconds or bytes)")
+    }
+    Ok(value as i64)
+}
+
+/// Parses a float, optionally followed by a duration or size unit.
+///
+/// `inf`, `NaN` and results too large for an `f64` are rejected.
+pub fn parse_float(raw: &str) -> Result<f64> {
+    ensure_plain_number(raw)?;
+    let value = match raw.parse::<f64>() {
+        Ok(f) => f,
+        Err(_) => {
+            let (number, factor) =
+                split_unit(raw).map_err(|e| anyhow!("Failed to parse float: {e}"))?;
+            number
+                .parse::<f64>()
+                .map_err(|e| anyhow!("Failed to parse float: {e}"))?
+                * factor
+        }
+    };
+    if !value.is_finite() {
+        bail!("{raw} is not a finite number")
+    }
+    Ok(value)
+}
+
+/// Renders a number of seconds or bytes using the largest unit that keeps it at or above 1.
+pub(crate) fn humanize(value: f64, unit: Unit) -> String {
+    let (base, candidates): (&str, &[&str]) = match unit {
+        Unit::Seconds => ("s", &["d", "h", "min", "s", "ms", "us", "ns"]),
+        Unit::Bytes => ("B", &["TiB", "GiB", "MiB", "KiB", "B"]),
+    };
+
+    let (suffix, factor) = candidates
+        .iter()
+        .filter_map(|c| SUFFIXES.iter().find(|(s, _)| s == c))
+        .copied()
+        .find(|(_, f)| value.abs() >= *f)
+        .unwrap_or((base, 1.0));
+
+    let scaled = format!("{:.2}", value / factor);
+    let scaled = scaled.trim_end_matches('0').trim_end_matches('.');
+    format!("{scaled}{suffix}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PrefValue;
+
+    #[test]
+    fn floats_round_trip() -> Result<()> {
+        for f in [0.1, 1.0 / 3.0, 1e-300] {
+            assert_eq!(parse_float(&f.to_string())?, f);
+            assert_eq!(parse_float(&format!("{f:?}"))?, f);
+            assert_eq!(parse_float(&PrefValue::Float(f).to_string())?, f);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_ambiguous_and_non_finite_floats() {
+        for raw in ["1,5", "inf", "-inf", "NaN", "infinity", "1e309", "1e300TiB"] {
+            assert!(parse_float(raw).is_err(), "{raw} was accepted");
+        }
+    }
+}
@@ -0,0 +1,322 @@
+// SPDX-License-Identifier: MIT
+
+//! Shared helpers for the CLI.
+//!
+//! Path arguments are normalized and booleans and dates parsed here so every subcommand treats
+//! them the same way.
+
+use anyhow::{Result, anyhow, bail};
+use std::{io::Read, path::PathBuf};
+
+use crate::core::types::{APPLE_DATE_RANGE, APPLE_EPOCH};
+
+/// Expands a leading `~` and any `$VAR` / `${VAR}` references in the given string.
+pub(crate) fn expand_vars(input: &str) -> Result<String> {
+    let mut out = String::with_capacity(input.len());
+
+    let rest = if input == "~" || input.starts_with("~/") {
+        let home = dirs::home_dir().ok_or_else(|| anyhow!("could not resolve home directory"))?;
+        out.push_str(&home.to_string_lossy());
+        &input[1..]
+    } else {
+        input
+    };
+
+    let mut chars = rest.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            out.push(c);
+            continue;
+        }
+
+        let braced = chars.next_if_eq(&'{').is_some();
+        let mut name = String::new();
+        while let Some(n) = chars.next_if(|n| n.is_ascii_alphanumeric() || *n == '_') {
+            name.push(n);
+        }
+
+        if braced && chars.next_if_eq(&'}').is_none() {
+            bail!("Unterminated variable reference in: {input}");
+        }
+        if name.is_empty() {
+            if braced {
+                bail!("Empty variable reference in: {input}");
+            }
+            out.push('$');
+            continue;
+        }
+
+        let value = std::env::var(&name)
+            .map_err(|_| anyhow!("Environment variable `{name}` is not set (in: {input})"))?;
+        out.push_str(&value);
+    }
+
+    Ok(out)
+}
+
+/// Parses a boolean. `true`/`false`, `yes`/`no`, `y`/`n`, `on`/`off` and `1`/`0` are accepted in
+/// any case, or only `true`/`false` when `strict` is set.
+pub(crate) fn parse_bool(raw: &str, strict: bool) -> Result<bool> {
+    if strict {
+        return match raw {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            _ => bail!(
+                "Invalid boolean value `{raw}` (only true/false is accepted with --strict-bool)"
+            ),
+        };
+    }
+
+    match raw.to_lowercase().as_str() {
+        "true" | "yes" | "y" | "on" | "1" => Ok(true),
+        "false" | "no" | "n" | "off" | "0" => Ok(false),
+        _ => bail!("Invalid boolean value `{raw}` (use true/false, yes/no, y/n, on/off or 1/0)"),
+    }
+}
+
+/// Parses a date into seconds since 2001-01-01, as [`PrefValue::Date`](crate::PrefValue::Date)
+/// stores it.
+///
+/// ISO 8601 dates with an offset (`2024-05-01T12:00:00Z`) are taken as they are. Without an offset,
+/// `2024-05-01 12:00`, `2024-05-01T12:00:00` and plain `2024-05-01` are in local time. `now` and
+/// Unix timestamps like `@1714564800` are accepted too. Dates must fall in the years 1 to 9999.
+pub(crate) fn parse_date(raw: &str) -> Result<f64> {
+    let apple_ts = parse_any_date(raw)?;
+    if !APPLE_DATE_RANGE.contains(&apple_ts) {
+        bail!("Date `{raw}` is outside of the years 1 to 9999.")
+    }
+    Ok(apple_ts)
+}
+
+fn parse_any_date(raw: &str) -> Result<f64> {
+    use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+
+    let apple = |date: DateTime<chrono::FixedOffset>| {
+        (date.timestamp() - APPLE_EPOCH) as f64 + date.timestamp_subsec_nanos() as f64 / 1e9
+    };
+    let raw = raw.trim();
+
+    if raw == "now" {
+        return Ok(apple(Local::now().fixed_offset()));
+    }
+    if let Some(secs) = raw.strip_prefix('@') {
+        let secs: f64 = secs
+            .parse()
+            .map_err(|_| anyhow!("Invalid Unix timestamp `{raw}`"))?;
+        return Ok(secs - APPLE_EPOCH as f64);
+    }
+    if let Ok(date) = DateTime::parse_from_rfc3339(&raw.replacen(' ', "T", 1)) {
+        return Ok(apple(date));
+    }
+
+    let naive = [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(raw, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+            .ok()
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+    })
+    .ok_or_else(|| {
+        anyhow!("Invalid date `{raw}` (use e.g. 2024-05-01T12:00:00Z, 2024-05-01 12:00 or now)")
+    })?;
+    match Local.from_local_datetime(&naive).single() {
+        Some(date) => Ok(apple(date.fixed_offset())),
+        None => bail!("{raw} is ambiguous or doesn't exist in the local time zone."),
+    }
+}
+
+/// `raw`, or everything read from stdin if it is `-`. A single trailing newline is dropped from
+/// stdin, as shells do for `$(cat file)`.
+pub(crate) fn stdin_or(raw: &str) -> Result<String> {
+    if raw != "-" {
+        return Ok(raw.to_string());
+    }
+    let mut text = String::new();
+    std::io::stdin()
+        .read_to_string(&mut text)
+        .map_err(|e| anyhow!("failed to read the value from stdin: {e}"))?;
+    if text.ends_with('\n') {
+        text.pop();
+        if text.ends_with('\r') {
+            text.pop();
+        }
+    }
+    Ok(text)
+}
+
+/// Parses hex bytes as `defaults write -data` takes them. Whitespace and the `<`/`>` around
+/// old-style plist data (`<0a1b 2c3d>`) are ignored.
+pub(crate) fn parse_hex(raw: &str) -> Result<Vec<u8>> {
+    let digits: Vec<u8> = raw
+        .trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+    if !digits.len().is_multiple_of(2) {
+        bail!("Invalid hex data: odd number of digits");
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| anyhow!("Invalid hex data: `{}`", String::from_utf8_lossy(pair)))
+        })
+        .collect()
+}
+
+/// Parses base64 in the standard or URL-safe alphabet. Padding is optional and whitespace, as in
+/// wrapped output of `base64`, is ignored.
+pub(crate) fn parse_base64(raw: &str) -> Result<Vec<u8>> {
+    let sextet = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    };
+    let text: Vec<u8> = raw.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    let text = text
+        .strip_suffix(b"==")
+        .or_else(|| text.strip_suffix(b"="))
+        .unwrap_or(&text);
+    if text.len() % 4 == 1 {
+        bail!("Invalid base64 data: truncated");
+    }
+
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut bits, mut count) = (0u32, 0);
+    for &c in text {
+        let value = sextet(c)
+            .ok_or_else(|| anyhow!("Invalid base64 data: unexpected `{}`", char::from(c)))?;
+        bits = bits << 6 | u32::from(value);
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+        }
+    }
+    Ok(out)
+}
+
+/// Parses a URL for [`PrefValue::Url`](crate::PrefValue::Url). Absolute paths, including ones
+/// starting with `~`, become `file://` URLs; anything else needs a scheme, such as `https:`.
+pub(crate) fn parse_url(raw: &str) -> Result<String> {
+    let raw = raw.trim();
+    if raw.starts_with('/') || raw.starts_with('~') {
+        let path = expand_vars(raw)?;
+        let mut url = String::from("file://");
+        for b in path.bytes() {
+            match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'.' | b'_' | b'~' => {
+                    url.push(char::from(b))
+                }
+                _ => url.push_str(&format!("%{b:02X}")),
+            }
+        }
+        return Ok(url);
+    }
+
+    let scheme = raw.split_once(':').map(|(scheme, _)| scheme);
+    let valid_scheme = scheme.is_some_and(|s| {
+        s.starts_with(|c: char| c.is_ascii_alphabetic())
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+    if !valid_scheme {
+        bail!(
+            "Invalid URL `{raw}`: it needs a scheme such as https: or file:, or an absolute path"
+        );
+    }
+    if let Some(c) = raw.chars().find(|c| {
+        c.is_whitespace()
+            || c.is_control()
+            || !c.is_ascii()
+            || matches!(c, '"' | '<' | '>' | '\\' | '^' | '`' | '{' | '|' | '}')
+    }) {
+        bail!("Invalid URL `{raw}`: `{c}` must be percent-encoded");
+    }
+    Ok(raw.to_string())
+}
+
+/// Parses a UUID such as `01234567-89AB-CDEF-0123-456789ABCDEF`, uppercased as CoreFoundation
+/// stores it.
+pub(crate) fn parse_uuid(raw: &str) -> Result<String> {
+    let raw = raw.trim();
+    if !crate::preferences::translate::is_uuid(raw.as_bytes()) {
+        bail!("Invalid UUID `{raw}` (use e.g. 01234567-89AB-CDEF-0123-456789ABCDEF)");
+    }
+    Ok(raw.to_ascii_uppercase())
+}
+
+/// A regex matching whole strings against a glob where `*` matches any run of characters and `?`
+/// a single one.
+pub(crate) fn glob_regex(glob: &str) -> Result<regex::Regex> {
+    let pattern = regex::escape(glob).replace(r"\*", ".*").replace(r"\?", ".");
+    Ok(regex::Regex::new(&format!("^{pattern}$"))?)
+}
+
+/// Normalizes a path argument: expands `~` and environment variables, then makes it absolute.
+pub(crate) fn normalize_path(input: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(expand_vars(input)?);
+
+    if path.is_absolute() {
+        Ok(path)
+    } else {
+        Ok(std::env::current_dir()?.join(path))
+    }
+}
+
+/// Expands a path-like preference value, leaving anything else untouched.
+pub(crate) fn expand_value(input: &str) -> Result<String> {
+    if input.starts_with("./") || input.starts_with("../") {
+        Ok(normalize_path(input)?.to_string_lossy().into_owned())
+    } else {
+        expand_vars(input)
+    }
+}
+
+/// Whether an environment variable is set to a truthy value.
+pub(crate) fn env_flag(name: &str) -> bool {
+    std::env::var(name)
+        .is_ok_and(|v| !matches!(v.to_lowercase().as_str(), "" | "0" | "false" | "no"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dates() -> Result<()> {
+        assert_eq!(parse_date("2024-05-01T12:00:00Z")?, 736_257_600.0);
+        assert_eq!(parse_date("2024-05-01 12:00:00+00:00")?, 736_257_600.0);
+        assert_eq!(parse_date("@1714564800")?, 736_257_600.0);
+        assert_eq!(parse_date("@978307200.5")?, 0.5);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unrepresentable_dates() {
+        for raw in [
+            "@inf",
+            "@-inf",
+            "@nan",
+            "@1e300",
+            "@-1e300",
+            "0000-01-01T00:00:00Z",
+            "tomorrow",
+        ] {
+            assert!(parse_date(raw).is_err(), "{raw} was accepted");
+        }
+    }
+}
@@ -0,0 +1,209 @@
+// SPDX-License-Identifier: MIT
+
+//! Text representations of [`PrefValue`].
+//!
+//! The `Display` implementation of [`PrefValue`] is the plain style: strings are printed as-is,
+//! which reads naturally but can't tell the string `"1"` from the integer `1`. The typed style
+//! has the following grammar, where every value's type can be told from its text alone:
+//!
+//! | Type       | Typed form                                        |
+//! |------------|---------------------------------------------------|
+//! | string     | `"text"`, escaping `"`, `\` and control characters |
+//! | integer    | `42`                                              |
+//! | float      | `42.0`, `1e300`, `NaN`, `inf` (never a bare integer) |
+//! | boolean    | `true`, `false`                                   |
+//! | array      | `[a, b]`                                          |
+//! | dictionary | `{"key": value}`, sorted by key                   |
+//! | data       | `<0a1b2c>`                                        |
+//! | date       | `date("2001-01-01T00:00:00Z")`                    |
+//! | url        | `url("https://example.com")`                      |
+//! | uuid       | `uuid("68753A44-4D6F-1226-9C60-0050E4C00067")`    |
+//! | uid        | `uid(7)`                                          |
+//!
+//! The Apple style mirrors the output of `defaults read`.
+
+use std::fmt::Write;
+
+use crate::{KeyPath, PrefValue};
+
+/// How [`PrefValue::to_display_string`] renders a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisplayStyle {
+    /// The same as `Display`: readable, but ambiguous between types.
+    #[default]
+    Plain,
+    /// Unambiguous: the type of every value can be told from its text.
+    Typed,
+    /// Indented, in the style of `defaults read`.
+    Apple,
+}
+
+impl PrefValue {
+    /// Render the value in the given style.
+    pub fn to_display_string(&self, style: DisplayStyle) -> String {
+        match style {
+            DisplayStyle::Plain => self.to_string(),
+            DisplayStyle::Typed => {
+                let mut out = String::new();
+                typed(self, &mut out);
+                out
+            }
+            DisplayStyle::Apple => apple(self, &KeyPath::default(), 0, &|_, _| None),
+        }
+    }
+}
+
+/// A string in double quotes, with quotes, backslashes and control characters escaped.
+fn quoted(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{{{:x}}}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn typed(val: &PrefValue, out: &mut String) {
+    match val {
+        PrefValue::String(s) => quoted(s, out),
+        PrefValue::Integer(i) => {
+            let _ = write!(out, "{i}");
+        }
+        PrefValue::Float(f) => {
+            let _ = write!(out, "{f:?}");
+        }
+        PrefValue::Boolean(b) => {
+            let _ = write!(out, "{b}");
+        }
+        PrefValue::Array(arr) => {
+            out.push('[');
+            for (i, v) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                typed(v, out);
+            }
+            out.push(']');
+        }
+        PrefValue::Dictionary(dict) => {
+            let mut entries: Vec<_> = dict.iter().collect();
+            entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (k, v)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                quoted(k, out);
+                out.push_str(": ");
+                typed(v, out);
+            }
+            out.push('}');
+        }
+        PrefValue::Data(data) => {
+            out.push('<');
+            for b in data.iter() {
+                let _ = write!(out, "{b:02x}");
+            }
+            out.push('>');
+        }
+        PrefValue::Date(ts) => {
+            out.push_str("date(");
+            quoted(&super::types::apple_date_text(*ts), out);
+            out.push(')');
+        }
+        PrefValue::Url(url) => {
+            out.push_str("url(");
+            quoted(url, out);
+            out.push(')');
+        }
+        PrefValue::Uuid(uuid) => {
+            out.push_str("uuid(");
+            quoted(uuid, out);
+            out.push(')');
+        }
+        PrefValue::Uid(uid) => {
+            let _ = write!(out, "uid({uid})");
+        }
+    }
+}
+
+/// Render `val`, found at `path`, in the style of `defaults read`.
+///
+/// `render` is asked first for every nested value and may return its own text for it.
+pub(crate) fn apple(
+    val: &PrefValue,
+    path: &KeyPath,
+    indent: usize,
+    render: &dyn Fn(&KeyPath, &PrefValue) -> Option<String>,
+) -> String {
+    if let Some(rendered) = render(path, val) {
+        return rendered;
+    }
+
+    let ind = |n| "    ".repeat(n);
+    match val {
+        PrefValue::Dictionary(dict) => {
+            let mut out = String::new();
+            out.push_str("{\n");
+            for (k, v) in dict {
+                out.push_str(&format!(
+                    "{}{} = {}",
+                    ind(indent + 1),
+                    quote_word(k),
+                    apple(v, &path.key(k), indent + 1, render)
+                ));
+                out.push(';');
+                out.push('\n');
+            }
+            out.push_str(&format!("{}}}", ind(indent)));
+            out
+        }
+        PrefValue::Array(arr) => {
+            let mut out = String::new();
+            out.push_str("(\n");
+            for (i, v) in arr.iter().enumerate() {
+                out.push_str(&ind(indent + 1));
+                out.push_str(&apple(v, &path.index(i), indent + 1, render));
+                out.push(',');
+                out.push('\n');
+            }
+            out.push_str(&format!("{})", ind(indent)));
+            out
+        }
+        PrefValue::String(s) => quote_word(s),
+        PrefValue::Boolean(b) => b.to_string(),
+        PrefValue::Data(data) => {
+            let mut s = data
+                .iter()
+                .map(|b| format!("0x{:02X}", b))
+                .collect::<Vec<_>>();
+            s.truncate(5);
+
+            format!("<length = {}, bytes = [{}...]>", data.len(), s.join(", "))
+        }
+        PrefValue::Url(url) => format!("<URL: {}>", url),
+        PrefValue::Uuid(uuid) => format!("<UUID: {}>", uuid),
+        PrefValue::Uid(uid) => format!("<UID: {}>", uid),
+        _ => val.to_string(),
+    }
+}
+
+/// Quotes a key or string for Apple-style output, unless it is a single plain word.
+fn quote_word(s: &str) -> String {
+    if s.chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        s.to_string()
+    } else {
+        format!("\"{}\"", s.replace('"', "\\\""))
+    }
+}
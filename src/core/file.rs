@@ -0,0 +1,215 @@
+// SPDX-License-Identifier: MIT
+
+//! File helpers shared by everything that writes plist files to disk.
+//!
+//! Files are never written in place: data goes to a temp file next to the destination which is
+//! then renamed over it, so an interrupted run leaves the destination untouched. Temp and lock
+//! files carry the owning process ID, which lets later runs tell stale leftovers apart from files
+//! still in use.
+//!
+//! Plists are parsed into memory as a whole, so files above a configurable size are refused with
+//! [`FileTooLarge`] before they are read. Some apps keep caches of hundreds of megabytes in their
+//! preferences folder, which would otherwise get drs killed for running out of memory.
+
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// Suffix of temp files created by [`write_atomic`].
+pub(crate) const TEMP_SUFFIX: &str = ".drs.tmp";
+
+/// Suffix of lock files owned by a drs process.
+pub(crate) const LOCK_SUFFIX: &str = ".drs.lock";
+
+/// Default for [`max_size`]: 64 MiB, far above any regular preferences file.
+pub(crate) const DEFAULT_MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+static MAX_SIZE: AtomicU64 = AtomicU64::new(DEFAULT_MAX_SIZE);
+
+/// Error returned when a plist is larger than the configured maximum size.
+#[derive(Debug)]
+pub struct FileTooLarge {
+    /// The refused file.
+    pub path: PathBuf,
+    /// Its size in bytes.
+    pub size: u64,
+    /// The maximum size in bytes.
+    pub limit: u64,
+}
+
+impl std::fmt::Display for FileTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Refused to load {}: it is {} bytes, above the limit of {} bytes.",
+            self.path.display(),
+            self.size,
+            self.limit
+        )
+    }
+}
+
+impl std::error::Error for FileTooLarge {}
+
+/// Set the maximum size of plists drs loads; `0` removes the limit.
+pub(crate) fn set_max_size(bytes: u64) {
+    MAX_SIZE.store(bytes, Ordering::SeqCst);
+}
+
+pub(crate) fn max_size() -> u64 {
+    MAX_SIZE.load(Ordering::SeqCst)
+}
+
+/// Fails with [`FileTooLarge`] if `path` exceeds the maximum size. Missing files pass.
+pub(crate) fn check_size(path: &Path) -> Result<()> {
+    let limit = max_size();
+    let Ok(meta) = fs::metadata(path) else {
+        return Ok(());
+    };
+    if limit > 0 && meta.len() > limit {
+        return Err(FileTooLarge {
+            path: path.to_path_buf(),
+            size: meta.len(),
+            limit,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Read a whole file after checking it against the maximum size.
+pub(crate) fn read_limited(path: &Path) -> Result<Vec<u8>> {
+    check_size(path)?;
+    fs::read(path).with_context(|| format!("failed to read {}", path.display()))
+}
+
+unsafe extern "C" {
+    fn kill(pid: i32, sig: i32) -> i32;
+    fn flock(fd: i32, operation: i32) -> i32;
+    fn geteuid() -> u32;
+}
+
+/// `LOCK_EX` from `<sys/file.h>`.
+const LOCK_EX: i32 = 2;
+
+/// An exclusive lock on the directory of a file, released when dropped.
+pub(crate) struct DirLock {
+    _handle: fs::File,
+}
+
+/// Take an exclusive lock on the directory holding `path`, waiting for other holders.
+///
+/// Locking the directory rather than the file survives [`write_atomic`] replacing the file, and
+/// the kernel drops the lock if the process dies, so it can never go stale.
+pub(crate) fn lock_dir(path: &Path) -> Result<DirLock> {
+    use std::os::fd::AsRawFd;
+
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let handle =
+        fs::File::open(dir).with_context(|| format!("failed to open {}", dir.display()))?;
+    if unsafe { flock(handle.as_raw_fd(), LOCK_EX) } != 0 {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("failed to lock {}", dir.display()));
+    }
+    Ok(DirLock { _handle: handle })
+}
+
+/// The user and group owning `path`, if it exists.
+pub(crate) fn owner(path: &Path) -> Option<(u32, u32)> {
+    use std::os::unix::fs::MetadataExt;
+
+    fs::metadata(path).ok().map(|m| (m.uid(), m.gid()))
+}
+
+/// Hand `path` to the given user and group when running as root.
+///
+/// Files root writes into a user's home would otherwise end up owned by root, and that user's
+/// apps couldn't update them anymore.
+pub(crate) fn set_owner(path: &Path, (uid, gid): (u32, u32)) -> Result<()> {
+    if unsafe { geteuid() } != 0 {
+        return Ok(());
+    }
+    std::os::unix::fs::chown(path, Some(uid), Some(gid))
+        .with_context(|| format!("failed to change the owner of {}", path.display()))
+}
+
+/// Returns the temp file path used while writing `path`.
+fn temp_path_for(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    path.with_file_name(format!(".{name}.{}{TEMP_SUFFIX}", std::process::id()))
+}
+
+/// Atomically write `data` to `path` via a temp file in the destination directory.
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    clean_stale_files(dir)?;
+
+    let temp = temp_path_for(path);
+    let written = write_and_rename(&temp, path, data);
+
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written.with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn write_and_rename(temp: &Path, path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut file = fs::File::create(temp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(temp, path)
+}
+
+/// Returns the owning process ID of a drs temp or lock file, if the name is one.
+fn owner_pid(name: &str) -> Option<i32> {
+    let stem = name
+        .strip_suffix(TEMP_SUFFIX)
+        .or_else(|| name.strip_suffix(LOCK_SUFFIX))?;
+
+    stem.rsplit_once('.')?.1.parse().ok()
+}
+
+/// Whether a process with the given ID is still running.
+fn is_alive(pid: i32) -> bool {
+    pid == std::process::id() as i32 || unsafe { kill(pid, 0) } == 0
+}
+
+/// Remove drs temp and lock files in `dir` whose owning process is no longer running.
+///
+/// Returns the paths that were removed.
+pub(crate) fn clean_stale_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(removed),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", dir.display())),
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(pid) = owner_pid(&name.to_string_lossy()) else {
+            continue;
+        };
+
+        if !is_alive(pid) && fs::remove_file(entry.path()).is_ok() {
+            removed.push(entry.path());
+        }
+    }
+
+    Ok(removed)
+}
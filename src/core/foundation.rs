@@ -5,8 +5,17 @@
 //! Provides minimal CFPreferences integration:
 //! - Domain listing
 //! - Single key read / whole domain read
+//! - Any host, or the current host only for ByHost domains
 //! - Write key
 //! - Delete key / whole domain
+//! - Single key read from one layer of the search list
+//!
+//! [`Domain::Path`] domains are handed to the file backend in [`super::plist_file`] instead.
+//!
+//! The domain-level functions are built on scoped wrappers taking an explicit user and host, which
+//! are also exposed as [`crate::raw`] behind the `unstable-raw` feature.
+//!
+//! All mutating functions refuse to run while read-only mode is enabled.
 
 use anyhow::{Result, bail};
 use std::collections::{HashMap, HashSet};
@@ -17,128 +26,345 @@ use core_foundation::{
 };
 
 use core_foundation_sys::{
-    array::{CFArrayGetCount, CFArrayGetValueAtIndex},
+    array::{CFArrayGetCount, CFArrayGetValueAtIndex, CFArrayRef},
+    base::CFRelease,
     preferences::{
-        CFPreferencesAppSynchronize, CFPreferencesCopyAppValue, CFPreferencesCopyApplicationList,
-        CFPreferencesCopyKeyList, CFPreferencesSetAppValue, kCFPreferencesAnyHost,
+        CFPreferencesAppSynchronize, CFPreferencesAppValueIsForced, CFPreferencesCopyAppValue,
+        CFPreferencesCopyApplicationList, CFPreferencesCopyKeyList, CFPreferencesCopyValue,
+        CFPreferencesSetAppValue, CFPreferencesSetValue, CFPreferencesSynchronize,
+        kCFPreferencesAnyHost, kCFPreferencesAnyUser, kCFPreferencesCurrentHost,
         kCFPreferencesCurrentUser,
     },
-    string::CFStringGetTypeID,
+    propertylist::CFPropertyListRef,
+    string::{CFStringGetTypeID, CFStringRef},
 };
 
-use crate::core::{
-    convert::{cf_to_pref, pref_to_cf},
-    types::PrefValue,
+use crate::{
+    Domain,
+    core::{
+        convert::{cf_to_pref, pref_to_cf},
+        guard::ensure_writable,
+        plist_file,
+        types::{PrefLayer, PrefValue},
+    },
 };
 
-/// List all preference application IDs (domains) for CurrentUser / AnyHost.
-pub(crate) fn list_domains() -> Result<HashSet<String>> {
-    unsafe {
-        let arr_ref =
-            CFPreferencesCopyApplicationList(kCFPreferencesCurrentUser, kCFPreferencesAnyHost);
-        if arr_ref.is_null() {
-            bail!("Couldn't copy application list from CoreFoundation API.")
+/// The application ID matching every application, i.e. the global domain.
+pub const ANY_APPLICATION: &str = "kCFPreferencesAnyApplication";
+
+/// The user a preference is stored for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum User {
+    /// The user running this process (`kCFPreferencesCurrentUser`).
+    Current,
+    /// Every user of the Mac (`kCFPreferencesAnyUser`), stored under `/Library/Preferences`.
+    Any,
+}
+
+/// The host a preference is stored for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Host {
+    /// This Mac only (`kCFPreferencesCurrentHost`), stored in a ByHost plist.
+    Current,
+    /// Any Mac (`kCFPreferencesAnyHost`).
+    Any,
+}
+
+impl User {
+    fn as_cf(self) -> CFStringRef {
+        unsafe {
+            match self {
+                User::Current => kCFPreferencesCurrentUser,
+                User::Any => kCFPreferencesAnyUser,
+            }
         }
+    }
+}
+
+impl Host {
+    fn as_cf(self) -> CFStringRef {
+        unsafe {
+            match self {
+                Host::Current => kCFPreferencesCurrentHost,
+                Host::Any => kCFPreferencesAnyHost,
+            }
+        }
+    }
+}
+
+/// The strings of a CFArray we own, skipping anything else; the array is released.
+unsafe fn take_strings(arr_ref: CFArrayRef) -> Vec<String> {
+    unsafe {
         let len = CFArrayGetCount(arr_ref);
-        let mut out = HashSet::with_capacity(len as usize);
+        let mut out = Vec::with_capacity(len as usize);
         for i in 0..len {
             let val = CFArrayGetValueAtIndex(arr_ref, i);
             if !val.is_null() && CFGetTypeID(val as _) == CFStringGetTypeID() {
-                let s = CFString::wrap_under_get_rule(val as _);
-                out.insert(s.to_string());
+                out.push(CFString::wrap_under_get_rule(val as _).to_string());
             }
         }
-        Ok(out)
+        CFRelease(arr_ref as _);
+        out
     }
 }
 
-/// Read a single key as PrefValue.
-pub(crate) fn read_pref(domain: &str, key: &str) -> Result<PrefValue> {
+/// Convert a value we own, releasing it.
+unsafe fn take_value(raw: CFPropertyListRef) -> Option<PrefValue> {
+    if raw.is_null() {
+        return None;
+    }
     unsafe {
-        let domain_cf = CFString::new(domain);
-        let key_cf = CFString::new(key);
-        let raw = CFPreferencesCopyAppValue(
-            key_cf.as_concrete_TypeRef(),
-            domain_cf.as_concrete_TypeRef(),
-        );
-        if raw.is_null() {
-            bail!("Key not found for domain {domain}: {key}");
+        let value = cf_to_pref(raw as _);
+        CFRelease(raw);
+        Some(value)
+    }
+}
+
+/// The application IDs with preferences stored for `user` and `host`.
+pub fn copy_application_list(user: User, host: Host) -> Result<Vec<String>> {
+    unsafe {
+        let arr_ref = CFPreferencesCopyApplicationList(user.as_cf(), host.as_cf());
+        if arr_ref.is_null() {
+            bail!("Couldn't copy application list from CoreFoundation API.")
         }
-        Ok(cf_to_pref(raw as _))
+        Ok(take_strings(arr_ref))
     }
 }
 
-/// Read the whole domain as PrefValue::Dictionary.
-pub(crate) fn read_pref_domain(domain: &str) -> Result<PrefValue> {
+/// The keys `app` stores for `user` and `host`, without consulting any other scope.
+pub fn copy_key_list(app: &str, user: User, host: Host) -> Vec<String> {
+    let app_cf = CFString::new(app);
     unsafe {
-        let domain_cf = CFString::new(domain);
-        let keys_ref = CFPreferencesCopyKeyList(
-            domain_cf.as_concrete_TypeRef(),
-            kCFPreferencesCurrentUser,
-            kCFPreferencesAnyHost,
-        );
+        let keys_ref =
+            CFPreferencesCopyKeyList(app_cf.as_concrete_TypeRef(), user.as_cf(), host.as_cf());
         if keys_ref.is_null() {
-            return Ok(PrefValue::Dictionary(HashMap::new()));
+            return Vec::new();
         }
-        let len = CFArrayGetCount(keys_ref);
-        let mut map = HashMap::new();
-        for i in 0..len {
-            let key_ref = CFArrayGetValueAtIndex(keys_ref, i);
-            if key_ref.is_null() || CFGetTypeID(key_ref as _) != CFStringGetTypeID() {
-                continue;
-            }
-            let key_cf = CFString::wrap_under_get_rule(key_ref as _);
-            let raw = CFPreferencesCopyAppValue(
-                key_cf.as_concrete_TypeRef(),
-                domain_cf.as_concrete_TypeRef(),
-            );
-            if !raw.is_null() {
-                map.insert(key_cf.to_string(), cf_to_pref(raw as _));
-            }
-        }
-        Ok(PrefValue::Dictionary(map))
+        take_strings(keys_ref)
     }
 }
 
-/// Write (set) a single key in a domain. Returns success (synchronize result).
-pub(crate) fn write_pref(domain: &str, key: &str, value: &PrefValue) -> Result<()> {
+/// The value `app` stores for `key` for `user` and `host`, without consulting any other scope.
+pub fn copy_value(app: &str, key: &str, user: User, host: Host) -> Option<PrefValue> {
+    let app_cf = CFString::new(app);
+    let key_cf = CFString::new(key);
     unsafe {
-        let domain_cf = CFString::new(domain);
-        let key_cf = CFString::new(key);
-        let value_ref = pref_to_cf(value);
-        CFPreferencesSetAppValue(
+        take_value(CFPreferencesCopyValue(
             key_cf.as_concrete_TypeRef(),
-            value_ref,
-            domain_cf.as_concrete_TypeRef(),
+            app_cf.as_concrete_TypeRef(),
+            user.as_cf(),
+            host.as_cf(),
+        ))
+    }
+}
+
+/// The value of `key` through `app`'s whole search list, as the app itself would read it.
+pub fn copy_app_value(app: &str, key: &str) -> Option<PrefValue> {
+    let app_cf = CFString::new(app);
+    let key_cf = CFString::new(key);
+    unsafe {
+        take_value(CFPreferencesCopyAppValue(
+            key_cf.as_concrete_TypeRef(),
+            app_cf.as_concrete_TypeRef(),
+        ))
+    }
+}
+
+/// Whether `key` in `app` is forced by a configuration profile, so stored values are ignored.
+pub fn value_is_forced(app: &str, key: &str) -> bool {
+    let app_cf = CFString::new(app);
+    let key_cf = CFString::new(key);
+    unsafe {
+        CFPreferencesAppValueIsForced(key_cf.as_concrete_TypeRef(), app_cf.as_concrete_TypeRef())
+            != 0
+    }
+}
+
+/// Set `key` for `user` and `host` in `app`, or remove it when `value` is `None`.
+///
+/// The change is only staged in this process until [`synchronize`] is called.
+pub fn set_value(
+    app: &str,
+    key: &str,
+    value: Option<&PrefValue>,
+    user: User,
+    host: Host,
+) -> Result<()> {
+    ensure_writable(app)?;
+    let app_cf = CFString::new(app);
+    let key_cf = CFString::new(key);
+    unsafe {
+        CFPreferencesSetValue(
+            key_cf.as_concrete_TypeRef(),
+            value.map_or(std::ptr::null(), pref_to_cf),
+            app_cf.as_concrete_TypeRef(),
+            user.as_cf(),
+            host.as_cf(),
         );
-        if CFPreferencesAppSynchronize(domain_cf.as_concrete_TypeRef()) != 0 {
-            Ok(())
-        } else {
-            bail!("Failed to write key: {}", key)
-        }
     }
+    Ok(())
 }
 
-/// Delete a single key. Returns success (including if key absent).
-pub(crate) fn delete_key(domain: &str, key: &str) -> Result<()> {
+/// Flush staged changes of `app` for `user` and `host` to disk and pick up changes made by
+/// others. Returns whether this succeeded.
+pub fn synchronize(app: &str, user: User, host: Host) -> bool {
+    let app_cf = CFString::new(app);
+    unsafe {
+        CFPreferencesSynchronize(app_cf.as_concrete_TypeRef(), user.as_cf(), host.as_cf()) != 0
+    }
+}
+
+/// The explicit user and host of `domain`, or `None` for domains read through the app's search
+/// list and written for the current user on any host.
+fn scope(domain: &Domain) -> Option<(User, Host)> {
+    match domain {
+        Domain::ByHost(_) => Some((User::Current, Host::Current)),
+        Domain::System(_) => Some((User::Any, Host::Any)),
+        _ => None,
+    }
+}
+
+/// The application ID CFPreferences knows `domain` by.
+///
+/// Containers and App Groups aren't on the search list of other processes, so their plist is addressed by its
+/// absolute path (without the extension), as `defaults` does.
+pub(crate) fn app_id(domain: &Domain) -> String {
+    if domain.in_container()
+        && let Some(path) = domain.plist_path()
+    {
+        return path.with_extension("").to_string_lossy().into_owned();
+    }
+    domain.get_cf_name()
+}
+
+/// List all preference application IDs (domains) for CurrentUser / AnyHost.
+pub(crate) fn list_domains() -> Result<HashSet<String>> {
+    Ok(copy_application_list(User::Current, Host::Any)?
+        .into_iter()
+        .collect())
+}
+
+/// Read `key` of `domain`, known to CFPreferences as `app`.
+fn read_value(domain: &Domain, app: &str, key: &str) -> Option<PrefValue> {
+    match scope(domain) {
+        Some((user, host)) => copy_value(app, key, user, host),
+        None => copy_app_value(app, key),
+    }
+}
+
+/// Read a single key as PrefValue.
+pub(crate) fn read_pref(domain: &Domain, key: &str) -> Result<PrefValue> {
+    if let Some(path) = domain.file() {
+        return plist_file::read_pref(path, key);
+    }
+    match read_value(domain, &app_id(domain), key) {
+        Some(value) => Ok(value),
+        None => bail!("Key not found for domain {domain}: {key}"),
+    }
+}
+
+/// Read a single key as PrefValue, or `None` if it isn't set. Other errors, such as a plist
+/// which can't be parsed, are returned rather than read as `None`.
+pub(crate) fn read_pref_opt(domain: &Domain, key: &str) -> Result<Option<PrefValue>> {
+    if let Some(path) = domain.file() {
+        return plist_file::read_pref_opt(path, key);
+    }
+    Ok(read_value(domain, &app_id(domain), key))
+}
+
+/// Read the whole domain as PrefValue::Dictionary.
+pub(crate) fn read_pref_domain(domain: &Domain) -> Result<PrefValue> {
+    if let Some(path) = domain.file() {
+        return plist_file::read_pref_domain(path);
+    }
+    let app = app_id(domain);
+    let (user, host) = scope(domain).unwrap_or((User::Current, Host::Any));
+    let map: HashMap<String, PrefValue> = copy_key_list(&app, user, host)
+        .into_iter()
+        .filter_map(|key| read_value(domain, &app, &key).map(|value| (key, value)))
+        .collect();
+    Ok(PrefValue::Dictionary(map))
+}
+
+/// Read `key` from a single layer of the search list, without falling back to other layers.
+///
+/// Managed layers aren't stored in the CFPreferences database and always read as `None`.
+pub(crate) fn read_layer(domain: &str, key: &str, layer: &PrefLayer) -> Option<PrefValue> {
+    if layer.managed {
+        return None;
+    }
+    let app = if layer.global {
+        ANY_APPLICATION
+    } else {
+        domain
+    };
+    let user = if layer.any_user {
+        User::Any
+    } else {
+        User::Current
+    };
+    let host = if layer.current_host {
+        Host::Current
+    } else {
+        Host::Any
+    };
+    copy_value(app, key, user, host)
+}
+
+/// Set or remove `key` in `domain` and synchronize it.
+fn store(domain: &Domain, key: &str, value: Option<&PrefValue>) -> Result<bool> {
+    let app = app_id(domain);
+    if let Some((user, host)) = scope(domain) {
+        set_value(&app, key, value, user, host)?;
+        let synced = synchronize(&app, user, host);
+        if !synced && user == User::Any {
+            bail!("Couldn't change {domain}: /Library/Preferences can only be changed as root.");
+        }
+        return Ok(synced);
+    }
+
+    ensure_writable(&app)?;
+    let app_cf = CFString::new(&app);
+    let key_cf = CFString::new(key);
     unsafe {
-        let domain_cf = CFString::new(domain);
-        let key_cf = CFString::new(key);
         CFPreferencesSetAppValue(
             key_cf.as_concrete_TypeRef(),
-            std::ptr::null(),
-            domain_cf.as_concrete_TypeRef(),
+            value.map_or(std::ptr::null(), pref_to_cf),
+            app_cf.as_concrete_TypeRef(),
         );
-        if CFPreferencesAppSynchronize(domain_cf.as_concrete_TypeRef()) != 0 {
-            Ok(())
-        } else {
-            bail!("Failed to delete key: {}", key)
-        }
+        Ok(CFPreferencesAppSynchronize(app_cf.as_concrete_TypeRef()) != 0)
+    }
+}
+
+/// Write (set) a single key in a domain. Returns success (synchronize result).
+pub(crate) fn write_pref(domain: &Domain, key: &str, value: &PrefValue) -> Result<()> {
+    if let Some(path) = domain.file() {
+        return plist_file::write_pref(path, key, value);
+    }
+    if store(domain, key, Some(value))? {
+        Ok(())
+    } else {
+        bail!("Failed to write key: {}", key)
+    }
+}
+
+/// Delete a single key. Returns success (including if key absent).
+pub(crate) fn delete_key(domain: &Domain, key: &str) -> Result<()> {
+    if let Some(path) = domain.file() {
+        return plist_file::delete_key(path, key);
+    }
+    if store(domain, key, None)? {
+        Ok(())
+    } else {
+        bail!("Failed to delete key: {}", key)
     }
 }
 
 /// Delete all keys in a domain.
-pub(crate) fn delete_domain(domain: &str) -> Result<()> {
+pub(crate) fn delete_domain(domain: &Domain) -> Result<()> {
+    if let Some(path) = domain.file() {
+        return plist_file::delete_domain(path);
+    }
     let loaded = read_pref_domain(domain)?;
 
     match loaded {
@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: MIT
+
+//! Process-wide guard for mutating operations.
+//!
+//! Every backend function which modifies preferences checks [`ensure_writable`] first, so
+//! read-only mode holds no matter which higher-level code path requested the change.
+
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Error returned by mutating operations while read-only mode is enabled.
+#[derive(Debug)]
+pub struct RefusedReadOnly {
+    /// The domain (or file) the refused operation targeted.
+    pub target: String,
+}
+
+impl std::fmt::Display for RefusedReadOnly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Refused to modify {}: read-only mode is enabled.",
+            self.target
+        )
+    }
+}
+
+impl std::error::Error for RefusedReadOnly {}
+
+pub(crate) fn set_read_only(enabled: bool) {
+    READ_ONLY.store(enabled, Ordering::SeqCst);
+}
+
+pub(crate) fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::SeqCst)
+}
+
+/// Fails with [`RefusedReadOnly`] if read-only mode is enabled.
+pub(crate) fn ensure_writable(target: &str) -> Result<()> {
+    if is_read_only() {
+        return Err(RefusedReadOnly {
+            target: target.to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
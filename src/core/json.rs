@@ -0,0 +1,238 @@
+// SPDX-License-Identifier: MIT
+
+//! Typed JSON: a JSON encoding of [`PrefValue`] which round-trips every value exactly.
+//!
+//! Strings, booleans, arrays and dictionaries map to their JSON counterparts, integers to JSON
+//! integers and finite floats to JSON numbers with a decimal point or exponent (`1.0`, `1e300`).
+//! Everything else is wrapped in an object naming its type:
+//!
+//! | Type                           | Typed JSON                                                 |
+//! |--------------------------------|------------------------------------------------------------|
+//! | non-finite float               | `{"$type": "float", "value": "nan"}` (or `inf`, `-inf`)    |
+//! | data                           | `{"$type": "data", "value": "0a1b2c"}`                     |
+//! | date                           | `{"$type": "date", "value": "2001-01-01T00:00:00Z"}`       |
+//! | url                            | `{"$type": "url", "value": "https://example.com"}`         |
+//! | uuid                           | `{"$type": "uuid", "value": "68753A44-4D6F-1226-9C60-0050E4C00067"}` |
+//! | uid                            | `{"$type": "uid", "value": 7}`                             |
+//! | dictionary with a `$type` key  | `{"$type": "dictionary", "value": {...}}`                  |
+//!
+//! Dates which the timestamp can't represent exactly also carry `"seconds"`, their exact offset
+//! from 2001-01-01 as stored by CFDate, which takes precedence when reading.
+
+use anyhow::{Context, Result, bail};
+use chrono::DateTime;
+use serde_json::{Map, Value, json};
+
+use super::types::{APPLE_EPOCH, PrefValue, apple_date_text};
+
+/// The CFDate timestamp of an RFC 3339 date.
+fn parse_date(text: &str) -> Result<f64> {
+    let date = DateTime::parse_from_rfc3339(text)
+        .with_context(|| format!("Invalid date in typed JSON: {text}"))?;
+    Ok((date.timestamp() - APPLE_EPOCH) as f64 + date.timestamp_subsec_nanos() as f64 / 1e9)
+}
+
+fn parse_hex(text: &str) -> Result<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        bail!("Invalid data in typed JSON: {text}");
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| {
+            text.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+                .with_context(|| format!("Invalid data in typed JSON: {text}"))
+        })
+        .collect()
+}
+
+fn wrap(kind: &str, value: Value) -> Value {
+    json!({ "$type": kind, "value": value })
+}
+
+impl PrefValue {
+    /// Convert the value into typed JSON, which [`Self::from_typed_json`] reads back unchanged.
+    pub fn to_typed_json(&self) -> Value {
+        match self {
+            PrefValue::String(s) => Value::String(s.clone()),
+            PrefValue::Integer(i) => Value::from(*i),
+            PrefValue::Float(f) => match serde_json::Number::from_f64(*f) {
+                Some(n) => Value::Number(n),
+                None if f.is_nan() => wrap("float", json!("nan")),
+                None if *f > 0.0 => wrap("float", json!("inf")),
+                None => wrap("float", json!("-inf")),
+            },
+            PrefValue::Boolean(b) => Value::Bool(*b),
+            PrefValue::Array(arr) => Value::Array(arr.iter().map(Self::to_typed_json).collect()),
+            PrefValue::Dictionary(dict) => {
+                let object: Map<String, Value> = dict
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.to_typed_json()))
+                    .collect();
+                if dict.contains_key("$type") {
+                    wrap("dictionary", Value::Object(object))
+                } else {
+                    Value::Object(object)
+                }
+            }
+            PrefValue::Data(data) => wrap(
+                "data",
+                Value::String(data.iter().map(|b| format!("{b:02x}")).collect()),
+            ),
+            PrefValue::Date(ts) => {
+                let text = apple_date_text(*ts);
+                let mut date = wrap("date", Value::String(text.clone()));
+                if parse_date(&text).ok() != Some(*ts)
+                    && let Some(seconds) = serde_json::Number::from_f64(*ts)
+                {
+                    date["seconds"] = Value::Number(seconds);
+                }
+                date
+            }
+            PrefValue::Url(url) => wrap("url", Value::String(url.clone())),
+            PrefValue::Uuid(uuid) => wrap("uuid", Value::String(uuid.clone())),
+            PrefValue::Uid(uid) => wrap("uid", Value::from(*uid)),
+        }
+    }
+
+    /// Read a value from typed JSON.
+    ///
+    /// Plain JSON is accepted too: numbers without a decimal point or exponent become integers.
+    /// `null` has no counterpart and is rejected.
+    pub fn from_typed_json(json: &Value) -> Result<PrefValue> {
+        Ok(match json {
+            Value::Null => bail!("null has no property list counterpart."),
+            Value::Bool(b) => PrefValue::Boolean(*b),
+            Value::Number(n) => match n.as_i64() {
+                Some(i) => PrefValue::Integer(i),
+                None if n.is_u64() => bail!("{n} is too large for an integer."),
+                None => PrefValue::Float(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            Value::String(s) => PrefValue::String(s.clone()),
+            Value::Array(arr) => PrefValue::Array(
+                arr.iter()
+                    .map(Self::from_typed_json)
+                    .collect::<Result<_>>()?,
+            ),
+            Value::Object(object) => match object.get("$type") {
+                Some(Value::String(kind)) => Self::from_wrapped(kind, object)?,
+                Some(_) => bail!("$type must be a string in typed JSON."),
+                None => Self::from_object(object)?,
+            },
+        })
+    }
+
+    fn from_object(object: &Map<String, Value>) -> Result<PrefValue> {
+        Ok(PrefValue::Dictionary(
+            object
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), Self::from_typed_json(v)?)))
+                .collect::<Result<_>>()?,
+        ))
+    }
+
+    fn from_wrapped(kind: &str, object: &Map<String, Value>) -> Result<PrefValue> {
+        let value = object
+            .get("value")
+            .with_context(|| format!("{kind} is missing its value in typed JSON."))?;
+        let text = || {
+            value
+                .as_str()
+                .with_context(|| format!("The value of a {kind} must be a string in typed JSON."))
+        };
+
+        Ok(match kind {
+            "float" => match text()? {
+                "nan" => PrefValue::Float(f64::NAN),
+                "inf" => PrefValue::Float(f64::INFINITY),
+                "-inf" => PrefValue::Float(f64::NEG_INFINITY),
+                other => bail!("Invalid float in typed JSON: {other}"),
+            },
+            "data" => PrefValue::Data(parse_hex(text()?)?.into()),
+            "date" => match object.get("seconds").and_then(Value::as_f64) {
+                Some(seconds) => PrefValue::Date(seconds),
+                None => PrefValue::Date(parse_date(text()?)?),
+            },
+            "url" => PrefValue::Url(text()?.to_string()),
+            "uuid" => PrefValue::Uuid(text()?.to_string()),
+            "uid" => PrefValue::Uid(
+                value
+                    .as_u64()
+                    .context("The value of a uid must be an unsigned integer in typed JSON.")?,
+            ),
+            "dictionary" => match value {
+                Value::Object(inner) => Self::from_object(inner)?,
+                _ => bail!("The value of a dictionary must be an object in typed JSON."),
+            },
+            other => bail!("Unknown $type in typed JSON: {other}"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn round_trips_every_type() -> Result<()> {
+        let values = [
+            PrefValue::String("a".into()),
+            PrefValue::Integer(-3),
+            PrefValue::Float(1.0),
+            PrefValue::Float(f64::INFINITY),
+            PrefValue::Float(f64::NEG_INFINITY),
+            PrefValue::Boolean(true),
+            PrefValue::Data(vec![0x0a, 0xff].into()),
+            PrefValue::Date(0.0),
+            PrefValue::Date(1.25),
+            // Too far out for a timestamp; kept by `seconds`.
+            PrefValue::Date(1e300),
+            PrefValue::Url("https://example.com".into()),
+            PrefValue::Uuid("68753A44-4D6F-1226-9C60-0050E4C00067".into()),
+            PrefValue::Uid(7),
+            PrefValue::Dictionary(HashMap::from([("$type".into(), PrefValue::Integer(1))])),
+        ];
+        let all = PrefValue::Array(values.to_vec());
+        assert_eq!(PrefValue::from_typed_json(&all.to_typed_json())?, all);
+
+        let nan = PrefValue::from_typed_json(&PrefValue::Float(f64::NAN).to_typed_json())?;
+        assert!(matches!(nan, PrefValue::Float(f) if f.is_nan()));
+        Ok(())
+    }
+
+    #[test]
+    fn reads_plain_json() -> Result<()> {
+        let value = PrefValue::from_typed_json(&json!({ "a": [1, 1.0, "x", false] }))?;
+        assert_eq!(
+            value,
+            PrefValue::Dictionary(HashMap::from([(
+                "a".into(),
+                PrefValue::Array(vec![
+                    PrefValue::Integer(1),
+                    PrefValue::Float(1.0),
+                    PrefValue::String("x".into()),
+                    PrefValue::Boolean(false),
+                ])
+            )]))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_json() {
+        for json in [
+            json!(null),
+            json!(u64::MAX),
+            json!({ "$type": 1 }),
+            json!({ "$type": "nope", "value": "" }),
+            json!({ "$type": "data" }),
+            json!({ "$type": "data", "value": "abc" }),
+            json!({ "$type": "date", "value": "yesterday" }),
+            json!({ "$type": "uid", "value": -1 }),
+            json!({ "$type": "dictionary", "value": [] }),
+        ] {
+            assert!(PrefValue::from_typed_json(&json).is_err(), "{json}");
+        }
+    }
+}
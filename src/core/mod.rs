@@ -1,5 +1,10 @@
 // SPDX-License-Identifier: MIT
 
 pub(crate) mod convert;
+pub(crate) mod display;
+pub(crate) mod file;
 pub(crate) mod foundation;
+pub(crate) mod guard;
+pub(crate) mod json;
+pub(crate) mod plist_file;
 pub(crate) mod types;
@@ -0,0 +1,141 @@
+// SPDX-License-Identifier: MIT
+
+//! File backend for [`Domain::Path`](crate::Domain::Path): plists read and written directly
+//! rather than through CFPreferences.
+//!
+//! Every change is a read-modify-write of the whole file, done while holding an exclusive lock
+//! on its directory so concurrent drs processes don't lose each other's writes. Files are
+//! replaced atomically and keep their binary or XML format and owner; new files are binary and
+//! owned by the owner of their directory.
+
+use anyhow::{Result, bail};
+use plist::Value;
+use std::{collections::HashMap, io::Cursor, path::Path};
+
+use crate::{
+    PrefValue,
+    core::{file, guard::ensure_writable},
+    preferences::convert::{plist_to_prefvalue, prefvalue_to_plist},
+};
+
+/// The keys of the plist at `path`, and whether it is stored as XML.
+pub(crate) fn load(path: &Path) -> Result<(HashMap<String, PrefValue>, bool)> {
+    let data = file::read_limited(path)?;
+    let PrefValue::Dictionary(root) = plist_to_prefvalue(&Value::from_reader(Cursor::new(&data))?)?
+    else {
+        bail!("{} must hold a dictionary at its root.", path.display());
+    };
+    Ok((root, !data.starts_with(b"bplist")))
+}
+
+/// Change the plist at `path` with `f` under the directory lock, creating it if missing.
+///
+/// Nothing is written if `f` fails.
+pub(crate) fn update(
+    path: &Path,
+    f: impl FnOnce(&mut HashMap<String, PrefValue>) -> Result<()>,
+) -> Result<()> {
+    ensure_writable(&path.display().to_string())?;
+    let _lock = file::lock_dir(path)?;
+
+    let (mut root, xml) = if path.exists() {
+        load(path)?
+    } else {
+        (HashMap::new(), false)
+    };
+    f(&mut root)?;
+
+    let plist = prefvalue_to_plist(&PrefValue::Dictionary(root));
+    let mut buf = Vec::new();
+    if xml {
+        plist.to_writer_xml(&mut buf)?;
+    } else {
+        plist.to_writer_binary(&mut buf)?;
+    }
+
+    let owner = file::owner(path).or_else(|| path.parent().and_then(file::owner));
+    file::write_atomic(path, &buf)?;
+    match owner {
+        Some(owner) => file::set_owner(path, owner),
+        None => Ok(()),
+    }
+}
+
+/// Read a single key of the plist at `path`.
+pub(crate) fn read_pref(path: &Path, key: &str) -> Result<PrefValue> {
+    match load(path)?.0.remove(key) {
+        Some(value) => Ok(value),
+        None => bail!("Key not found in {}: {key}", path.display()),
+    }
+}
+
+/// Read a single key of the plist at `path`, or `None` if the file or the key is missing.
+pub(crate) fn read_pref_opt(path: &Path, key: &str) -> Result<Option<PrefValue>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(load(path)?.0.remove(key))
+}
+
+/// Read the whole plist at `path`; a missing file reads as an empty dictionary.
+pub(crate) fn read_pref_domain(path: &Path) -> Result<PrefValue> {
+    if !path.exists() {
+        return Ok(PrefValue::Dictionary(HashMap::new()));
+    }
+    Ok(PrefValue::Dictionary(load(path)?.0))
+}
+
+/// Write a single key to the plist at `path`.
+pub(crate) fn write_pref(path: &Path, key: &str, value: &PrefValue) -> Result<()> {
+    update(path, |root| {
+        root.insert(key.to_string(), value.clone());
+        Ok(())
+    })
+}
+
+/// Delete a single key from the plist at `path`; absent keys are fine.
+pub(crate) fn delete_key(path: &Path, key: &str) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    update(path, |root| {
+        root.remove(key);
+        Ok(())
+    })
+}
+
+/// Remove every key from the plist at `path`, leaving an empty dictionary.
+pub(crate) fn delete_domain(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    update(path, |root| {
+        root.clear();
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_missing_keys_read_as_unset() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("drs-plist-file-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("test.plist");
+
+        assert_eq!(read_pref_opt(&path, "a")?, None);
+
+        std::fs::write(&path, "not a plist")?;
+        assert!(read_pref_opt(&path, "a").is_err());
+
+        std::fs::remove_file(&path)?;
+        write_pref(&path, "a", &PrefValue::Integer(1))?;
+        assert_eq!(read_pref_opt(&path, "a")?, Some(PrefValue::Integer(1)));
+        assert_eq!(read_pref_opt(&path, "b")?, None);
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
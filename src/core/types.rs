@@ -4,10 +4,31 @@
 //!
 //! The batch operations in the API (batch-read and batch-delete) work on the [`Domain`] and [`PrefValue`] types.
 
-use std::collections::HashMap;
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    hash::{Hash, Hasher},
+};
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::Domain;
 
 /// Value stored in preferences.
-#[derive(Debug, Clone, PartialEq)]
+///
+/// `Display` prints values plainly, so a string and a number can look alike; see
+/// [`PrefValue::to_display_string`] for an unambiguous style.
+///
+/// Values are totally ordered and hashable, so they can be sorted, deduplicated and used as set
+/// members or map keys:
+///
+/// - Values of different types are never equal and sort by type, in the order of the variants
+///   below. In particular `Integer(1)` and `Float(1.0)` differ.
+/// - Floats and dates compare by value, except that `-0.0` equals `0.0` and every NaN equals
+///   every other NaN, sorting after positive infinity.
+/// - Arrays compare element by element; dictionaries compare as their entries sorted by key, so
+///   insertion order never matters.
+#[derive(Debug, Clone)]
 pub enum PrefValue {
     String(String),
     Integer(i64),
@@ -22,6 +43,64 @@ pub enum PrefValue {
     Uid(u64),
 }
 
+/// A layer of the CFPreferences search list which can hold a value.
+///
+/// Reads walk the layers from managed (configuration profile) preferences through this app's
+/// own values to the global domain, first for the current user and then for any user, and return
+/// the first value found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrefLayer {
+    /// Forced by a configuration profile rather than stored by CFPreferences. Managed values
+    /// can't be changed with drs.
+    pub managed: bool,
+    /// Stored in the global domain rather than the app's own.
+    pub global: bool,
+    /// Stored for all users (`/Library/Preferences`) rather than the current one.
+    pub any_user: bool,
+    /// Stored for this machine only (a ByHost preference).
+    pub current_host: bool,
+}
+
+impl PrefLayer {
+    /// The layer plain writes and deletes go to: this app, current user, any host.
+    pub const DEFAULT: PrefLayer = PrefLayer {
+        managed: false,
+        global: false,
+        any_user: false,
+        current_host: false,
+    };
+
+    /// The layer writes and deletes on `domain` go to.
+    pub fn written_by(domain: &Domain) -> PrefLayer {
+        PrefLayer {
+            global: matches!(domain, Domain::Global),
+            any_user: matches!(domain, Domain::System(_)),
+            current_host: domain.is_current_host(),
+            ..PrefLayer::DEFAULT
+        }
+    }
+}
+
+impl std::fmt::Display for PrefLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let domain = if self.global { "global domain" } else { "app" };
+        let user = if self.any_user {
+            "any user"
+        } else {
+            "current user"
+        };
+        if self.managed {
+            return write!(f, "managed, {domain}, {user}");
+        }
+        let host = if self.current_host {
+            "current host"
+        } else {
+            "any host"
+        };
+        write!(f, "{domain}, {user}, {host}")
+    }
+}
+
 impl Default for PrefValue {
     fn default() -> Self {
         PrefValue::String(String::default())
@@ -62,23 +141,141 @@ impl std::fmt::Display for PrefValue {
                 let data: Vec<String> = data.iter().map(|f| f.to_string()).collect();
                 write!(f, "[{}]", data.join(", "))
             }
-            PrefValue::Date(apple_ts) => {
-                write!(f, "{}", {
-                    use chrono::{TimeZone, Utc};
+            PrefValue::Date(apple_ts) => match apple_date(*apple_ts) {
+                Ok(date) => write!(f, "{date}"),
+                Err(_) => f.write_str(&apple_date_text(*apple_ts)),
+            },
+        }
+    }
+}
+
+/// Seconds between the Unix epoch and 2001-01-01, the CFDate reference date.
+pub(crate) const APPLE_EPOCH: i64 = 978_307_200;
+
+/// The earliest and latest dates supported, 0001-01-01 and 9999-12-31 23:59:59 UTC, in seconds
+/// since 2001-01-01. Plist and RFC 3339 dates can't represent years outside of 1 to 9999.
+pub(crate) const APPLE_DATE_RANGE: std::ops::RangeInclusive<f64> =
+    -63_113_904_000.0..=252_423_993_599.0;
+
+/// The UTC time of a date stored as seconds since 2001-01-01, as CFDate does. Fails for dates
+/// outside [`APPLE_DATE_RANGE`].
+pub(crate) fn apple_date(apple_ts: f64) -> Result<chrono::DateTime<chrono::Utc>> {
+    if !APPLE_DATE_RANGE.contains(&apple_ts) {
+        bail!("{apple_ts} seconds after 2001-01-01 is not a supported date.")
+    }
+    let secs = apple_ts.floor();
+    let nanos = ((apple_ts - secs) * 1e9) as u32;
+    chrono::DateTime::from_timestamp(secs as i64 + APPLE_EPOCH, nanos.min(999_999_999))
+        .ok_or_else(|| anyhow!("{apple_ts} seconds after 2001-01-01 is not a supported date."))
+}
+
+/// `apple_ts` as an RFC 3339 date, or described as seconds if it is out of range.
+pub(crate) fn apple_date_text(apple_ts: f64) -> String {
+    match apple_date(apple_ts) {
+        Ok(date) => date.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+        Err(_) => format!("invalid date ({apple_ts} seconds after 2001-01-01)"),
+    }
+}
+
+/// `f` with every NaN and both zeros collapsed into one value each, as compared and hashed.
+fn canonical_float(f: f64) -> f64 {
+    if f.is_nan() {
+        f64::NAN
+    } else if f == 0.0 {
+        0.0
+    } else {
+        f
+    }
+}
+
+/// The entries of `dict` sorted by key.
+fn sorted_entries(dict: &HashMap<String, PrefValue>) -> Vec<(&String, &PrefValue)> {
+    let mut entries: Vec<_> = dict.iter().collect();
+    entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    entries
+}
+
+impl PartialEq for PrefValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (PrefValue::Float(a), PrefValue::Float(b))
+            | (PrefValue::Date(a), PrefValue::Date(b)) => {
+                canonical_float(*a).to_bits() == canonical_float(*b).to_bits()
+            }
+            (PrefValue::Array(a), PrefValue::Array(b)) => a == b,
+            (PrefValue::Dictionary(a), PrefValue::Dictionary(b)) => {
+                a.len() == b.len() && a.iter().all(|(k, v)| b.get(k) == Some(v))
+            }
+            _ => self.cmp(other) == Ordering::Equal,
+        }
+    }
+}
 
-                    let base = Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap();
-                    let secs = *apple_ts as i64;
-                    let nanos = ((apple_ts % 1.0) * 1e9) as u32;
+impl Eq for PrefValue {}
 
-                    base + chrono::Duration::seconds(secs)
-                        + chrono::Duration::nanoseconds(nanos as i64)
-                })
+impl PartialOrd for PrefValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PrefValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (PrefValue::String(a), PrefValue::String(b))
+            | (PrefValue::Url(a), PrefValue::Url(b))
+            | (PrefValue::Uuid(a), PrefValue::Uuid(b)) => a.cmp(b),
+            (PrefValue::Integer(a), PrefValue::Integer(b)) => a.cmp(b),
+            (PrefValue::Float(a), PrefValue::Float(b))
+            | (PrefValue::Date(a), PrefValue::Date(b)) => {
+                canonical_float(*a).total_cmp(&canonical_float(*b))
+            }
+            (PrefValue::Boolean(a), PrefValue::Boolean(b)) => a.cmp(b),
+            (PrefValue::Array(a), PrefValue::Array(b)) => a.cmp(b),
+            (PrefValue::Dictionary(a), PrefValue::Dictionary(b)) => {
+                sorted_entries(a).cmp(&sorted_entries(b))
             }
+            (PrefValue::Data(a), PrefValue::Data(b)) => a.cmp(b),
+            (PrefValue::Uid(a), PrefValue::Uid(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl Hash for PrefValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            PrefValue::String(s) | PrefValue::Url(s) | PrefValue::Uuid(s) => s.hash(state),
+            PrefValue::Integer(i) => i.hash(state),
+            PrefValue::Float(f) | PrefValue::Date(f) => canonical_float(*f).to_bits().hash(state),
+            PrefValue::Boolean(b) => b.hash(state),
+            PrefValue::Array(arr) => arr.hash(state),
+            PrefValue::Dictionary(dict) => sorted_entries(dict).hash(state),
+            PrefValue::Data(data) => data.hash(state),
+            PrefValue::Uid(uid) => uid.hash(state),
         }
     }
 }
 
 impl PrefValue {
+    /// Position of the variant, which orders values of different types.
+    fn rank(&self) -> u8 {
+        match self {
+            PrefValue::String(_) => 0,
+            PrefValue::Integer(_) => 1,
+            PrefValue::Float(_) => 2,
+            PrefValue::Boolean(_) => 3,
+            PrefValue::Array(_) => 4,
+            PrefValue::Dictionary(_) => 5,
+            PrefValue::Data(_) => 6,
+            PrefValue::Date(_) => 7,
+            PrefValue::Url(_) => 8,
+            PrefValue::Uuid(_) => 9,
+            PrefValue::Uid(_) => 10,
+        }
+    }
+
     /// Returns the name of the type for the PrefValue instance.
     pub fn get_type(&self) -> &'static str {
         match self {
@@ -95,4 +292,52 @@ impl PrefValue {
             PrefValue::Uid(_) => "uid",
         }
     }
+
+    /// Convert the value into JSON for machine-readable output.
+    ///
+    /// Types without a JSON counterpart are flattened: data becomes a hex string, dates become
+    /// their UTC timestamp and non-finite floats become `null`.
+    pub fn to_json(&self) -> serde_json::Value {
+        use serde_json::Value;
+
+        match self {
+            PrefValue::String(s) | PrefValue::Url(s) | PrefValue::Uuid(s) => {
+                Value::String(s.clone())
+            }
+            PrefValue::Integer(i) => Value::from(*i),
+            PrefValue::Float(f) => serde_json::Number::from_f64(*f)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            PrefValue::Boolean(b) => Value::Bool(*b),
+            PrefValue::Array(arr) => Value::Array(arr.iter().map(PrefValue::to_json).collect()),
+            PrefValue::Dictionary(dict) => {
+                Value::Object(dict.iter().map(|(k, v)| (k.clone(), v.to_json())).collect())
+            }
+            PrefValue::Data(data) => {
+                Value::String(data.iter().map(|b| format!("{b:02x}")).collect())
+            }
+            PrefValue::Date(_) => Value::String(self.to_string()),
+            PrefValue::Uid(uid) => Value::from(*uid),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apple_dates() -> Result<()> {
+        assert_eq!(apple_date(0.0)?.to_rfc3339(), "2001-01-01T00:00:00+00:00");
+        assert_eq!(
+            apple_date(-0.5)?.to_rfc3339(),
+            "2000-12-31T23:59:59.500+00:00"
+        );
+        for ts in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 1e300, -1e300] {
+            assert!(apple_date(ts).is_err(), "{ts} was accepted");
+            // Out of range dates are still displayed rather than panicking.
+            assert!(PrefValue::Date(ts).to_string().starts_with("invalid date"));
+        }
+        Ok(())
+    }
 }
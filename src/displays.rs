@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: MIT
+
+//! The displays connected to this Mac, for preferences keyed by display UUID.
+//!
+//! WindowServer and a few apps keep settings per display, under the UUID macOS assigns each one
+//! (e.g. in `com.apple.windowserver.displays`). These UUIDs differ between Macs and displays, so
+//! they can be looked up by role instead: `builtin` for the Mac's own screen, `main` for the
+//! display with the menu bar, or the display's name.
+//!
+//! ```no_run
+//! use defaults_rs::displays;
+//!
+//! for display in displays::online() {
+//!     println!("{} {}", display.uuid, display.name);
+//! }
+//! let builtin = displays::resolve("builtin");
+//! ```
+
+use core_foundation::{base::TCFType, string::CFString};
+use core_foundation_sys::{
+    base::{CFRelease, CFTypeRef, kCFAllocatorDefault},
+    uuid::{CFUUIDCreateString, CFUUIDRef},
+};
+use std::process::Command;
+
+#[cfg_attr(
+    target_os = "macos",
+    link(name = "CoreGraphics", kind = "framework"),
+    link(name = "ColorSync", kind = "framework")
+)]
+unsafe extern "C" {
+    fn CGGetOnlineDisplayList(max: u32, displays: *mut u32, count: *mut u32) -> i32;
+    fn CGMainDisplayID() -> u32;
+    fn CGDisplayIsBuiltin(display: u32) -> u32;
+    fn CGDisplayCreateUUIDFromDisplayID(display: u32) -> CFUUIDRef;
+}
+
+/// A display connected to this Mac.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Display {
+    /// The UUID preferences are keyed by, uppercased.
+    pub uuid: String,
+    /// The name System Settings shows, or a generic one if it couldn't be determined.
+    pub name: String,
+    /// Whether this is the Mac's own screen.
+    pub builtin: bool,
+    /// Whether this display has the menu bar.
+    pub main: bool,
+}
+
+/// Display names from System Information, by display ID.
+fn names() -> Vec<(u32, String)> {
+    let Ok(out) = Command::new("system_profiler")
+        .args(["SPDisplaysDataType", "-json"])
+        .output()
+    else {
+        return Vec::new();
+    };
+    let Ok(json) = serde_json::from_slice::<serde_json::Value>(&out.stdout) else {
+        return Vec::new();
+    };
+    // Displays are listed under the graphics card driving them.
+    json["SPDisplaysDataType"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|gpu| gpu["spdisplays_ndrvs"].as_array())
+        .flatten()
+        .filter_map(|display| {
+            let id = display["_spdisplays_displayID"].as_str()?;
+            let id = id.parse().or_else(|_| u32::from_str_radix(id, 16)).ok()?;
+            Some((id, display["_name"].as_str()?.to_string()))
+        })
+        .collect()
+}
+
+fn uuid_of(id: u32) -> Option<String> {
+    let uuid = unsafe { CGDisplayCreateUUIDFromDisplayID(id) };
+    if uuid.is_null() {
+        return None;
+    }
+    let text = unsafe {
+        let cfstr = CFUUIDCreateString(kCFAllocatorDefault, uuid);
+        CFRelease(uuid as CFTypeRef);
+        CFString::wrap_under_create_rule(cfstr).to_string()
+    };
+    Some(text.to_ascii_uppercase())
+}
+
+/// The displays connected to this Mac, the main display first.
+pub fn online() -> Vec<Display> {
+    let mut ids = [0u32; 32];
+    let mut count = 0;
+    if unsafe { CGGetOnlineDisplayList(ids.len() as u32, ids.as_mut_ptr(), &mut count) } != 0 {
+        return Vec::new();
+    }
+    let main = unsafe { CGMainDisplayID() };
+    let names = names();
+
+    let mut displays: Vec<Display> = ids[..count as usize]
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &id)| {
+            let builtin = unsafe { CGDisplayIsBuiltin(id) } != 0;
+            let name = names
+                .iter()
+                .find(|(n, _)| *n == id)
+                .map(|(_, name)| name.clone())
+                .unwrap_or_else(|| {
+                    if builtin {
+                        "Built-in display".to_string()
+                    } else {
+                        format!("Display {}", i + 1)
+                    }
+                });
+            Some(Display {
+                uuid: uuid_of(id)?,
+                name,
+                builtin,
+                main: id == main,
+            })
+        })
+        .collect();
+    displays.sort_by_key(|d| !d.main);
+    displays
+}
+
+/// The UUID of the display called `symbol`: `builtin`, `main`, or a display's name, ignoring
+/// case. `None` if no such display is connected.
+pub fn resolve(symbol: &str) -> Option<String> {
+    online()
+        .into_iter()
+        .find(|d| match symbol {
+            "builtin" => d.builtin,
+            "main" => d.main,
+            name => d.name.eq_ignore_ascii_case(name),
+        })
+        .map(|d| d.uuid)
+}
+
+/// The name of the connected display with `uuid`, ignoring case.
+pub fn name_of(uuid: &str) -> Option<String> {
+    online()
+        .into_iter()
+        .find(|d| d.uuid.eq_ignore_ascii_case(uuid))
+        .map(|d| d.name)
+}
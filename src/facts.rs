@@ -0,0 +1,111 @@
+// SPDX-License-Identifier: MIT
+
+//! Facts about the system drs runs on, which manifest conditions are evaluated against.
+//!
+//! ```no_run
+//! let facts = defaults_rs::facts::gather();
+//! println!("{}", facts.to_json());
+//! ```
+
+use std::{
+    ffi::{CStr, c_char},
+    process::Command,
+};
+
+unsafe extern "C" {
+    fn gethostname(name: *mut c_char, len: usize) -> i32;
+}
+
+/// Where macOS records its version.
+const SYSTEM_VERSION: &str = "/System/Library/CoreServices/SystemVersion.plist";
+
+/// A snapshot of the facts about this Mac.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Facts {
+    /// The host name, without a trailing `.local`.
+    pub hostname: String,
+    /// The macOS version, e.g. `14.5`; empty if it couldn't be determined.
+    pub macos: String,
+    /// The hardware UUID, which also names ByHost plists; empty if it couldn't be determined.
+    pub hardware_uuid: String,
+    /// The CPU architecture drs was built for, `arm64` or `x86_64`.
+    pub arch: String,
+    /// The login name of the user running drs.
+    pub user: String,
+}
+
+fn hostname() -> String {
+    let mut buf = [0 as c_char; 256];
+    if unsafe { gethostname(buf.as_mut_ptr(), buf.len()) } != 0 {
+        return String::new();
+    }
+    // Truncated names aren't guaranteed to be terminated.
+    buf[buf.len() - 1] = 0;
+    let name = unsafe { CStr::from_ptr(buf.as_ptr()) }.to_string_lossy();
+    name.strip_suffix(".local").unwrap_or(&name).to_string()
+}
+
+fn macos_version() -> String {
+    plist::Value::from_file(SYSTEM_VERSION)
+        .ok()
+        .and_then(|v| v.into_dictionary())
+        .and_then(|mut d| d.remove("ProductVersion"))
+        .and_then(|v| v.into_string())
+        .unwrap_or_default()
+}
+
+/// The hardware UUID of this Mac, as reported by the I/O Registry.
+pub(crate) fn hardware_uuid() -> Option<String> {
+    let out = Command::new("ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+        .output()
+        .ok()?;
+    // Output contains a line like: "IOPlatformUUID" = "01234567-89AB-CDEF-0123-456789ABCDEF"
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .find(|line| line.contains("\"IOPlatformUUID\""))
+        .and_then(|line| line.split_once('='))
+        .map(|(_, uuid)| uuid.trim().trim_matches('"').to_string())
+        .filter(|uuid| !uuid.is_empty())
+}
+
+impl Facts {
+    /// The fact called `name`, as named in [`Self::to_json`].
+    pub fn get(&self, name: &str) -> Option<&str> {
+        Some(match name {
+            "hostname" => &self.hostname,
+            "macos" => &self.macos,
+            "hardware_uuid" => &self.hardware_uuid,
+            "arch" => &self.arch,
+            "user" => &self.user,
+            _ => return None,
+        })
+    }
+
+    /// The facts as a JSON object keyed by the names manifest conditions use.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "hostname": self.hostname,
+            "macos": self.macos,
+            "hardware_uuid": self.hardware_uuid,
+            "arch": self.arch,
+            "user": self.user,
+        })
+    }
+}
+
+/// Gather the facts about this Mac.
+pub fn gather() -> Facts {
+    Facts {
+        hostname: hostname(),
+        macos: macos_version(),
+        hardware_uuid: hardware_uuid().unwrap_or_default(),
+        arch: match std::env::consts::ARCH {
+            "aarch64" => "arm64".to_string(),
+            other => other.to_string(),
+        },
+        user: std::env::var("USER")
+            .or_else(|_| std::env::var("LOGNAME"))
+            .unwrap_or_default(),
+    }
+}
@@ -3,13 +3,51 @@
 //! Library API for defaults-rs: macOS preferences management in Rust.
 
 mod core;
-pub use core::types::PrefValue;
+pub mod displays;
+pub mod facts;
+pub use core::display::DisplayStyle;
+pub use core::file::FileTooLarge;
+pub use core::guard::RefusedReadOnly;
+pub use core::types::{PrefLayer, PrefValue};
 
 mod preferences;
 pub use preferences::Preferences;
-pub use preferences::types::{Domain, FindMatch};
+pub use preferences::cache::CacheStats;
+pub use preferences::client::{Backend, Host, PreferencesClient, PreferencesClientBuilder};
+pub use preferences::find::FindQuery;
+pub use preferences::hash::HashAlgo;
+pub use preferences::keypath::{KeyPath, PathSegment};
+pub use preferences::normalize::{Normalization, NormalizeRule};
+pub use preferences::report::{ApplyItem, ApplyOutcome, ApplyReport, ApplyStatus};
+#[cfg(feature = "test-fixtures")]
+pub use preferences::roundtrip::RoundTrip;
+#[cfg(feature = "test-fixtures")]
+pub use preferences::sandbox::TestSandbox;
+pub use preferences::screensaver::ScreenSaver;
+pub use preferences::transaction::{OnError, Transaction};
+pub use preferences::translate::{Translator, UuidMap};
+pub use preferences::types::{Domain, DomainPage, FindMatch, ForcedKey, MatchKind, PlistFormat};
+pub use preferences::user::UserScope;
+pub use preferences::watch::{DomainChange, WatchOptions};
 
 #[cfg(feature = "cli")]
 pub mod cli;
+
+#[cfg(feature = "unstable-raw")]
+pub mod raw;
+
+/// Pure parsers exposed for the fuzz targets in `fuzz/`. Not part of the stable API.
+#[doc(hidden)]
+pub mod fuzzing {
+    /// [`PrefValue::from_openstep`] parses old-style plist literals.
+    pub use crate::PrefValue;
+    #[cfg(feature = "cli")]
+    pub use crate::cli::parse_rename_pattern;
+    #[cfg(feature = "cli")]
+    pub use crate::cli::units::{parse_float, parse_int};
+    pub use crate::preferences::keypath::KeyPath;
+}
 #[cfg(feature = "cli")]
 pub mod prettifier;
+#[cfg(feature = "cli")]
+pub mod render;
@@ -3,12 +3,29 @@
 #[cfg(feature = "cli")]
 use anyhow::anyhow;
 #[cfg(feature = "cli")]
-use defaults_rs::cli::{build_cli, handle_subcommand};
+use defaults_rs::cli::{apple_flags, build_cli, handle_subcommand, query};
 
 /// main runner func
 #[cfg(feature = "cli")]
 fn main() {
-    let matches = build_cli().get_matches();
+    // `drs q` is polled by widgets, so it bypasses clap entirely.
+    if std::env::args_os().nth(1).is_some_and(|cmd| cmd == "q") {
+        let args: Result<Vec<String>, _> = std::env::args_os()
+            .skip(2)
+            .map(|arg| arg.into_string())
+            .collect();
+        let result = match args {
+            Ok(args) => query::run(&args),
+            Err(arg) => Err(anyhow!("{} is not valid UTF-8.", arg.to_string_lossy())),
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let matches = build_cli().get_matches_from(apple_flags(std::env::args_os()));
 
     let result = match matches.subcommand() {
         Some((cmd, sub_m)) => match handle_subcommand(cmd, sub_m) {
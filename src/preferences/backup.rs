@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: MIT
+
+//! Opt-in backups taken before a domain is modified.
+//!
+//! Once a backup directory is configured, the first mutating operation on each domain within the
+//! process exports that domain to `<dir>/<domain>/<timestamp>.plist` before anything is changed.
+//! Current-host and system domains go to `<dir>/ByHost/<domain>/` and `<dir>/System/<domain>/`.
+
+use anyhow::Result;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::{Domain, PrefValue, Preferences, core::guard};
+
+#[derive(Default)]
+struct BackupState {
+    dir: Option<PathBuf>,
+    done: HashSet<Domain>,
+}
+
+static STATE: Mutex<Option<BackupState>> = Mutex::new(None);
+
+fn state() -> MutexGuard<'static, Option<BackupState>> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub(crate) fn set_dir(dir: Option<PathBuf>) {
+    state().get_or_insert_with(BackupState::default).dir = dir;
+}
+
+pub(crate) fn dir() -> Option<PathBuf> {
+    state().as_ref().and_then(|s| s.dir.clone())
+}
+
+/// The folder inside `dir` holding the backups of `domain`.
+pub(crate) fn domain_dir(dir: &Path, domain: &Domain) -> PathBuf {
+    match domain {
+        Domain::ByHost(name) => dir.join("ByHost").join(name),
+        Domain::System(name) => dir.join("System").join(name),
+        Domain::Path(path) => dir
+            .join("Files")
+            .join(path.file_stem().unwrap_or(path.as_os_str())),
+        _ => dir.join(domain.get_cf_name()),
+    }
+}
+
+/// Back up `domain` if backups are enabled and it hasn't been backed up by this process yet.
+pub(crate) fn ensure(domain: &Domain) -> Result<()> {
+    if guard::is_read_only() {
+        return Ok(());
+    }
+
+    let dir = {
+        let mut state = state();
+        let Some(state) = state.as_mut() else {
+            return Ok(());
+        };
+        let Some(dir) = state.dir.clone() else {
+            return Ok(());
+        };
+        if !state.done.insert(domain.clone()) {
+            return Ok(());
+        }
+        dir
+    };
+
+    match Preferences::read_domain(domain.clone())? {
+        PrefValue::Dictionary(dict) if dict.is_empty() => return Ok(()),
+        _ => {}
+    }
+
+    let domain_dir = domain_dir(&dir, domain);
+    std::fs::create_dir_all(&domain_dir)?;
+
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let path = domain_dir.join(format!("{stamp}.plist"));
+    Preferences::export(domain.clone(), &path.to_string_lossy())
+}
@@ -0,0 +1,132 @@
+// SPDX-License-Identifier: MIT
+
+//! Opt-in, size-bounded cache of recently read domains.
+//!
+//! Whole-domain reads are kept per process, keyed by [`Domain`], together with the modification
+//! time of the domain's plist. A cached entry is only used while that time is unchanged, and every
+//! mutating operation of this crate drops the entries of the domain it touches. Once the cache is
+//! full, the least recently used domain is evicted.
+
+use anyhow::Result;
+use std::{
+    fs,
+    sync::{Mutex, MutexGuard},
+    time::SystemTime,
+};
+
+use crate::{
+    Domain, PrefValue,
+    core::{file, foundation},
+};
+
+/// Counters describing the domain cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Maximum number of cached domains; 0 while caching is disabled.
+    pub capacity: usize,
+    /// Number of domains currently cached.
+    pub entries: usize,
+    /// Reads answered from the cache.
+    pub hits: u64,
+    /// Reads which went to CoreFoundation.
+    pub misses: u64,
+}
+
+struct Entry {
+    domain: Domain,
+    mtime: Option<SystemTime>,
+    value: PrefValue,
+}
+
+#[derive(Default)]
+struct CacheState {
+    stats: CacheStats,
+    /// Entries ordered from least to most recently used.
+    entries: Vec<Entry>,
+}
+
+static STATE: Mutex<Option<CacheState>> = Mutex::new(None);
+
+fn state() -> MutexGuard<'static, Option<CacheState>> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn mtime(domain: &Domain) -> Option<SystemTime> {
+    domain
+        .plist_path()
+        .and_then(|path| fs::metadata(path).ok())
+        .and_then(|meta| meta.modified().ok())
+}
+
+pub(crate) fn set_capacity(capacity: usize) {
+    let mut state = state();
+    let state = state.get_or_insert_with(CacheState::default);
+
+    state.stats.capacity = capacity;
+    let excess = state.entries.len().saturating_sub(capacity);
+    state.entries.drain(..excess);
+}
+
+pub(crate) fn stats() -> CacheStats {
+    state()
+        .as_ref()
+        .map_or_else(CacheStats::default, |s| CacheStats {
+            entries: s.entries.len(),
+            ..s.stats
+        })
+}
+
+/// Drop the cached entry of a domain after it was modified.
+pub(crate) fn invalidate(domain: &Domain) {
+    if let Some(state) = state().as_mut() {
+        state.entries.retain(|e| e.domain != *domain);
+    }
+}
+
+/// Read a whole domain, answering from the cache when it is enabled and still fresh.
+pub(crate) fn read_domain(domain: &Domain) -> Result<PrefValue> {
+    if let Some(path) = domain.plist_path() {
+        file::check_size(&path)?;
+    }
+
+    let enabled = state().as_ref().is_some_and(|s| s.stats.capacity > 0);
+    if !enabled {
+        return foundation::read_pref_domain(domain);
+    }
+
+    let current = mtime(domain);
+    {
+        let mut state = state();
+        if let Some(state) = state.as_mut()
+            && let Some(pos) = state
+                .entries
+                .iter()
+                .position(|e| e.domain == *domain && e.mtime == current)
+        {
+            let entry = state.entries.remove(pos);
+            let value = entry.value.clone();
+            state.entries.push(entry);
+            state.stats.hits += 1;
+            return Ok(value);
+        }
+    }
+
+    let value = foundation::read_pref_domain(domain)?;
+
+    let mut state = state();
+    if let Some(state) = state.as_mut() {
+        state.stats.misses += 1;
+        state.entries.retain(|e| e.domain != *domain);
+        if state.stats.capacity > 0 {
+            if state.entries.len() >= state.stats.capacity {
+                state.entries.remove(0);
+            }
+            state.entries.push(Entry {
+                domain: domain.clone(),
+                mtime: current,
+                value: value.clone(),
+            });
+        }
+    }
+    Ok(value)
+}
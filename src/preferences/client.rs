@@ -0,0 +1,272 @@
+// SPDX-License-Identifier: MIT
+
+//! Configured handles to preferences.
+//!
+//! [`Preferences`] is a set of static functions working on whatever [`Domain`] they are given.
+//! A [`PreferencesClient`] carries a scope instead, the host, user and backend, and maps every
+//! domain passed to it accordingly, so differently scoped clients can be used side by side:
+//!
+//! ```no_run
+//! use defaults_rs::{Backend, Domain, Host, PrefValue, PreferencesClient, UserScope};
+//!
+//! let dock = Domain::User("com.apple.dock".into());
+//! let here = PreferencesClient::builder().host(Host::Current).build();
+//! let newhire = PreferencesClient::builder()
+//!     .user(UserScope::lookup("newhire").unwrap())
+//!     .build();
+//! let files = PreferencesClient::builder().backend(Backend::File).read_only(true).build();
+//!
+//! here.write(&dock, "autohide", PrefValue::Boolean(true)).unwrap();
+//! newhire.write(&dock, "autohide", PrefValue::Boolean(true)).unwrap();
+//! println!("{}", files.read(&dock, "autohide").unwrap());
+//! ```
+//!
+//! Process-wide settings such as [`Preferences::set_read_only`], backups and the cache apply to
+//! clients as well.
+
+use anyhow::{Result, bail};
+use std::{collections::HashMap, io::Write};
+
+use crate::{
+    ApplyReport, Domain, HashAlgo, KeyPath, PlistFormat, PrefValue, Preferences, RefusedReadOnly,
+    UserScope,
+};
+
+/// Which host's preferences a client uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Host {
+    /// The preferences shared by every Mac the user logs in to, as `defaults` uses by default.
+    #[default]
+    Any,
+    /// The current host's (ByHost) preferences, as with `defaults -currentHost`.
+    Current,
+}
+
+/// How a client reaches preferences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Through CFPreferences, which running apps see immediately.
+    #[default]
+    CoreFoundation,
+    /// Reading and writing the plist files backing each domain directly, as with
+    /// [`Domain::Path`].
+    File,
+}
+
+/// Builder for a [`PreferencesClient`].
+#[derive(Debug, Clone, Default)]
+pub struct PreferencesClientBuilder {
+    client: PreferencesClient,
+}
+
+impl PreferencesClientBuilder {
+    /// Use this host's preferences ([`Host::Any`] by default).
+    pub fn host(mut self, host: Host) -> Self {
+        self.client.host = host;
+        self
+    }
+
+    /// Use another user's preferences, which always goes through their plist files.
+    pub fn user(mut self, user: UserScope) -> Self {
+        self.client.user = Some(user);
+        self
+    }
+
+    /// Use this backend ([`Backend::CoreFoundation`] by default).
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.client.backend = backend;
+        self
+    }
+
+    /// Refuse every modification made through this client with [`RefusedReadOnly`].
+    pub fn read_only(mut self, enabled: bool) -> Self {
+        self.client.read_only = enabled;
+        self
+    }
+
+    /// Create the client.
+    pub fn build(self) -> PreferencesClient {
+        self.client
+    }
+}
+
+/// A handle to preferences with its own host, user and backend.
+#[derive(Debug, Clone, Default)]
+pub struct PreferencesClient {
+    host: Host,
+    user: Option<UserScope>,
+    backend: Backend,
+    read_only: bool,
+}
+
+impl PreferencesClient {
+    /// Start configuring a client. Without any options it behaves like [`Preferences`].
+    pub fn builder() -> PreferencesClientBuilder {
+        PreferencesClientBuilder::default()
+    }
+
+    /// The host this client uses.
+    pub fn host(&self) -> Host {
+        self.host
+    }
+
+    /// The user this client uses, if not the current one.
+    pub fn user(&self) -> Option<&UserScope> {
+        self.user.as_ref()
+    }
+
+    /// The backend this client uses.
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Whether this client refuses modifications.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// The domain this client actually operates on for `domain`.
+    ///
+    /// [`Domain::System`] and [`Domain::Path`] domains aren't host- or user-specific and are
+    /// only affected by the backend.
+    pub fn domain(&self, domain: &Domain) -> Result<Domain> {
+        let domain = match (self.host, domain) {
+            (Host::Current, Domain::User(_) | Domain::Global) => domain.current_host(),
+            _ => domain.clone(),
+        };
+        if let Some(user) = &self.user {
+            return user.domain(&domain);
+        }
+        if self.backend == Backend::CoreFoundation || domain.file().is_some() {
+            return Ok(domain);
+        }
+        match domain.plist_path() {
+            Some(path) => Ok(Domain::Path(path)),
+            None => bail!("{domain} has no plist yet."),
+        }
+    }
+
+    /// The mapped domain, failing with [`RefusedReadOnly`] for read-only clients.
+    fn writable(&self, domain: &Domain) -> Result<Domain> {
+        let domain = self.domain(domain)?;
+        if self.read_only {
+            return Err(RefusedReadOnly {
+                target: domain.to_string(),
+            }
+            .into());
+        }
+        Ok(domain)
+    }
+
+    /// [`Preferences::read`] in this client's scope.
+    pub fn read(&self, domain: &Domain, key: &str) -> Result<PrefValue> {
+        Preferences::read(self.domain(domain)?, key)
+    }
+
+    /// [`Preferences::read_path`] in this client's scope.
+    pub fn read_path(&self, domain: &Domain, path: &KeyPath) -> Result<PrefValue> {
+        Preferences::read_path(self.domain(domain)?, path)
+    }
+
+    /// [`Preferences::read_batch`] in this client's scope.
+    pub fn read_batch(&self, domain: &Domain, keys: &[&str]) -> Result<HashMap<String, PrefValue>> {
+        Preferences::read_batch(self.domain(domain)?, keys)
+    }
+
+    /// [`Preferences::read_domain`] in this client's scope.
+    pub fn read_domain(&self, domain: &Domain) -> Result<PrefValue> {
+        Preferences::read_domain(self.domain(domain)?)
+    }
+
+    /// [`Preferences::read_type`] in this client's scope.
+    pub fn read_type(&self, domain: &Domain, key: &str) -> Result<String> {
+        Preferences::read_type(self.domain(domain)?, key)
+    }
+
+    /// [`Preferences::hash_domain`] in this client's scope.
+    pub fn hash_domain(&self, domain: &Domain, algo: HashAlgo) -> Result<String> {
+        Preferences::hash_domain(self.domain(domain)?, algo)
+    }
+
+    /// [`Preferences::write`] in this client's scope.
+    pub fn write(&self, domain: &Domain, key: &str, value: PrefValue) -> Result<()> {
+        Preferences::write(self.writable(domain)?, key, value)
+    }
+
+    /// [`Preferences::delete`] in this client's scope.
+    pub fn delete(&self, domain: &Domain, key: &str) -> Result<()> {
+        Preferences::delete(self.writable(domain)?, key)
+    }
+
+    /// [`Preferences::delete_domain`] in this client's scope.
+    pub fn delete_domain(&self, domain: &Domain) -> Result<()> {
+        Preferences::delete_domain(self.writable(domain)?)
+    }
+
+    /// [`Preferences::rename`] in this client's scope.
+    pub fn rename(&self, domain: &Domain, old_key: &str, new_key: &str) -> Result<()> {
+        Preferences::rename(self.writable(domain)?, old_key, new_key)
+    }
+
+    /// [`Preferences::array_insert`] in this client's scope.
+    pub fn array_insert(
+        &self,
+        domain: &Domain,
+        path: &KeyPath,
+        index: usize,
+        value: PrefValue,
+    ) -> Result<()> {
+        Preferences::array_insert(self.writable(domain)?, path, index, value)
+    }
+
+    /// [`Preferences::array_remove`] in this client's scope.
+    pub fn array_remove(&self, domain: &Domain, path: &KeyPath, index: usize) -> Result<PrefValue> {
+        Preferences::array_remove(self.writable(domain)?, path, index)
+    }
+
+    /// [`Preferences::array_append`] in this client's scope.
+    pub fn array_append(&self, domain: &Domain, key: &str, values: Vec<PrefValue>) -> Result<()> {
+        Preferences::array_append(self.writable(domain)?, key, values)
+    }
+
+    /// [`Preferences::dict_merge`] in this client's scope.
+    pub fn dict_merge(
+        &self,
+        domain: &Domain,
+        key: &str,
+        map: HashMap<String, PrefValue>,
+    ) -> Result<()> {
+        Preferences::dict_merge(self.writable(domain)?, key, map)
+    }
+
+    /// [`Preferences::array_move`] in this client's scope.
+    pub fn array_move(
+        &self,
+        domain: &Domain,
+        path: &KeyPath,
+        from: usize,
+        to: usize,
+    ) -> Result<()> {
+        Preferences::array_move(self.writable(domain)?, path, from, to)
+    }
+
+    /// [`Preferences::import`] in this client's scope.
+    pub fn import(&self, domain: &Domain, import_path: &str) -> Result<ApplyReport> {
+        Preferences::import(self.writable(domain)?, import_path)
+    }
+
+    /// [`Preferences::export`] in this client's scope.
+    pub fn export(&self, domain: &Domain, export_path: &str) -> Result<()> {
+        Preferences::export(self.domain(domain)?, export_path)
+    }
+
+    /// [`Preferences::export_to`] in this client's scope.
+    pub fn export_to(
+        &self,
+        domain: &Domain,
+        out: &mut impl Write,
+        format: PlistFormat,
+    ) -> Result<()> {
+        Preferences::export_to(self.domain(domain)?, out, format)
+    }
+}
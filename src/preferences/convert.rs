@@ -2,13 +2,14 @@ use anyhow::{Context, Result, bail};
 use plist::{Uid, Value};
 use std::{
     collections::HashMap,
+    io::Cursor,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use crate::PrefValue;
-
-// Apple epoch is Jan 1, 2001, which is 978307200 seconds after UNIX_EPOCH
-static APPLE_EPOCH_UNIX: u64 = 978307200;
+use crate::{
+    PrefValue,
+    core::types::{APPLE_DATE_RANGE, APPLE_EPOCH},
+};
 
 pub(crate) fn plist_to_prefvalue(val: &Value) -> Result<PrefValue> {
     let val = match val {
@@ -38,7 +39,7 @@ pub(crate) fn plist_to_prefvalue(val: &Value) -> Result<PrefValue> {
                 .duration_since(UNIX_EPOCH)
                 .context("Failed to calculate duration since UNIX_EPOCH when converting.")?
                 .as_secs_f64();
-            let seconds_since_apple_epoch = duration_since_unix - APPLE_EPOCH_UNIX as f64;
+            let seconds_since_apple_epoch = duration_since_unix - APPLE_EPOCH as f64;
             PrefValue::Date(seconds_since_apple_epoch)
         }
         Value::Uid(uid) => PrefValue::Uid(uid.get()),
@@ -62,10 +63,18 @@ pub(crate) fn prefvalue_to_plist(val: &PrefValue) -> Value {
         ),
         PrefValue::Data(data) => Value::Data(data.clone().to_vec()),
         PrefValue::Date(dt) => {
-            let secs = APPLE_EPOCH_UNIX as f64 + *dt;
-            let system_time = UNIX_EPOCH
-                + Duration::from_secs(secs as u64)
-                + Duration::from_nanos((secs.fract() * 1e9) as u64);
+            // Plist dates only cover years 1 to 9999, so dates outside are clamped to that range.
+            let dt = if dt.is_nan() {
+                0.0
+            } else {
+                dt.clamp(*APPLE_DATE_RANGE.start(), *APPLE_DATE_RANGE.end())
+            };
+            let secs = APPLE_EPOCH as f64 + dt;
+            let system_time = if secs >= 0.0 {
+                UNIX_EPOCH + Duration::from_secs_f64(secs)
+            } else {
+                UNIX_EPOCH - Duration::from_secs_f64(-secs)
+            };
             Value::Date(plist::Date::from(system_time))
         }
         PrefValue::Url(url) => Value::String(url.clone()),
@@ -73,3 +82,76 @@ pub(crate) fn prefvalue_to_plist(val: &PrefValue) -> Value {
         PrefValue::Uid(uid) => Value::Uid(Uid::new(*uid)),
     }
 }
+
+impl PrefValue {
+    /// Parse an XML plist value such as `<date>2024-05-01T12:00:00Z</date>`, or a `<dict>` copied
+    /// out of a plist. Complete plist documents are accepted as well.
+    ///
+    /// ```
+    /// use defaults_rs::PrefValue;
+    ///
+    /// let value = PrefValue::from_plist_fragment("<array><integer>1</integer></array>").unwrap();
+    /// assert_eq!(value, PrefValue::Array(vec![PrefValue::Integer(1)]));
+    /// ```
+    pub fn from_plist_fragment(text: &str) -> Result<PrefValue> {
+        let document = if text.contains("<plist") {
+            text.to_string()
+        } else {
+            format!(r#"<?xml version="1.0" encoding="UTF-8"?><plist version="1.0">{text}</plist>"#)
+        };
+        let value = Value::from_reader_xml(Cursor::new(document.as_bytes()))
+            .context("Invalid plist fragment")?;
+        plist_to_prefvalue(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn out_of_range_dates_are_clamped() -> Result<()> {
+        for ts in [f64::NAN, f64::INFINITY, -1e300, 1e300] {
+            let mut xml = Vec::new();
+            prefvalue_to_plist(&PrefValue::Date(ts)).to_writer_xml(&mut xml)?;
+        }
+        let Value::Date(date) = prefvalue_to_plist(&PrefValue::Date(-0.5)) else {
+            bail!("not a date")
+        };
+        assert_eq!(date.to_xml_format(), "2000-12-31T23:59:59.5Z");
+        Ok(())
+    }
+
+    #[test]
+    fn parses_plist_fragments() -> Result<()> {
+        assert_eq!(
+            PrefValue::from_plist_fragment("<date>2001-01-01T00:00:01Z</date>")?,
+            PrefValue::Date(1.0)
+        );
+        assert_eq!(
+            PrefValue::from_plist_fragment("<dict><key>a</key><data>AQI=</data></dict>")?,
+            PrefValue::Dictionary(HashMap::from([(
+                "a".into(),
+                PrefValue::Data(vec![1, 2].into())
+            )]))
+        );
+        let document = r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><string>x</string></plist>"#;
+        assert_eq!(
+            PrefValue::from_plist_fragment(document)?,
+            PrefValue::String("x".into())
+        );
+        for invalid in [
+            "",
+            "<integer>x</integer>",
+            "<dict><key>a</key></dict>",
+            "plain",
+        ] {
+            assert!(
+                PrefValue::from_plist_fragment(invalid).is_err(),
+                "{invalid}"
+            );
+        }
+        Ok(())
+    }
+}
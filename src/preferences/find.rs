@@ -0,0 +1,208 @@
+// SPDX-License-Identifier: MIT
+
+//! The query syntax of [`Preferences::find`](crate::Preferences::find).
+//!
+//! A query is made of words, matched case-insensitively anywhere in the text. Words next to each
+//! other, or joined by `AND`, must all match; of words joined by `OR`, one is enough; a word
+//! prefixed with `-` must not match. `AND` binds tighter than `OR`, and parentheses group. Double
+//! quotes keep a phrase with spaces, or a word such as `OR` or `-1`, together.
+//!
+//! Words are matched against the domain, key path and value of each entry, so `dock AND autohide`
+//! finds `autohide` in `com.apple.dock`. Entries are only reported if one of the words not
+//! prefixed with `-` is part of the key or value itself, not just of the domain or a parent key.
+//!
+//! ```
+//! use defaults_rs::FindQuery;
+//!
+//! let query = FindQuery::parse("dock AND (autohide OR magnification) -launchpad").unwrap();
+//! assert!(query.matches("com.apple.dock autohide"));
+//! assert!(!query.matches("com.apple.dock launchpad-autohide"));
+//! ```
+
+use anyhow::{Result, bail};
+
+/// A parsed find query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FindQuery {
+    /// Text which must be contained, lowercased.
+    Word(String),
+    /// A query which must not match.
+    Not(Box<FindQuery>),
+    /// Queries which must all match.
+    And(Vec<FindQuery>),
+    /// Queries of which at least one must match.
+    Or(Vec<FindQuery>),
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Word(String),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '"' => {
+                chars.next();
+                let mut phrase = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => phrase.push(c),
+                        None => bail!("Unclosed quote in the query."),
+                    }
+                }
+                tokens.push(Token::Word(phrase));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '"') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                match word.as_str() {
+                    "AND" => tokens.push(Token::And),
+                    "OR" => tokens.push(Token::Or),
+                    "-" if chars.peek().is_some_and(|c| matches!(c, '(' | '"')) => {
+                        tokens.push(Token::Not)
+                    }
+                    _ => match word.strip_prefix('-').filter(|w| !w.is_empty()) {
+                        Some(rest) => {
+                            tokens.push(Token::Not);
+                            tokens.push(Token::Word(rest.to_string()));
+                        }
+                        None => tokens.push(Token::Word(word)),
+                    },
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    fn or(&mut self) -> Result<FindQuery> {
+        let mut any = vec![self.and()?];
+        while self.tokens.next_if_eq(&Token::Or).is_some() {
+            any.push(self.and()?);
+        }
+        Ok(if any.len() == 1 {
+            any.remove(0)
+        } else {
+            FindQuery::Or(any)
+        })
+    }
+
+    fn and(&mut self) -> Result<FindQuery> {
+        let mut all = vec![self.unary()?];
+        loop {
+            match self.tokens.peek() {
+                None | Some(Token::Or | Token::Close) => break,
+                Some(Token::And) => {
+                    self.tokens.next();
+                }
+                Some(_) => {}
+            }
+            all.push(self.unary()?);
+        }
+        Ok(if all.len() == 1 {
+            all.remove(0)
+        } else {
+            FindQuery::And(all)
+        })
+    }
+
+    fn unary(&mut self) -> Result<FindQuery> {
+        match self.tokens.next() {
+            Some(Token::Not) => Ok(FindQuery::Not(Box::new(self.unary()?))),
+            Some(Token::Word(word)) => Ok(FindQuery::Word(word.to_lowercase())),
+            Some(Token::Open) => {
+                let inner = self.or()?;
+                if self.tokens.next() != Some(Token::Close) {
+                    bail!("Unclosed parenthesis in the query.");
+                }
+                Ok(inner)
+            }
+            Some(Token::Close) => bail!("Unexpected ) in the query."),
+            Some(Token::And | Token::Or) | None => {
+                bail!("Expected a word in the query; quote AND and OR to search for them.")
+            }
+        }
+    }
+}
+
+impl FindQuery {
+    /// Parse a query; a single word parses to [`FindQuery::Word`].
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?.into_iter().peekable(),
+        };
+        if parser.tokens.peek().is_none() {
+            bail!("The query is empty.");
+        }
+        let query = parser.or()?;
+        if parser.tokens.next().is_some() {
+            bail!("Unexpected ) in the query.");
+        }
+        if !query.has_word() {
+            bail!("The query needs at least one word not prefixed with -.");
+        }
+        Ok(query)
+    }
+
+    /// Whether `text` satisfies the query, ignoring case.
+    pub fn matches(&self, text: &str) -> bool {
+        self.matches_lower(&text.to_lowercase())
+    }
+
+    pub(crate) fn matches_lower(&self, text: &str) -> bool {
+        match self {
+            FindQuery::Word(word) => text.contains(word.as_str()),
+            FindQuery::Not(query) => !query.matches_lower(text),
+            FindQuery::And(all) => all.iter().all(|q| q.matches_lower(text)),
+            FindQuery::Or(any) => any.iter().any(|q| q.matches_lower(text)),
+        }
+    }
+
+    /// Whether any word not prefixed with `-` is part of the lowercase `text`.
+    pub(crate) fn hits(&self, text: &str) -> bool {
+        match self {
+            FindQuery::Word(word) => text.contains(word.as_str()),
+            FindQuery::Not(_) => false,
+            FindQuery::And(queries) | FindQuery::Or(queries) => {
+                queries.iter().any(|q| q.hits(text))
+            }
+        }
+    }
+
+    fn has_word(&self) -> bool {
+        match self {
+            FindQuery::Word(_) => true,
+            FindQuery::Not(_) => false,
+            FindQuery::And(queries) | FindQuery::Or(queries) => {
+                queries.iter().any(FindQuery::has_word)
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: MIT
+
+//! Canonical content hashing of preference values.
+//!
+//! Values are first encoded into a canonical byte stream (type-tagged, length-prefixed, with
+//! dictionary entries sorted by key) and then hashed. Two domains with the same contents therefore
+//! hash identically no matter the key order or the plist format they were stored in.
+
+use crate::PrefValue;
+
+/// Hash algorithm used by [`Preferences::hash_domain`](crate::Preferences::hash_domain).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashAlgo {
+    /// SHA-256, rendered as 64 hex characters.
+    #[default]
+    Sha256,
+    /// 64-bit FNV-1a, rendered as 16 hex characters. Fast, but not collision resistant.
+    Fnv1a64,
+}
+
+impl HashAlgo {
+    /// Hash the canonical encoding of `val` and render it as lowercase hex.
+    pub(crate) fn hash(self, val: &PrefValue) -> String {
+        let mut buf = Vec::new();
+        encode(val, &mut buf);
+
+        match self {
+            HashAlgo::Sha256 => sha256(&buf).iter().map(|b| format!("{b:02x}")).collect(),
+            HashAlgo::Fnv1a64 => format!("{:016x}", fnv1a64(&buf)),
+        }
+    }
+}
+
+fn encode_len(len: usize, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&(len as u64).to_le_bytes());
+}
+
+fn encode_bytes(tag: u8, bytes: &[u8], buf: &mut Vec<u8>) {
+    buf.push(tag);
+    encode_len(bytes.len(), buf);
+    buf.extend_from_slice(bytes);
+}
+
+fn encode_float(tag: u8, f: f64, buf: &mut Vec<u8>) {
+    buf.push(tag);
+    let f = if f.is_nan() { f64::NAN } else { f };
+    buf.extend_from_slice(&f.to_bits().to_le_bytes());
+}
+
+/// Writes the canonical encoding of a value.
+fn encode(val: &PrefValue, buf: &mut Vec<u8>) {
+    match val {
+        PrefValue::String(s) => encode_bytes(b's', s.as_bytes(), buf),
+        PrefValue::Integer(i) => {
+            buf.push(b'i');
+            buf.extend_from_slice(&i.to_le_bytes());
+        }
+        PrefValue::Float(f) => encode_float(b'f', *f, buf),
+        PrefValue::Boolean(b) => buf.extend_from_slice(&[b'b', *b as u8]),
+        PrefValue::Array(arr) => {
+            buf.push(b'a');
+            encode_len(arr.len(), buf);
+            for v in arr {
+                encode(v, buf);
+            }
+        }
+        PrefValue::Dictionary(dict) => {
+            buf.push(b'd');
+            encode_len(dict.len(), buf);
+
+            let mut entries: Vec<_> = dict.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            for (k, v) in entries {
+                encode_bytes(b's', k.as_bytes(), buf);
+                encode(v, buf);
+            }
+        }
+        PrefValue::Data(data) => encode_bytes(b'D', data, buf),
+        PrefValue::Date(dt) => encode_float(b't', *dt, buf),
+        PrefValue::Url(url) => encode_bytes(b'u', url.as_bytes(), buf),
+        PrefValue::Uuid(uuid) => encode_bytes(b'U', uuid.to_uppercase().as_bytes(), buf),
+        PrefValue::Uid(uid) => {
+            buf.push(b'k');
+            buf.extend_from_slice(&uid.to_le_bytes());
+        }
+    }
+}
+
+fn fnv1a64(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+pub(crate) fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for chunk in msg.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in chunk.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for (k, wi) in SHA256_K.iter().zip(w) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(*k)
+                .wrapping_add(wi);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (state, v) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *state = state.wrapping_add(v);
+        }
+    }
+
+    let mut out = [0u8; 32];
+    for (chunk, v) in out.chunks_exact_mut(4).zip(h) {
+        chunk.copy_from_slice(&v.to_be_bytes());
+    }
+    out
+}
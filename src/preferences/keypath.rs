@@ -0,0 +1,187 @@
+// SPDX-License-Identifier: MIT
+
+//! Key paths for addressing values nested inside a domain.
+//!
+//! A key path is a list of dictionary keys and array indices, written as
+//! `persistent-apps[0].tile-data.file-label`. Keys which are empty or contain any of
+//! `. [ ] " \` are wrapped in double quotes, with `"` and `\` escaped by a backslash:
+//! `"NSToolbar Configuration.Browser".TB Item Identifiers[2]`. Outside quotes, a backslash
+//! escapes the character after it, so `NSToolbar Configuration\.Browser` is a single key too.
+//!
+//! Code which already knows the segments can skip the syntax with [`KeyPath::from_segments`].
+//!
+//! [`KeyPath`]'s `Display` output always parses back into the same path, which is what `find`
+//! prints and `read` accepts.
+
+use anyhow::{Result, bail};
+
+use crate::PrefValue;
+
+/// A single step in a [`KeyPath`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// A dictionary key.
+    Key(String),
+    /// An array index.
+    Index(usize),
+}
+
+/// A path to a value nested inside a domain.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyPath(Vec<PathSegment>);
+
+impl KeyPath {
+    /// Parse a key path from its textual form.
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut chars = input.chars().peekable();
+        let mut expect_key = true;
+
+        loop {
+            match chars.peek() {
+                None if expect_key => bail!("Key path is missing a key: {input}"),
+                None => break,
+                Some('[') => {
+                    if expect_key {
+                        bail!("Expected a key before `[` in key path: {input}");
+                    }
+                    chars.next();
+                    let mut digits = String::new();
+                    while let Some(d) = chars.next_if(char::is_ascii_digit) {
+                        digits.push(d);
+                    }
+                    if chars.next() != Some(']') || digits.is_empty() {
+                        bail!("Invalid array index in key path: {input}");
+                    }
+                    segments.push(PathSegment::Index(digits.parse()?));
+                    expect_key = false;
+                }
+                Some('.') if !expect_key => {
+                    chars.next();
+                    expect_key = true;
+                }
+                Some(_) if !expect_key => bail!("Expected `.` or `[` in key path: {input}"),
+                Some('"') => {
+                    chars.next();
+                    let mut key = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => match chars.next() {
+                                Some(c) => key.push(c),
+                                None => bail!("Unterminated escape in key path: {input}"),
+                            },
+                            Some(c) => key.push(c),
+                            None => bail!("Unterminated quote in key path: {input}"),
+                        }
+                    }
+                    segments.push(PathSegment::Key(key));
+                    expect_key = false;
+                }
+                Some(_) => {
+                    let mut key = String::new();
+                    while let Some(c) = chars.next_if(|c| !matches!(c, '.' | '[' | ']' | '"')) {
+                        if c == '\\' {
+                            match chars.next() {
+                                Some(c) => key.push(c),
+                                None => bail!("Unterminated escape in key path: {input}"),
+                            }
+                        } else {
+                            key.push(c);
+                        }
+                    }
+                    if key.is_empty() {
+                        bail!("Unexpected character in key path: {input}");
+                    }
+                    segments.push(PathSegment::Key(key));
+                    expect_key = false;
+                }
+            }
+        }
+
+        Ok(KeyPath(segments))
+    }
+
+    /// A path made of the given segments, taken literally.
+    ///
+    /// ```
+    /// use defaults_rs::{KeyPath, PathSegment};
+    ///
+    /// let path = KeyPath::from_segments([
+    ///     PathSegment::Key("NSToolbar Configuration.Browser".into()),
+    ///     PathSegment::Key("TB Item Identifiers".into()),
+    ///     PathSegment::Index(2),
+    /// ]);
+    /// let parsed = KeyPath::parse(r"NSToolbar Configuration\.Browser.TB Item Identifiers[2]");
+    /// assert_eq!(path, parsed.unwrap());
+    /// ```
+    pub fn from_segments(segments: impl IntoIterator<Item = PathSegment>) -> Self {
+        KeyPath(segments.into_iter().collect())
+    }
+
+    /// The segments making up this path.
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.0
+    }
+
+    /// Returns a new path with the given key appended.
+    pub fn key(&self, key: &str) -> Self {
+        let mut path = self.clone();
+        path.0.push(PathSegment::Key(key.to_string()));
+        path
+    }
+
+    /// Returns a new path with the given array index appended.
+    pub fn index(&self, index: usize) -> Self {
+        let mut path = self.clone();
+        path.0.push(PathSegment::Index(index));
+        path
+    }
+
+    /// Splits off the leading key, which names the top-level preference in a domain.
+    pub fn split_first(&self) -> Option<(&str, KeyPath)> {
+        match self.0.split_first()? {
+            (PathSegment::Key(key), rest) => Some((key, KeyPath(rest.to_vec()))),
+            _ => None,
+        }
+    }
+
+    /// Look up the value this path points to inside `root`.
+    pub fn resolve<'a>(&self, root: &'a PrefValue) -> Option<&'a PrefValue> {
+        self.0.iter().try_fold(root, |val, seg| match (seg, val) {
+            (PathSegment::Key(k), PrefValue::Dictionary(dict)) => dict.get(k),
+            (PathSegment::Index(i), PrefValue::Array(arr)) => arr.get(*i),
+            _ => None,
+        })
+    }
+
+    /// Look up the value this path points to inside `root` for changing it in place.
+    pub fn resolve_mut<'a>(&self, root: &'a mut PrefValue) -> Option<&'a mut PrefValue> {
+        self.0.iter().try_fold(root, |val, seg| match (seg, val) {
+            (PathSegment::Key(k), PrefValue::Dictionary(dict)) => dict.get_mut(k),
+            (PathSegment::Index(i), PrefValue::Array(arr)) => arr.get_mut(*i),
+            _ => None,
+        })
+    }
+}
+
+impl std::fmt::Display for KeyPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, seg) in self.0.iter().enumerate() {
+            match seg {
+                PathSegment::Index(idx) => write!(f, "[{idx}]")?,
+                PathSegment::Key(key) => {
+                    if i > 0 {
+                        write!(f, ".")?;
+                    }
+                    if key.is_empty() || key.contains(['.', '[', ']', '"', '\\']) {
+                        write!(f, "\"{}\"", key.replace('\\', "\\\\").replace('"', "\\\""))?;
+                    } else {
+                        write!(f, "{key}")?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -7,31 +7,103 @@
 //!
 //! It acts as the main interface between the CLI/library and the backend (CoreFoundation or file-based).
 
-mod convert;
+pub(crate) mod backup;
+pub mod cache;
+pub mod client;
+pub(crate) mod convert;
+pub mod find;
+pub mod hash;
+pub mod keypath;
+pub mod normalize;
+mod openstep;
+pub mod report;
+#[cfg(feature = "test-fixtures")]
+pub mod roundtrip;
+#[cfg(feature = "test-fixtures")]
+pub mod sandbox;
+pub mod screensaver;
+mod search_list;
+pub mod transaction;
+pub mod translate;
 pub mod types;
+pub mod user;
+pub mod watch;
 
 use anyhow::{Context, Result, bail};
 use std::{
-    collections::{HashMap, HashSet},
-    fs::{self, File},
-    io::Cursor,
-    path::PathBuf,
+    collections::{BTreeMap, HashMap, HashSet},
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+    time::Instant,
 };
 
 use crate::{
-    Domain, FindMatch, PrefValue,
+    ApplyReport, CacheStats, Domain, DomainChange, DomainPage, FindMatch, FindQuery, ForcedKey,
+    HashAlgo, KeyPath, MatchKind, NormalizeRule, PathSegment, PlistFormat, PrefLayer, PrefValue,
+    WatchOptions,
     preferences::convert::{plist_to_prefvalue, prefvalue_to_plist},
+    preferences::translate::Translator,
 };
 use plist::Value;
 
 /// Backend selection for preferences (CoreFoundation vs File)
-use crate::core::foundation;
+use crate::core::{file, foundation, guard};
 
 /// Provides operations for reading, writing, deleting, and managing
 /// macOS plist preference files in user or global domains.
 pub struct Preferences;
 
 impl Preferences {
+    /// Enable or disable read-only mode for the whole process.
+    ///
+    /// While enabled, every operation which would modify preferences fails with
+    /// [`RefusedReadOnly`](crate::RefusedReadOnly) instead.
+    pub fn set_read_only(enabled: bool) {
+        guard::set_read_only(enabled);
+    }
+
+    /// Whether read-only mode is enabled.
+    pub fn is_read_only() -> bool {
+        guard::is_read_only()
+    }
+
+    /// Refuse to load plists larger than `bytes` (64 MiB by default), failing with
+    /// [`FileTooLarge`](crate::FileTooLarge) instead. Pass `0` to remove the limit.
+    pub fn set_max_file_size(bytes: u64) {
+        file::set_max_size(bytes);
+    }
+
+    /// The maximum size of plists drs loads, `0` meaning no limit.
+    pub fn max_file_size() -> u64 {
+        file::max_size()
+    }
+
+    /// Enable backups before modifications, stored under the given directory.
+    ///
+    /// The first write, delete, rename or import touching a domain within this process exports
+    /// that domain to `<dir>/<domain>/<timestamp>.plist` beforehand. Pass `None` to disable.
+    pub fn set_backup_dir(dir: Option<PathBuf>) {
+        backup::set_dir(dir);
+    }
+
+    /// The directory backups are written to, if enabled.
+    pub fn backup_dir() -> Option<PathBuf> {
+        backup::dir()
+    }
+
+    /// Enable the in-memory cache of whole-domain reads, holding up to `capacity` domains.
+    ///
+    /// Cached domains are reused until their plist changes on disk or they are modified through
+    /// this crate. Pass 0 to disable the cache (the default).
+    pub fn set_cache_capacity(capacity: usize) {
+        cache::set_capacity(capacity);
+    }
+
+    /// Statistics of the domain cache.
+    pub fn cache_stats() -> CacheStats {
+        cache::stats()
+    }
+
     /// List all available domains.
     pub fn list_domains() -> Result<HashSet<Domain>> {
         let list = foundation::list_domains()?;
@@ -40,9 +112,12 @@ impl Preferences {
         Ok(domains)
     }
 
-    /// Search all domains for keys or values containing the given word (case-insensitive).
-    pub fn find(word: &str) -> Result<HashMap<Domain, Vec<FindMatch>>> {
-        let word_lower = word.to_lowercase();
+    /// Search all domains for keys or values matching `query`, ignoring case.
+    ///
+    /// A single word finds every key and value containing it; see [`FindQuery`] for combining
+    /// words with `AND`, `OR` and `-`.
+    pub fn find(query: &str) -> Result<HashMap<Domain, Vec<FindMatch>>> {
+        let query = FindQuery::parse(query)?;
         let mut results: std::collections::HashMap<Domain, Vec<FindMatch>> =
             std::collections::HashMap::new();
 
@@ -52,10 +127,10 @@ impl Preferences {
             .collect();
 
         for domain in domains {
-            let loaded = foundation::read_pref_domain(&domain.to_string())?;
+            let loaded = cache::read_domain(&domain)?;
             let mut matches = Vec::new();
 
-            Self::find_in_value(&loaded, &word_lower, String::new(), &mut matches);
+            Self::find_in_value(&domain, &loaded, &query, KeyPath::default(), &mut matches);
             if !matches.is_empty() {
                 results.insert(domain, matches);
             }
@@ -63,60 +138,249 @@ impl Preferences {
         Ok(results)
     }
 
-    /// Recursively searches a plist Value.
-    fn find_in_value(
+    /// Recursively searches a plist Value from `domain`.
+    ///
+    /// Match keys are rendered as [`KeyPath`]s, so they can be passed straight to [`Self::read`].
+    pub(crate) fn find_in_value(
+        domain: &Domain,
         val: &PrefValue,
-        word_lower: &str,
-        key_path: String,
+        query: &FindQuery,
+        key_path: KeyPath,
         matches: &mut Vec<FindMatch>,
     ) {
-        fn contains_word(haystack: &str, needle: &str) -> bool {
-            haystack.to_lowercase().contains(needle)
-        }
+        let found = |path: &KeyPath, kind: MatchKind, value: &PrefValue| {
+            #[cfg(feature = "cli")]
+            let snippet =
+                crate::render::render(domain, path, value).unwrap_or_else(|| value.to_string());
+            #[cfg(not(feature = "cli"))]
+            let snippet = value.to_string();
+            FindMatch {
+                domain: domain.clone(),
+                key: path.to_string(),
+                kind,
+                value: value.clone(),
+                value_type: value.get_type(),
+                snippet,
+            }
+        };
+        // Words may match the domain and parent keys too, but a match needs one in its own text.
+        let name = domain.get_cf_name();
+        let leaf_text = |v: &PrefValue| match v {
+            PrefValue::Array(_) | PrefValue::Dictionary(_) => String::new(),
+            v => v.to_string().to_lowercase(),
+        };
         match val {
             PrefValue::Dictionary(dict) => {
                 for (k, v) in dict {
-                    let new_key_path = if key_path.is_empty() {
-                        k.clone()
-                    } else {
-                        format!("{key_path}.{k}")
-                    };
-                    if contains_word(k, word_lower) {
-                        matches.push(FindMatch {
-                            key: new_key_path.clone(),
-                            value: v.clone(),
-                        });
+                    let new_key_path = key_path.key(k);
+                    let context = format!("{name} {new_key_path} {}", leaf_text(v)).to_lowercase();
+                    if query.hits(&k.to_lowercase()) && query.matches_lower(&context) {
+                        matches.push(found(&new_key_path, MatchKind::Key, v));
                     }
-                    Self::find_in_value(v, word_lower, new_key_path, matches);
+                    Self::find_in_value(domain, v, query, new_key_path, matches);
                 }
             }
             PrefValue::Array(arr) => {
                 for (i, v) in arr.iter().enumerate() {
-                    let new_key_path = format!("{key_path}[{i}]");
-                    Self::find_in_value(v, word_lower, new_key_path, matches);
+                    Self::find_in_value(domain, v, query, key_path.index(i), matches);
                 }
             }
             _ => {
-                if contains_word(&val.to_string(), word_lower) {
-                    matches.push(FindMatch {
-                        key: key_path.clone(),
-                        value: val.clone(),
-                    });
+                let text = leaf_text(val);
+                let context = format!("{name} {key_path} {text}").to_lowercase();
+                if query.hits(&text) && query.matches_lower(&context) {
+                    matches.push(found(&key_path, MatchKind::Value, val));
                 }
             }
         }
     }
 
     /// Read a value from the given domain and key.
+    ///
+    /// If no key with the exact given name exists, `key` is parsed as a [`KeyPath`] and the
+    /// nested value it points to is returned instead.
     pub fn read(domain: Domain, key: &str) -> Result<PrefValue> {
-        let cf_name = &domain.get_cf_name();
-        foundation::read_pref(cf_name, key)
+        match foundation::read_pref(&domain, key) {
+            Ok(val) => Ok(val),
+            Err(e) => match KeyPath::parse(key) {
+                Ok(path) if path.segments() != [PathSegment::Key(key.to_string())] => {
+                    Self::read_path(domain, &path)
+                }
+                _ => Err(e),
+            },
+        }
+    }
+
+    /// Read the value at a key path in the given domain.
+    pub fn read_path(domain: Domain, path: &KeyPath) -> Result<PrefValue> {
+        let (key, rest) = path
+            .split_first()
+            .context("Key path must start with a key.")?;
+        let root = foundation::read_pref(&domain, key)?;
+
+        rest.resolve(&root)
+            .cloned()
+            .with_context(|| format!("Key path not found for domain {domain}: {path}"))
+    }
+
+    /// The layers of the search list consulted when reading from `domain`, from the highest
+    /// precedence to the lowest.
+    ///
+    /// Suites an app adds at runtime aren't visible from outside the app and are not included.
+    pub fn search_list(domain: &Domain) -> Vec<PrefLayer> {
+        search_list::layers(domain)
+    }
+
+    /// Read `key` from a single layer of the search list, without falling back to other layers.
+    pub fn read_layer(domain: &Domain, key: &str, layer: &PrefLayer) -> Option<PrefValue> {
+        search_list::read_layer(domain, key, layer)
+    }
+
+    /// The search-list layers storing a value for `key`, from the highest precedence to the
+    /// lowest.
+    ///
+    /// An empty result means the key isn't stored anywhere: the app falls back to its built-in
+    /// default, and deleting the key has no effect.
+    pub fn value_layers(domain: Domain, key: &str) -> Vec<PrefLayer> {
+        search_list::layers(&domain)
+            .into_iter()
+            .filter(|layer| search_list::read_layer(&domain, key, layer).is_some())
+            .collect()
+    }
+
+    /// Whether `key` has a stored value at any layer, as opposed to only an app default.
+    pub fn is_set(domain: Domain, key: &str) -> bool {
+        !Self::value_layers(domain, key).is_empty()
+    }
+
+    /// Whether `key` is forced by a configuration profile (MDM). Values written to a forced key
+    /// are stored, but apps keep seeing the profile's value until it is removed.
+    pub fn is_forced(domain: Domain, key: &str) -> bool {
+        domain.file().is_none() && foundation::value_is_forced(&foundation::app_id(&domain), key)
+    }
+
+    /// Every key forced by configuration profiles, ordered by domain and key.
+    ///
+    /// Keys come from the managed preferences profiles install, and from the keys of every domain
+    /// which CFPreferences reports as forced.
+    pub fn forced_keys() -> Result<Vec<ForcedKey>> {
+        let mut forced: BTreeMap<(String, String), ForcedKey> = BTreeMap::new();
+        // Values for the current user come last and take precedence.
+        for (domain, values) in search_list::managed_values() {
+            for (key, value) in values {
+                let id = (domain.to_string(), key.clone());
+                let domain = domain.clone();
+                forced.insert(id, ForcedKey { domain, key, value });
+            }
+        }
+
+        let domains = Self::list_domains()?.into_iter().chain([Domain::Global]);
+        for domain in domains {
+            let Ok(PrefValue::Dictionary(dict)) = cache::read_domain(&domain) else {
+                continue;
+            };
+            let app = foundation::app_id(&domain);
+            for (key, value) in dict {
+                let id = (domain.to_string(), key.clone());
+                if forced.contains_key(&id) || !foundation::value_is_forced(&app, &key) {
+                    continue;
+                }
+                let value = foundation::copy_app_value(&app, &key).unwrap_or(value);
+                let domain = domain.clone();
+                forced.insert(id, ForcedKey { domain, key, value });
+            }
+        }
+        Ok(forced.into_values().collect())
+    }
+
+    /// Read several keys from the given domain at once.
+    ///
+    /// Keys which are not present in the domain are left out of the result.
+    pub fn read_batch(domain: Domain, keys: &[&str]) -> Result<HashMap<String, PrefValue>> {
+        Ok(keys
+            .iter()
+            .filter_map(|key| {
+                Self::read(domain.clone(), key)
+                    .ok()
+                    .map(|val| (key.to_string(), val))
+            })
+            .collect())
     }
 
     /// Read an entire domain.
     pub fn read_domain(domain: Domain) -> Result<PrefValue> {
-        let cf_name = &domain.get_cf_name();
-        foundation::read_pref_domain(cf_name)
+        cache::read_domain(&domain)
+    }
+
+    /// Read a page of an entire domain.
+    ///
+    /// Entries are sorted by key and start right after `cursor` (if given), holding at most
+    /// `limit` entries, which must be at least 1. Pass the returned `next_cursor` back in to fetch
+    /// the following page.
+    pub fn read_domain_page(
+        domain: Domain,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<DomainPage> {
+        if limit == 0 {
+            bail!("The page limit must be at least 1.")
+        }
+        let loaded = cache::read_domain(&domain)?;
+
+        let PrefValue::Dictionary(dict) = loaded else {
+            bail!("Cannot paginate a domain which is not a dictionary.")
+        };
+
+        let mut entries: Vec<(String, PrefValue)> = dict
+            .into_iter()
+            .filter(|(k, _)| cursor.is_none_or(|c| k.as_str() > c))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let next_cursor = if entries.len() > limit {
+            entries.truncate(limit);
+            entries.last().map(|(k, _)| k.clone())
+        } else {
+            None
+        };
+
+        Ok(DomainPage {
+            entries,
+            next_cursor,
+        })
+    }
+
+    /// Compute a canonical content hash of a domain.
+    ///
+    /// The hash only depends on the domain's keys and values, not on key order or the format
+    /// of the underlying plist, so it can be used to cheaply detect changes.
+    pub fn hash_domain(domain: Domain, algo: HashAlgo) -> Result<String> {
+        let loaded = Self::read_domain(domain)?;
+        Ok(algo.hash(&loaded))
+    }
+
+    /// Watch a domain for changes, blocking the current thread.
+    ///
+    /// `on_change` is called once per burst of changes (see [`WatchOptions`]) and watching stops
+    /// as soon as it returns `false`.
+    pub fn watch(
+        domain: Domain,
+        opts: &WatchOptions,
+        on_change: impl FnMut(&DomainChange) -> bool,
+    ) -> Result<()> {
+        watch::watch(vec![domain], opts, on_change)
+    }
+
+    /// Watch several domains for changes from a single polling loop, blocking the current thread.
+    ///
+    /// Each domain's changes are coalesced separately and reported with the domain they belong
+    /// to; watching stops as soon as `on_change` returns `false`.
+    pub fn watch_many(
+        domains: Vec<Domain>,
+        opts: &WatchOptions,
+        on_change: impl FnMut(&DomainChange) -> bool,
+    ) -> Result<()> {
+        watch::watch(domains, opts, on_change)
     }
 
     /// Write a value to the given domain and key.
@@ -124,30 +388,32 @@ impl Preferences {
     /// If the domain file does not exist, it will be created.
     /// If the key already exists, its value will be overwritten.
     pub fn write(domain: Domain, key: &str, value: PrefValue) -> Result<()> {
-        let cf_name = &domain.get_cf_name();
-        foundation::write_pref(cf_name, key, &value)?;
+        backup::ensure(&domain)?;
+        cache::invalidate(&domain);
+        foundation::write_pref(&domain, key, &value)?;
 
         Ok(())
     }
 
     /// Delete a key from the given domain.
     pub fn delete(domain: Domain, key: &str) -> Result<()> {
-        let cf_name = &domain.get_cf_name();
-        foundation::delete_key(cf_name, key)
+        backup::ensure(&domain)?;
+        cache::invalidate(&domain);
+        foundation::delete_key(&domain, key)
     }
 
     /// Delete a whole domain.
     pub fn delete_domain(domain: Domain) -> Result<()> {
-        let cf_name = &domain.get_cf_name();
-        foundation::delete_domain(cf_name)
+        backup::ensure(&domain)?;
+        cache::invalidate(&domain);
+        foundation::delete_domain(&domain)
     }
 
     /// Read the type of a value at the given key in the specified domain.
     ///
     /// Returns a string describing the type.
     pub fn read_type(domain: Domain, key: &str) -> Result<String> {
-        let cf_name = domain.get_cf_name();
-        let loaded = foundation::read_pref(&cf_name, key)?;
+        let loaded = foundation::read_pref(&domain, key)?;
 
         Ok(loaded.get_type().to_string())
     }
@@ -156,57 +422,339 @@ impl Preferences {
     ///
     /// Moves the value from `old_key` to `new_key` within the domain plist.
     pub fn rename(domain: Domain, old_key: &str, new_key: &str) -> Result<()> {
-        let cf_name = &domain.get_cf_name();
+        backup::ensure(&domain)?;
+        cache::invalidate(&domain);
 
         // Read old value
-        let val = foundation::read_pref(cf_name, old_key)?;
+        let val = foundation::read_pref(&domain, old_key)?;
 
-        foundation::write_pref(cf_name, new_key, &val)?;
-        foundation::delete_key(cf_name, old_key)?;
+        foundation::write_pref(&domain, new_key, &val)?;
+        foundation::delete_key(&domain, old_key)?;
 
         Ok(())
     }
 
-    /// Import a plist file into the specified domain.
+    /// Rename several keys in the given domain as a single operation.
     ///
-    /// Replaces any existing file for the domain.
-    pub fn import(domain: Domain, import_path: &str) -> Result<()> {
-        let data = fs::read(import_path)?;
+    /// All renames are validated up front: every old key must exist, and no new key may collide
+    /// with another target or with a key that is not being renamed away. If writing fails midway,
+    /// the keys touched so far are restored.
+    pub fn rename_many(domain: Domain, renames: &[(String, String)]) -> Result<()> {
+        let PrefValue::Dictionary(current) = foundation::read_pref_domain(&domain)? else {
+            bail!("Cannot rename keys in a domain which is not a dictionary.")
+        };
 
-        let plist_val = Value::from_reader(Cursor::new(&data))?;
+        let sources: HashSet<&str> = renames.iter().map(|(old, _)| old.as_str()).collect();
+        let mut targets = HashSet::new();
+        for (old, new) in renames {
+            if !current.contains_key(old) {
+                bail!("Key not found for domain {domain}: {old}");
+            }
+            if !targets.insert(new.as_str()) {
+                bail!("More than one key would be renamed to {new}.");
+            }
+            if current.contains_key(new) && !sources.contains(new.as_str()) {
+                bail!("Cannot rename {old} to {new}: {new} already exists.");
+            }
+        }
 
-        let dict = match plist_val {
-            Value::Dictionary(d) => d,
-            _ => {
-                bail!("Import must be a dictionary at root.")
+        backup::ensure(&domain)?;
+        cache::invalidate(&domain);
+
+        let applied = (|| -> Result<()> {
+            for (old, _) in renames {
+                foundation::delete_key(&domain, old)?;
+            }
+            for (old, new) in renames {
+                foundation::write_pref(&domain, new, &current[old])?;
+            }
+            Ok(())
+        })();
+
+        if let Err(e) = applied {
+            let mut unrestored = Vec::new();
+            for (_, new) in renames {
+                if let Err(err) = foundation::delete_key(&domain, new) {
+                    unrestored.push(format!("{new} ({err})"));
+                }
+            }
+            for (old, _) in renames {
+                if let Err(err) = foundation::write_pref(&domain, old, &current[old]) {
+                    unrestored.push(format!("{old} ({err})"));
+                }
+            }
+            return Err(e.context(if unrestored.is_empty() {
+                "Rename failed; the original keys were restored.".to_string()
+            } else {
+                format!(
+                    "Rename failed, and some keys couldn't be restored: {}",
+                    unrestored.join(", ")
+                )
+            }));
+        }
+        Ok(())
+    }
+
+    /// Insert `value` into the array at `path` in `domain`, before the element at `index`.
+    ///
+    /// An `index` equal to the array's length appends the value.
+    pub fn array_insert(
+        domain: Domain,
+        path: &KeyPath,
+        index: usize,
+        value: PrefValue,
+    ) -> Result<()> {
+        Self::update_array(domain, path, |arr| {
+            if index > arr.len() {
+                bail!(
+                    "Index {index} is out of bounds for an array of {}.",
+                    arr.len()
+                );
+            }
+            arr.insert(index, value);
+            Ok(())
+        })
+    }
+
+    /// Append `values` to the array stored at `key` in `domain`, like `defaults write -array-add`.
+    ///
+    /// A missing key is written as a new array holding `values`.
+    pub fn array_append(domain: Domain, key: &str, values: Vec<PrefValue>) -> Result<()> {
+        if foundation::read_pref_opt(&domain, key)?.is_none() {
+            return Self::write(domain, key, PrefValue::Array(values));
+        }
+        Self::update_array(domain, &KeyPath::default().key(key), |arr| {
+            arr.extend(values);
+            Ok(())
+        })
+    }
+
+    /// Merge `map` into the dictionary stored at `key` in `domain`, like `defaults write -dict-add`.
+    ///
+    /// Keys of `map` replace existing entries of the same name; a missing key is written as a new
+    /// dictionary holding `map`.
+    pub fn dict_merge(domain: Domain, key: &str, map: HashMap<String, PrefValue>) -> Result<()> {
+        let merged = match foundation::read_pref_opt(&domain, key)? {
+            Some(PrefValue::Dictionary(mut dict)) => {
+                dict.extend(map);
+                dict
+            }
+            Some(other) => bail!("{key} is of type {}, not a dictionary.", other.get_type()),
+            None => map,
+        };
+        Self::write(domain, key, PrefValue::Dictionary(merged))
+    }
+
+    /// Remove the element at `index` from the array at `path` in `domain`, returning it.
+    pub fn array_remove(domain: Domain, path: &KeyPath, index: usize) -> Result<PrefValue> {
+        Self::update_array(domain, path, |arr| {
+            if index >= arr.len() {
+                bail!(
+                    "Index {index} is out of bounds for an array of {}.",
+                    arr.len()
+                );
+            }
+            Ok(arr.remove(index))
+        })
+    }
+
+    /// Move the element at `from` in the array at `path` in `domain` so it ends up at `to`.
+    pub fn array_move(domain: Domain, path: &KeyPath, from: usize, to: usize) -> Result<()> {
+        Self::update_array(domain, path, |arr| {
+            if from.max(to) >= arr.len() {
+                bail!(
+                    "Index {} is out of bounds for an array of {}.",
+                    from.max(to),
+                    arr.len()
+                );
             }
+            let value = arr.remove(from);
+            arr.insert(to, value);
+            Ok(())
+        })
+    }
+
+    /// Change the array at `path` in `domain` with `f` and write back the top-level key holding
+    /// it. Nothing is written if `f` fails, and the key is restored if writing fails.
+    fn update_array<T>(
+        domain: Domain,
+        path: &KeyPath,
+        f: impl FnOnce(&mut Vec<PrefValue>) -> Result<T>,
+    ) -> Result<T> {
+        let (key, rest) = path
+            .split_first()
+            .context("Key path must start with a key.")?;
+        let original = foundation::read_pref(&domain, key)?;
+
+        let mut root = original.clone();
+        let out = match rest.resolve_mut(&mut root) {
+            Some(PrefValue::Array(arr)) => f(arr)?,
+            Some(other) => bail!("{path} is of type {}, not an array.", other.get_type()),
+            None => bail!("Key path not found for domain {domain}: {path}"),
         };
 
-        let cf_name = &domain.get_cf_name();
-        for (k, v) in dict {
-            let pv = plist_to_prefvalue(&v)?;
-            foundation::write_pref(cf_name, &k, &pv)?;
+        backup::ensure(&domain)?;
+        cache::invalidate(&domain);
+        if let Err(e) = foundation::write_pref(&domain, key, &root) {
+            return Err(match foundation::write_pref(&domain, key, &original) {
+                Ok(()) => e.context(format!("Updating {path} failed; {key} was restored.")),
+                Err(err) => e.context(format!(
+                    "Updating {path} failed, and {key} couldn't be restored: {err}"
+                )),
+            });
+        }
+        Ok(out)
+    }
+
+    /// Import a plist or typed JSON file into the specified domain.
+    ///
+    /// Every key of the file is written to the domain, except keys which already hold the same
+    /// value. Keys which fail to write are recorded in the returned report rather than aborting the
+    /// import; use [`ApplyReport::into_result`] to treat them as an error.
+    pub fn import(domain: Domain, import_path: &str) -> Result<ApplyReport> {
+        Self::import_with(domain, import_path, &[])
+    }
+
+    /// Import a plist file into the specified domain, normalizing its values with `rules` first.
+    ///
+    /// Works like [`Self::import`]; every value a rule changed is listed in
+    /// [`ApplyReport::normalized`], and compared against the domain in its normalized form.
+    pub fn import_with(
+        domain: Domain,
+        import_path: &str,
+        rules: &[NormalizeRule],
+    ) -> Result<ApplyReport> {
+        Self::import_values(domain, Self::load_import(import_path)?, rules)
+    }
+
+    /// Import a plist file captured on another Mac, passing every key through `translator` first.
+    ///
+    /// Keys the translator fails on are reported as
+    /// [`ApplyStatus::Untranslated`](crate::ApplyStatus::Untranslated) and left alone;
+    /// see [`translate`] for what needs translating.
+    pub fn import_translated(
+        domain: Domain,
+        import_path: &str,
+        translator: &dyn Translator,
+    ) -> Result<ApplyReport> {
+        let mut values = Vec::new();
+        let mut untranslated = Vec::new();
+        for (k, v) in Self::load_import(import_path)? {
+            match translator.translate(&domain, &k, v.clone()) {
+                Ok(translated) => values.push(translated),
+                Err(e) => untranslated.push(report::untranslated(
+                    &domain,
+                    &k,
+                    foundation::read_pref(&domain, &k).ok(),
+                    v,
+                    format!("{e:#}"),
+                )),
+            }
+        }
+
+        let mut report = Self::import_values(domain, values, &[])?;
+        report.items.extend(untranslated);
+        Ok(report)
+    }
+
+    /// Write imported `values` to `domain`, normalized with `rules`, unless they already match.
+    fn import_values(
+        domain: Domain,
+        values: Vec<(String, PrefValue)>,
+        rules: &[NormalizeRule],
+    ) -> Result<ApplyReport> {
+        let started = Instant::now();
+        backup::ensure(&domain)?;
+        cache::invalidate(&domain);
+
+        let mut report = ApplyReport::default();
+        for (k, v) in values {
+            let v = normalize::normalize(
+                &domain,
+                &KeyPath::default().key(&k),
+                v,
+                rules,
+                &mut report.normalized,
+            );
+            let old = foundation::read_pref(&domain, &k).ok();
+            report
+                .items
+                .push(report::apply(&domain, &k, old, Some(v.clone()), || {
+                    foundation::write_pref(&domain, &k, &v)
+                }));
+        }
+        report.elapsed = started.elapsed();
+        Ok(report)
+    }
+
+    /// Import a plist file into the current host's (ByHost) preferences of a domain.
+    ///
+    /// CoreFoundation files these under this machine's hardware UUID, so ByHost exports taken on
+    /// another Mac can be restored without renaming anything. This is [`Self::import`] into
+    /// [`Domain::current_host`].
+    pub fn import_current_host(domain: Domain, import_path: &str) -> Result<ApplyReport> {
+        Self::import(domain.current_host(), import_path)
+    }
+
+    /// Reads a plist or typed JSON file to import, which must hold a dictionary at its root.
+    fn load_import(import_path: &str) -> Result<Vec<(String, PrefValue)>> {
+        let data = file::read_limited(Path::new(import_path))?;
+
+        if data.trim_ascii_start().starts_with(b"{") {
+            return match PrefValue::from_typed_json(&serde_json::from_slice(&data)?)? {
+                PrefValue::Dictionary(d) => Ok(d.into_iter().collect()),
+                _ => bail!("Import must be a dictionary at root."),
+            };
+        }
+        match Value::from_reader(Cursor::new(&data))? {
+            Value::Dictionary(d) => d
+                .into_iter()
+                .map(|(k, v)| Ok((k, plist_to_prefvalue(&v)?)))
+                .collect(),
+            _ => bail!("Import must be a dictionary at root."),
         }
-        Ok(())
     }
 
     /// Export a domain's plist file to the specified path.
+    ///
+    /// The file is written through a temp file in the destination directory and moved into place,
+    /// so an interrupted export never leaves a truncated plist behind.
     pub fn export(domain: Domain, export_path: &str) -> Result<()> {
-        let cf_name = &domain.get_cf_name();
-        let pref = foundation::read_pref_domain(cf_name)?;
+        let path = PathBuf::from(export_path);
+
+        let mut buf = Vec::new();
+        Self::export_to(domain, &mut buf, PlistFormat::Binary)?;
+
+        file::write_atomic(&path, &buf)
+    }
+
+    /// Export a domain as a plist of the given format to any writer, e.g. stdout.
+    pub fn export_to(domain: Domain, out: &mut impl Write, format: PlistFormat) -> Result<()> {
+        let pref = cache::read_domain(&domain)?;
 
         if !matches!(pref, PrefValue::Dictionary(_)) {
             bail!("CF export produced non-dictionary root")
         }
 
-        let plist = prefvalue_to_plist(&pref);
-        let path = PathBuf::from(export_path);
+        Self::write_to(&pref, out, format)
+    }
 
-        let file = File::create(path)?;
-        plist
-            .to_writer_binary(file)
-            .context("failed to export CF domain to plist")?;
+    /// Write `pref` as a plist of the given format, or as typed JSON.
+    pub(crate) fn write_to(
+        pref: &PrefValue,
+        out: &mut impl Write,
+        format: PlistFormat,
+    ) -> Result<()> {
+        if format == PlistFormat::Json {
+            serde_json::to_writer_pretty(&mut *out, &pref.to_typed_json())?;
+            return writeln!(out).context("failed to export CF domain to JSON");
+        }
 
-        Ok(())
+        let plist = prefvalue_to_plist(pref);
+        match format {
+            PlistFormat::Xml => plist.to_writer_xml(out),
+            _ => plist.to_writer_binary(out),
+        }
+        .context("failed to export CF domain to plist")
     }
 }
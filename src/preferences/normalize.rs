@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: MIT
+
+//! Clean-ups applied to values while they are imported.
+//!
+//! Hand-written plists tend to carry stray whitespace, repeated array entries and URLs spelled
+//! in different ways. [`Preferences::import_with`](crate::Preferences::import_with) can tidy these
+//! up on the way in, recording every value it changed in the [`ApplyReport`](crate::ApplyReport).
+
+use anyhow::{Result, bail};
+use std::collections::HashSet;
+
+use crate::{Domain, KeyPath, PrefValue};
+
+/// A single normalization rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NormalizeRule {
+    /// Strip leading and trailing whitespace from strings.
+    TrimStrings,
+    /// Drop repeated entries from arrays, keeping the first occurrence.
+    DedupArrays,
+    /// Lowercase the scheme and host of URLs and drop default ports.
+    NormalizeUrls,
+}
+
+impl NormalizeRule {
+    /// Every rule, in the order they are applied.
+    pub const ALL: [NormalizeRule; 3] = [
+        NormalizeRule::TrimStrings,
+        NormalizeRule::NormalizeUrls,
+        NormalizeRule::DedupArrays,
+    ];
+
+    /// Parse a rule from its name as shown by `Display`.
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "trim-strings" => Ok(NormalizeRule::TrimStrings),
+            "dedup-arrays" => Ok(NormalizeRule::DedupArrays),
+            "normalize-urls" => Ok(NormalizeRule::NormalizeUrls),
+            other => bail!(
+                "Unknown normalization rule: {other} (use trim-strings, dedup-arrays or normalize-urls)"
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for NormalizeRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            NormalizeRule::TrimStrings => "trim-strings",
+            NormalizeRule::DedupArrays => "dedup-arrays",
+            NormalizeRule::NormalizeUrls => "normalize-urls",
+        })
+    }
+}
+
+/// A value changed by a normalization rule.
+#[derive(Debug, Clone)]
+pub struct Normalization {
+    pub domain: Domain,
+    /// The key path of the changed value.
+    pub key: String,
+    pub rule: NormalizeRule,
+    pub before: PrefValue,
+    pub after: PrefValue,
+}
+
+/// Apply `rules` to `value`, stored at `path` in `domain`, recording each change in `out`.
+pub(crate) fn normalize(
+    domain: &Domain,
+    path: &KeyPath,
+    value: PrefValue,
+    rules: &[NormalizeRule],
+    out: &mut Vec<Normalization>,
+) -> PrefValue {
+    let value = match value {
+        PrefValue::Array(arr) => PrefValue::Array(
+            arr.into_iter()
+                .enumerate()
+                .map(|(i, v)| normalize(domain, &path.index(i), v, rules, out))
+                .collect(),
+        ),
+        PrefValue::Dictionary(dict) => PrefValue::Dictionary(
+            dict.into_iter()
+                .map(|(k, v)| {
+                    let v = normalize(domain, &path.key(&k), v, rules, out);
+                    (k, v)
+                })
+                .collect(),
+        ),
+        other => other,
+    };
+
+    NormalizeRule::ALL
+        .into_iter()
+        .filter(|rule| rules.contains(rule))
+        .fold(value, |value, rule| {
+            let Some(after) = apply(rule, &value) else {
+                return value;
+            };
+            out.push(Normalization {
+                domain: domain.clone(),
+                key: path.to_string(),
+                rule,
+                before: value,
+                after: after.clone(),
+            });
+            after
+        })
+}
+
+/// The result of applying `rule` to `value`, if it changes anything.
+fn apply(rule: NormalizeRule, value: &PrefValue) -> Option<PrefValue> {
+    match (rule, value) {
+        (NormalizeRule::TrimStrings, PrefValue::String(s)) if s.trim() != s => {
+            Some(PrefValue::String(s.trim().to_string()))
+        }
+        (NormalizeRule::DedupArrays, PrefValue::Array(arr)) => {
+            let mut seen = HashSet::new();
+            let deduped: Vec<PrefValue> = arr.iter().filter(|v| seen.insert(*v)).cloned().collect();
+            (deduped.len() != arr.len()).then_some(PrefValue::Array(deduped))
+        }
+        (NormalizeRule::NormalizeUrls, PrefValue::Url(url)) => {
+            normalize_url(url).map(PrefValue::Url)
+        }
+        (NormalizeRule::NormalizeUrls, PrefValue::String(s)) => {
+            normalize_url(s).map(PrefValue::String)
+        }
+        _ => None,
+    }
+}
+
+/// `url` with its scheme and host lowercased and a default port removed, if it looks like a URL
+/// and that changes it.
+fn normalize_url(url: &str) -> Option<String> {
+    let (scheme, rest) = url.trim().split_once("://")?;
+    if scheme.is_empty()
+        || !scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    {
+        return None;
+    }
+    let scheme = scheme.to_ascii_lowercase();
+
+    let split = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, tail) = rest.split_at(split);
+    let (userinfo, host) = match authority.rsplit_once('@') {
+        Some((userinfo, host)) => (Some(userinfo), host),
+        None => (None, authority),
+    };
+    let mut host = host.to_ascii_lowercase();
+    let default_port = match scheme.as_str() {
+        "http" => Some(":80"),
+        "https" => Some(":443"),
+        _ => None,
+    };
+    if let Some(port) = default_port
+        && host.ends_with(port)
+    {
+        host.truncate(host.len() - port.len());
+    }
+
+    let normalized = match userinfo {
+        Some(userinfo) => format!("{scheme}://{userinfo}@{host}{tail}"),
+        None => format!("{scheme}://{host}{tail}"),
+    };
+    (normalized != url).then_some(normalized)
+}
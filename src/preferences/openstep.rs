@@ -0,0 +1,306 @@
+// SPDX-License-Identifier: MIT
+
+//! Old-style (OpenStep) property list literals, as taken by `defaults write`.
+//!
+//! Dictionaries are written `{ key = value; ... }`, arrays `( value, ... )` and data `<0a1b2c>`.
+//! Strings are quoted with `"` or `'`, or left unquoted if they only contain letters, digits and
+//! `_$+/:.-`. Comments (`//` and `/* */`) are skipped.
+//!
+//! The format has no numbers: `defaults` writes every scalar as a string. As with untyped values
+//! given to `drs write`, unquoted numbers are written as integers or floats here instead; quote
+//! them to keep them strings.
+
+use anyhow::{Result, bail};
+use std::{collections::HashMap, iter::Peekable, str::Chars};
+
+use crate::PrefValue;
+
+/// How deeply dictionaries and arrays may nest, so hostile input can't overflow the stack.
+const MAX_DEPTH: usize = 512;
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    /// The number of dictionaries and arrays currently open.
+    depth: usize,
+}
+
+/// Whether `c` may appear in an unquoted string.
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_$+/:.-".contains(c)
+}
+
+impl Parser<'_> {
+    /// Skip whitespace and comments.
+    fn skip(&mut self) -> Result<()> {
+        loop {
+            match self.chars.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.chars.next();
+                }
+                Some('/') => {
+                    let mut ahead = self.chars.clone();
+                    ahead.next();
+                    match ahead.next() {
+                        Some('/') => {
+                            self.chars.find(|&c| c == '\n');
+                        }
+                        Some('*') => {
+                            self.chars.nth(1);
+                            let mut last = ' ';
+                            loop {
+                                match self.chars.next() {
+                                    Some('/') if last == '*' => break,
+                                    Some(c) => last = c,
+                                    None => bail!("Unclosed /* comment."),
+                                }
+                            }
+                        }
+                        _ => return Ok(()),
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Consume `expected`, after whitespace.
+    fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip()?;
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => bail!("Expected {expected:?} but found {c:?}."),
+            None => bail!("Expected {expected:?} but the value ended."),
+        }
+    }
+
+    fn value(&mut self) -> Result<PrefValue> {
+        self.skip()?;
+        match self.chars.peek().copied() {
+            Some('{') => self.nested(Self::dictionary),
+            Some('(') => self.nested(Self::array),
+            Some('<') => self.data(),
+            Some(quote @ ('"' | '\'')) => {
+                self.chars.next();
+                Ok(PrefValue::String(self.quoted(quote)?))
+            }
+            Some(c) if is_word_char(c) => Ok(word_value(self.word())),
+            Some(c) => bail!("Unexpected {c:?}; quote strings containing it."),
+            None => bail!("Expected a value but the value ended."),
+        }
+    }
+
+    /// Parse a dictionary or array with `parse`, one level deeper.
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<PrefValue>) -> Result<PrefValue> {
+        if self.depth == MAX_DEPTH {
+            bail!("Dictionaries and arrays are nested more than {MAX_DEPTH} levels deep.");
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn dictionary(&mut self) -> Result<PrefValue> {
+        self.chars.next();
+        let mut dict = HashMap::new();
+        loop {
+            self.skip()?;
+            if self.chars.next_if_eq(&'}').is_some() {
+                return Ok(PrefValue::Dictionary(dict));
+            }
+            let key = match self.chars.peek().copied() {
+                Some(quote @ ('"' | '\'')) => {
+                    self.chars.next();
+                    self.quoted(quote)?
+                }
+                Some(c) if is_word_char(c) => self.word(),
+                Some(c) => bail!("Expected a key but found {c:?}."),
+                None => bail!("Unclosed {{."),
+            };
+            self.expect('=')?;
+            let value = self.value()?;
+            self.skip()?;
+            // The last entry's ; is optional.
+            if self.chars.next_if_eq(&';').is_none() && self.chars.peek() != Some(&'}') {
+                match self.chars.peek() {
+                    Some(_) => bail!("Expected ; after the value of {key}."),
+                    None => bail!("Unclosed {{."),
+                }
+            }
+            dict.insert(key, value);
+        }
+    }
+
+    fn array(&mut self) -> Result<PrefValue> {
+        self.chars.next();
+        let mut items = Vec::new();
+        loop {
+            self.skip()?;
+            if self.chars.next_if_eq(&')').is_some() {
+                return Ok(PrefValue::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip()?;
+            if self.chars.next_if_eq(&',').is_none() && self.chars.peek() != Some(&')') {
+                match self.chars.peek() {
+                    Some(c) => bail!("Expected , or ) in an array but found {c:?}."),
+                    None => bail!("Unclosed (."),
+                }
+            }
+        }
+    }
+
+    fn data(&mut self) -> Result<PrefValue> {
+        self.chars.next();
+        let mut digits = String::new();
+        loop {
+            match self.chars.next() {
+                Some('>') => break,
+                Some(c) if c.is_ascii_hexdigit() => digits.push(c),
+                Some(c) if c.is_whitespace() => {}
+                Some(c) => bail!("Unexpected {c:?} in <data>."),
+                None => bail!("Unclosed <."),
+            }
+        }
+        if !digits.len().is_multiple_of(2) {
+            bail!("<{digits}> has an odd number of hex digits.");
+        }
+        let bytes = (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()?;
+        Ok(PrefValue::Data(bytes.into()))
+    }
+
+    /// The rest of a string opened with `quote`, with escapes resolved.
+    fn quoted(&mut self, quote: char) -> Result<String> {
+        let mut out = String::new();
+        loop {
+            match self.chars.next() {
+                Some(c) if c == quote => return Ok(out),
+                Some('\\') => match self.chars.next() {
+                    Some('n') => out.push('\n'),
+                    Some('t') => out.push('\t'),
+                    Some('r') => out.push('\r'),
+                    Some('U' | 'u') => {
+                        let hex: String = self.chars.by_ref().take(4).collect();
+                        let unit = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| anyhow::anyhow!("Invalid escape \\U{hex}."))?;
+                        out.push(unit);
+                    }
+                    Some(c) => out.push(c),
+                    None => bail!("Unclosed string."),
+                },
+                Some(c) => out.push(c),
+                None => bail!("Unclosed string."),
+            }
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(c) = self.chars.next_if(|&c| is_word_char(c)) {
+            word.push(c);
+        }
+        word
+    }
+}
+
+/// An unquoted string, as a number if it is one.
+fn word_value(word: String) -> PrefValue {
+    if let Ok(i) = word.parse::<i64>() {
+        return PrefValue::Integer(i);
+    }
+    if word.contains(|c: char| c.is_ascii_digit())
+        && let Ok(f) = word.parse::<f64>()
+        && f.is_finite()
+    {
+        return PrefValue::Float(f);
+    }
+    PrefValue::String(word)
+}
+
+impl PrefValue {
+    /// Parse an old-style (OpenStep) property list literal, as published in many `defaults write`
+    /// recipes.
+    ///
+    /// ```
+    /// use defaults_rs::PrefValue;
+    ///
+    /// let value = PrefValue::from_openstep(r#"{ "a" = 1; b = (x, "y z"); }"#).unwrap();
+    /// let PrefValue::Dictionary(dict) = value else { panic!() };
+    /// assert_eq!(dict["a"], PrefValue::Integer(1));
+    /// assert_eq!(
+    ///     dict["b"],
+    ///     PrefValue::Array(vec![PrefValue::String("x".into()), PrefValue::String("y z".into())])
+    /// );
+    /// ```
+    pub fn from_openstep(text: &str) -> Result<PrefValue> {
+        let mut parser = Parser {
+            chars: text.chars().peekable(),
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip()?;
+        if let Some(c) = parser.chars.next() {
+            bail!("Unexpected {c:?} after the value.");
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nested_values() -> Result<()> {
+        let value = PrefValue::from_openstep(
+            "{ Tabs = (Inbox, 'Sent Items', <0a1B>); n = 1; f = -1.5; s = \"1\"; /* c */ }",
+        )?;
+        let PrefValue::Dictionary(dict) = value else {
+            bail!("not a dictionary")
+        };
+        assert_eq!(
+            dict["Tabs"],
+            PrefValue::Array(vec![
+                PrefValue::String("Inbox".into()),
+                PrefValue::String("Sent Items".into()),
+                PrefValue::Data(vec![0x0a, 0x1b].into()),
+            ])
+        );
+        assert_eq!(dict["n"], PrefValue::Integer(1));
+        assert_eq!(dict["f"], PrefValue::Float(-1.5));
+        assert_eq!(dict["s"], PrefValue::String("1".into()));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed_values() {
+        for text in [
+            "{ a = 1",
+            "(1, 2",
+            "<0a1>",
+            "{ a = 1 b = 2 }",
+            "(1) x",
+            "\"open",
+            "/* open",
+        ] {
+            assert!(
+                PrefValue::from_openstep(text).is_err(),
+                "{text} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let nested = |depth: usize| "(".repeat(depth) + &")".repeat(depth);
+        assert!(PrefValue::from_openstep(&nested(MAX_DEPTH)).is_ok());
+        assert!(PrefValue::from_openstep(&nested(MAX_DEPTH + 1)).is_err());
+        // Deep enough to overflow the stack without the limit.
+        assert!(PrefValue::from_openstep(&"{a=".repeat(500_000)).is_err());
+    }
+}
@@ -0,0 +1,347 @@
+// SPDX-License-Identifier: MIT
+
+//! Per-key results of operations which apply many values at once.
+//!
+//! [`Preferences::import`](crate::Preferences::import) and
+//! [`Transaction::commit`](crate::Transaction::commit) return an [`ApplyReport`] listing every key
+//! they touched as changed, unchanged, failed or skipped, with the old and new values. The same report
+//! renders as a table for people ([`ApplyReport::table`]) and as JSON for CI logs
+//! ([`ApplyReport::to_json`]).
+
+use anyhow::{Result, bail};
+use std::time::{Duration, Instant};
+
+use crate::{Domain, Normalization, OnError, PrefValue, core::foundation};
+
+/// Outcome of applying a single key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplyStatus {
+    /// The stored value was replaced or removed.
+    Changed,
+    /// The key already held the requested value, so nothing was written.
+    Unchanged,
+    /// Applying the key failed; see [`ApplyItem::error`].
+    Error,
+    /// The key wasn't attempted because an earlier key failed.
+    Skipped,
+    /// The key holds values specific to another Mac which couldn't be translated, so it wasn't
+    /// written; see [`ApplyItem::error`].
+    Untranslated,
+}
+
+impl std::fmt::Display for ApplyStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ApplyStatus::Changed => "CHANGED",
+            ApplyStatus::Unchanged => "UNCHANGED",
+            ApplyStatus::Error => "ERROR",
+            ApplyStatus::Skipped => "SKIPPED",
+            ApplyStatus::Untranslated => "UNTRANSLATED",
+        })
+    }
+}
+
+/// How an operation which applies many values ended as a whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplyOutcome {
+    /// Every key was applied.
+    Clean,
+    /// Some keys failed, were skipped or couldn't be translated, and the others were left applied.
+    Partial,
+    /// A key failed and every domain changed so far was restored.
+    RolledBack,
+}
+
+impl std::fmt::Display for ApplyOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ApplyOutcome::Clean => "clean",
+            ApplyOutcome::Partial => "partial",
+            ApplyOutcome::RolledBack => "rolled back",
+        })
+    }
+}
+
+/// The result for one key.
+#[derive(Debug, Clone)]
+pub struct ApplyItem {
+    pub domain: Domain,
+    pub key: String,
+    pub status: ApplyStatus,
+    /// The value before applying, if the key existed.
+    pub old: Option<PrefValue>,
+    /// The requested value; `None` for deletions.
+    pub new: Option<PrefValue>,
+    /// What went wrong, for [`ApplyStatus::Error`].
+    pub error: Option<String>,
+    /// Time spent applying this key.
+    pub elapsed: Duration,
+}
+
+/// Per-key results of an apply, import or batch operation.
+#[derive(Debug, Clone, Default)]
+pub struct ApplyReport {
+    pub items: Vec<ApplyItem>,
+    /// Values changed by normalization rules before they were applied.
+    pub normalized: Vec<Normalization>,
+    /// Total time taken by the operation.
+    pub elapsed: Duration,
+    /// The failure policy the operation was committed with, if it was a transaction.
+    pub policy: Option<OnError>,
+    /// Domains restored to their previous values after a failure.
+    pub restored: Vec<Domain>,
+    /// Domains which couldn't be restored after a failure, with the first error for each.
+    pub restore_failed: Vec<(Domain, String)>,
+}
+
+impl ApplyReport {
+    /// Append all items of another report, adding up the elapsed time.
+    pub fn merge(&mut self, other: ApplyReport) {
+        self.items.extend(other.items);
+        self.normalized.extend(other.normalized);
+        self.elapsed += other.elapsed;
+        self.policy = self.policy.or(other.policy);
+        self.restored.extend(other.restored);
+        self.restore_failed.extend(other.restore_failed);
+    }
+
+    /// Number of items with the given status.
+    pub fn count(&self, status: ApplyStatus) -> usize {
+        self.items.iter().filter(|i| i.status == status).count()
+    }
+
+    /// Whether every item was applied without error.
+    pub fn is_ok(&self) -> bool {
+        self.count(ApplyStatus::Error) == 0
+    }
+
+    /// How the operation ended as a whole.
+    pub fn outcome(&self) -> ApplyOutcome {
+        if !self.restore_failed.is_empty() {
+            ApplyOutcome::Partial
+        } else if !self.restored.is_empty() {
+            ApplyOutcome::RolledBack
+        } else if self.is_ok()
+            && self.count(ApplyStatus::Skipped) == 0
+            && self.count(ApplyStatus::Untranslated) == 0
+        {
+            ApplyOutcome::Clean
+        } else {
+            ApplyOutcome::Partial
+        }
+    }
+
+    /// Turn the report into an error if any item failed.
+    pub fn into_result(self) -> Result<Self> {
+        if let Some(item) = self.items.iter().find(|i| i.status == ApplyStatus::Error) {
+            bail!(
+                "Failed to apply {} key(s), first {} in {}: {}",
+                self.count(ApplyStatus::Error),
+                item.key,
+                item.domain,
+                item.error.as_deref().unwrap_or("unknown error")
+            );
+        }
+        Ok(self)
+    }
+
+    /// A one-line summary such as `3 changed, 5 unchanged, 0 errors in 12ms`.
+    ///
+    /// Normalized and skipped values are counted as well when there are any, and the outcome is
+    /// added for transactions which didn't apply cleanly.
+    pub fn summary(&self) -> String {
+        let normalized = match self.normalized.len() {
+            0 => String::new(),
+            n => format!(", {n} normalized"),
+        };
+        let skipped = match self.count(ApplyStatus::Skipped) {
+            0 => String::new(),
+            n => format!(", {n} skipped"),
+        };
+        let untranslated = match self.count(ApplyStatus::Untranslated) {
+            0 => String::new(),
+            n => format!(", {n} untranslated"),
+        };
+        let outcome = match (self.policy, self.outcome()) {
+            (Some(policy), outcome) if outcome != ApplyOutcome::Clean => {
+                format!(" ({outcome}, on error {policy})")
+            }
+            _ => String::new(),
+        };
+        let unrestored: String = self
+            .restore_failed
+            .iter()
+            .map(|(domain, error)| format!("; couldn't restore {domain}: {error}"))
+            .collect();
+        format!(
+            "{} changed, {} unchanged, {} errors{skipped}{untranslated}{normalized} in {}ms{outcome}{unrestored}",
+            self.count(ApplyStatus::Changed),
+            self.count(ApplyStatus::Unchanged),
+            self.count(ApplyStatus::Error),
+            self.elapsed.as_millis()
+        )
+    }
+
+    /// Render the report as a plain-text table followed by the summary.
+    pub fn table(&self) -> String {
+        let show = |v: &Option<PrefValue>| v.as_ref().map_or("-".to_string(), |v| v.to_string());
+        let rows: Vec<[String; 5]> = self
+            .items
+            .iter()
+            .map(|i| {
+                [
+                    i.status.to_string(),
+                    i.domain.to_string(),
+                    i.key.clone(),
+                    show(&i.old),
+                    match i.status {
+                        ApplyStatus::Error | ApplyStatus::Untranslated => {
+                            i.error.clone().unwrap_or_default()
+                        }
+                        _ => show(&i.new),
+                    },
+                ]
+            })
+            .collect();
+
+        let header = ["STATUS", "DOMAIN", "KEY", "OLD", "NEW"].map(String::from);
+        let mut widths = header.clone().map(|h| h.len());
+        for row in &rows {
+            for (w, cell) in widths.iter_mut().zip(row) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+
+        let mut out = String::new();
+        for row in std::iter::once(&header).chain(&rows) {
+            let line: Vec<String> = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, w)| format!("{cell:<w$}"))
+                .collect();
+            out.push_str(line.join("  ").trim_end());
+            out.push('\n');
+        }
+        for n in &self.normalized {
+            out.push_str(&format!(
+                "NORMALIZED  {} {} ({}): {} -> {}\n",
+                n.domain, n.key, n.rule, n.before, n.after
+            ));
+        }
+        out.push_str(&self.summary());
+        out
+    }
+
+    /// Serialize the report for machine consumption.
+    pub fn to_json(&self) -> serde_json::Value {
+        let items: Vec<serde_json::Value> = self
+            .items
+            .iter()
+            .map(|i| {
+                serde_json::json!({
+                    "domain": i.domain.to_string(),
+                    "key": i.key,
+                    "status": i.status.to_string(),
+                    "old": i.old.as_ref().map(PrefValue::to_json),
+                    "new": i.new.as_ref().map(PrefValue::to_json),
+                    "error": i.error,
+                    "elapsed_ms": i.elapsed.as_secs_f64() * 1000.0,
+                })
+            })
+            .collect();
+
+        let normalized: Vec<serde_json::Value> = self
+            .normalized
+            .iter()
+            .map(|n| {
+                serde_json::json!({
+                    "domain": n.domain.to_string(),
+                    "key": n.key,
+                    "rule": n.rule.to_string(),
+                    "before": n.before.to_json(),
+                    "after": n.after.to_json(),
+                })
+            })
+            .collect();
+
+        serde_json::json!({
+            "items": items,
+            "normalized": normalized,
+            "changed": self.count(ApplyStatus::Changed),
+            "unchanged": self.count(ApplyStatus::Unchanged),
+            "errors": self.count(ApplyStatus::Error),
+            "skipped": self.count(ApplyStatus::Skipped),
+            "untranslated": self.count(ApplyStatus::Untranslated),
+            "policy": self.policy.map(|p| p.to_string()),
+            "outcome": self.outcome().to_string(),
+            "restored": self.restored.iter().map(Domain::to_string).collect::<Vec<_>>(),
+            "restore_failed": self
+                .restore_failed
+                .iter()
+                .map(|(domain, error)| serde_json::json!({ "domain": domain.to_string(), "error": error }))
+                .collect::<Vec<_>>(),
+            "elapsed_ms": self.elapsed.as_secs_f64() * 1000.0,
+        })
+    }
+}
+
+/// Apply one key through `write` unless it already holds `new`, timing and recording the outcome.
+pub(crate) fn apply(
+    domain: &Domain,
+    key: &str,
+    old: Option<PrefValue>,
+    new: Option<PrefValue>,
+    write: impl FnOnce() -> Result<()>,
+) -> ApplyItem {
+    let started = Instant::now();
+    let (status, error) = if old == new {
+        (ApplyStatus::Unchanged, None)
+    } else {
+        match write() {
+            Ok(()) => (ApplyStatus::Changed, None),
+            Err(e) => (ApplyStatus::Error, Some(format!("{e:#}"))),
+        }
+    };
+
+    ApplyItem {
+        domain: domain.clone(),
+        key: key.to_string(),
+        status,
+        old,
+        new,
+        error,
+        elapsed: started.elapsed(),
+    }
+}
+
+/// Record `key` as not written because its value couldn't be translated for this Mac.
+pub(crate) fn untranslated(
+    domain: &Domain,
+    key: &str,
+    old: Option<PrefValue>,
+    new: PrefValue,
+    error: String,
+) -> ApplyItem {
+    ApplyItem {
+        domain: domain.clone(),
+        key: key.to_string(),
+        status: ApplyStatus::Untranslated,
+        old,
+        new: Some(new),
+        error: Some(error),
+        elapsed: Duration::ZERO,
+    }
+}
+
+/// Record `key` as not attempted, along with its current value.
+pub(crate) fn skipped(domain: &Domain, key: &str, new: Option<PrefValue>) -> ApplyItem {
+    ApplyItem {
+        domain: domain.clone(),
+        key: key.to_string(),
+        status: ApplyStatus::Skipped,
+        old: foundation::read_pref(domain, key).ok(),
+        new,
+        error: None,
+        elapsed: Duration::ZERO,
+    }
+}
@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: MIT
+
+//! Golden round-trip checks for plist files.
+//!
+//! Each plist is imported into a sandbox domain, read back and exported again. The export is then
+//! compared against the original, first byte-for-byte and otherwise by value, which catches types
+//! or precision getting lost on the way through CoreFoundation.
+//!
+//! CoreFoundation has no UID type, so plists holding UIDs (as keyed archives do) are round-tripped
+//! through a plist file in the temporary directory instead, as `--file` would write them.
+
+use anyhow::{Context, Result, bail};
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    Domain, PrefValue, Preferences, TestSandbox, preferences::convert::plist_to_prefvalue,
+};
+
+/// Outcome of a round-trip check.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RoundTrip {
+    /// The export is byte-identical to the original file.
+    Identical,
+    /// The export differs in encoding, but holds the same values.
+    Equivalent,
+    /// Values changed on the way; describes the first difference.
+    Mismatch(String),
+}
+
+impl RoundTrip {
+    /// Whether the round-trip preserved every value.
+    pub fn is_ok(&self) -> bool {
+        !matches!(self, RoundTrip::Mismatch(_))
+    }
+}
+
+fn load(data: &[u8]) -> Result<PrefValue> {
+    let val = plist::Value::from_reader(Cursor::new(data))?;
+    if val.as_dictionary().is_none() {
+        bail!("Plist must be a dictionary at root.")
+    }
+    plist_to_prefvalue(&val)
+}
+
+/// Describes the first difference between two values, if any.
+fn diff(path: &str, expected: &PrefValue, actual: &PrefValue) -> Option<String> {
+    match (expected, actual) {
+        (PrefValue::Dictionary(e), PrefValue::Dictionary(a)) => {
+            let mut keys: Vec<&String> = e.keys().chain(a.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            keys.into_iter().find_map(|k| {
+                let sub = format!("{path}/{k}");
+                match (e.get(k), a.get(k)) {
+                    (Some(ev), Some(av)) => diff(&sub, ev, av),
+                    (Some(_), None) => Some(format!("{sub}: missing after round-trip")),
+                    (None, _) => Some(format!("{sub}: appeared after round-trip")),
+                }
+            })
+        }
+        (PrefValue::Array(e), PrefValue::Array(a)) if e.len() == a.len() => e
+            .iter()
+            .zip(a)
+            .enumerate()
+            .find_map(|(i, (ev, av))| diff(&format!("{path}[{i}]"), ev, av)),
+        _ if expected == actual => None,
+        _ => Some(format!(
+            "{path}: expected {} `{expected}`, got {} `{actual}`",
+            expected.get_type(),
+            actual.get_type()
+        )),
+    }
+}
+
+/// Whether `value` holds a UID anywhere.
+fn has_uid(value: &PrefValue) -> bool {
+    match value {
+        PrefValue::Uid(_) => true,
+        PrefValue::Array(items) => items.iter().any(has_uid),
+        PrefValue::Dictionary(dict) => dict.values().any(has_uid),
+        _ => false,
+    }
+}
+
+impl TestSandbox {
+    /// Run a plist file through import, read and export, and compare the result to the file.
+    pub fn round_trip(&self, path: &Path) -> Result<RoundTrip> {
+        let original =
+            fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let expected = load(&original)?;
+
+        let name = path
+            .file_stem()
+            .map(|s| {
+                s.to_string_lossy()
+                    .replace(|c: char| !c.is_alphanumeric(), "-")
+            })
+            .unwrap_or_default();
+        let file = std::env::temp_dir().join(format!("{}.{name}.in.plist", self.namespace()));
+        if has_uid(&expected) {
+            let outcome = self.check(
+                Domain::Path(file.clone()),
+                path,
+                &name,
+                &original,
+                &expected,
+            );
+            let _ = fs::remove_file(&file);
+            return outcome;
+        }
+        self.check(self.domain(&name), path, &name, &original, &expected)
+    }
+
+    /// Import `path` into `domain`, then read and export it and compare against the original.
+    fn check(
+        &self,
+        domain: Domain,
+        path: &Path,
+        name: &str,
+        original: &[u8],
+        expected: &PrefValue,
+    ) -> Result<RoundTrip> {
+        Preferences::import(domain.clone(), &path.to_string_lossy())?.into_result()?;
+        if let Some(d) = diff("", expected, &Preferences::read_domain(domain.clone())?) {
+            return Ok(RoundTrip::Mismatch(format!("after import: {d}")));
+        }
+
+        let export = std::env::temp_dir().join(format!("{}.{name}.plist", self.namespace()));
+        let exported = Preferences::export(domain, &export.to_string_lossy())
+            .and_then(|_| Ok(fs::read(&export)?));
+        let _ = fs::remove_file(&export);
+        let exported = exported?;
+
+        if exported == original {
+            return Ok(RoundTrip::Identical);
+        }
+        Ok(match diff("", expected, &load(&exported)?) {
+            Some(d) => RoundTrip::Mismatch(format!("after export: {d}")),
+            None => RoundTrip::Equivalent,
+        })
+    }
+
+    /// Round-trip every `.plist` file in a directory, in file name order.
+    pub fn round_trip_dir(&self, dir: &Path) -> Result<Vec<(PathBuf, RoundTrip)>> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .with_context(|| format!("failed to read {}", dir.display()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "plist"))
+            .collect();
+        paths.sort();
+
+        paths
+            .into_iter()
+            .map(|path| {
+                let outcome = self
+                    .round_trip(&path)
+                    .with_context(|| format!("round-trip failed for {}", path.display()))?;
+                Ok((path, outcome))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,84 @@
+// SPDX-License-Identifier: MIT
+
+//! Throwaway preference domains for tests.
+//!
+//! A [`TestSandbox`] hands out domains under a namespace unique to the sandbox
+//! (`dev.drs.sandbox.<pid>.<n>.<name>`). Dropping the sandbox deletes every domain in that
+//! namespace along with its plist, so tests never touch or leave behind real settings.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{Domain, Preferences};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Prefix shared by all sandbox domains.
+const PREFIX: &str = "dev.drs.sandbox";
+
+/// An isolated namespace of preference domains which is cleaned up on drop.
+///
+/// ```no_run
+/// use defaults_rs::{PrefValue, Preferences, TestSandbox};
+///
+/// let sandbox = TestSandbox::new();
+/// let domain = sandbox.domain("app");
+/// Preferences::write(domain.clone(), "count", PrefValue::Integer(5)).unwrap();
+/// assert_eq!(Preferences::read(domain, "count").unwrap(), PrefValue::Integer(5));
+/// ```
+#[derive(Debug)]
+pub struct TestSandbox {
+    namespace: String,
+}
+
+impl TestSandbox {
+    /// Create a sandbox with a fresh namespace.
+    pub fn new() -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        Self {
+            namespace: format!("{PREFIX}.{}.{id}", std::process::id()),
+        }
+    }
+
+    /// The namespace all of this sandbox's domains live under.
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// A domain inside the sandbox.
+    pub fn domain(&self, name: &str) -> Domain {
+        Domain::User(format!("{}.{name}", self.namespace))
+    }
+
+    /// Delete every domain inside the sandbox.
+    pub fn clear(&self) {
+        let Ok(domains) = Preferences::list_domains() else {
+            return;
+        };
+        let prefix = format!("{}.", self.namespace);
+
+        for domain in domains {
+            let name = domain.get_cf_name();
+            if !name.starts_with(&prefix) {
+                continue;
+            }
+
+            let path = domain.plist_path();
+            let _ = Preferences::delete_domain(domain);
+            if let Some(path) = path {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+impl Default for TestSandbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TestSandbox {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: MIT
+
+//! Typed access to the screen saver settings.
+//!
+//! `com.apple.screensaver` is a current-host (ByHost) domain: its settings are stored per Mac, in
+//! `~/Library/Preferences/ByHost/com.apple.screensaver.<hardware UUID>.plist`, so writing them to
+//! the plain user domain has no effect. [`ScreenSaver`] always goes through the ByHost domain.
+//!
+//! ```no_run
+//! use defaults_rs::ScreenSaver;
+//! use std::time::Duration;
+//!
+//! // start the screen saver after 10 minutes of inactivity
+//! ScreenSaver::set_idle_time(Some(Duration::from_secs(600))).unwrap();
+//! ```
+
+use anyhow::{Result, bail};
+use std::time::Duration;
+
+use crate::{Domain, PrefValue, Preferences};
+
+const DOMAIN: &str = "com.apple.screensaver";
+
+/// The current user's screen saver settings on this Mac.
+pub struct ScreenSaver;
+
+impl ScreenSaver {
+    /// The idle time macOS uses until one is set: 20 minutes.
+    pub const DEFAULT_IDLE_TIME: Duration = Duration::from_secs(1200);
+
+    /// The ByHost domain the settings are stored in.
+    pub fn domain() -> Domain {
+        Domain::ByHost(DOMAIN.to_string())
+    }
+
+    fn get(key: &str) -> Result<Option<PrefValue>> {
+        if !Preferences::is_set(Self::domain(), key) {
+            return Ok(None);
+        }
+        Preferences::read(Self::domain(), key).map(Some)
+    }
+
+    /// How long the Mac has to be idle before the screen saver starts, or `None` if it never
+    /// starts (`idleTime` 0).
+    pub fn idle_time() -> Result<Option<Duration>> {
+        match Self::get("idleTime")? {
+            None => Ok(Some(Self::DEFAULT_IDLE_TIME)),
+            Some(PrefValue::Integer(0)) => Ok(None),
+            Some(PrefValue::Integer(secs)) if secs > 0 => {
+                Ok(Some(Duration::from_secs(secs as u64)))
+            }
+            Some(other) => bail!("idleTime in {DOMAIN} is not a number of seconds: {other}"),
+        }
+    }
+
+    /// Start the screen saver after `idle` of inactivity, or never with `None`.
+    ///
+    /// The time is stored in whole seconds; System Settings only offers 1 minute to 1 hour, but
+    /// any time from 1 second works.
+    pub fn set_idle_time(idle: Option<Duration>) -> Result<()> {
+        let secs = match idle {
+            None => 0,
+            Some(idle) if idle.as_secs() == 0 => {
+                bail!("The idle time must be at least a second; use None for never.")
+            }
+            Some(idle) => i64::try_from(idle.as_secs())?,
+        };
+        Preferences::write(Self::domain(), "idleTime", PrefValue::Integer(secs))
+    }
+
+    /// The name of the selected screen saver (`moduleDict.moduleName`), if one was chosen.
+    pub fn module() -> Result<Option<String>> {
+        match Self::get("moduleDict")? {
+            Some(PrefValue::Dictionary(dict)) => Ok(match dict.get("moduleName") {
+                Some(PrefValue::String(name)) => Some(name.clone()),
+                _ => None,
+            }),
+            _ => Ok(None),
+        }
+    }
+
+    /// Whether the screen saver shows a clock (`showClock`); off until set.
+    pub fn show_clock() -> Result<bool> {
+        match Self::get("showClock")? {
+            None => Ok(false),
+            Some(PrefValue::Boolean(on)) => Ok(on),
+            Some(PrefValue::Integer(on)) => Ok(on != 0),
+            Some(other) => bail!("showClock in {DOMAIN} is not a boolean: {other}"),
+        }
+    }
+
+    /// Show or hide the clock on the screen saver.
+    pub fn set_show_clock(on: bool) -> Result<()> {
+        Preferences::write(Self::domain(), "showClock", PrefValue::Boolean(on))
+    }
+}
@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: MIT
+
+//! The CFPreferences search list: which layers a read consults, and in which order.
+//!
+//! Managed preferences installed by configuration profiles come first, followed by the
+//! CFPreferences database: the app's own values, then the global domain, each for the current
+//! host before any host, first for the current user and then for any user.
+//!
+//! Suites an app adds at runtime (`-[NSUserDefaults addSuiteNamed:]`) are only known inside that
+//! app and are left out.
+
+use plist::Value;
+use std::{collections::HashMap, env, fs, path::PathBuf};
+
+use crate::{
+    Domain, PrefLayer, PrefValue, core::foundation, preferences::convert::plist_to_prefvalue,
+};
+
+const MANAGED_DIR: &str = "/Library/Managed Preferences";
+const GLOBAL: &str = ".GlobalPreferences";
+
+/// The layers consulted when reading from `domain`, from the highest precedence to the lowest.
+pub(crate) fn layers(domain: &Domain) -> Vec<PrefLayer> {
+    if domain.file().is_some() {
+        return vec![PrefLayer::DEFAULT];
+    }
+
+    let scopes: &[bool] = match domain {
+        Domain::Global => &[true],
+        _ => &[false, true],
+    };
+
+    // Machine-wide domains aren't stored per user, so they only see the any-user layers.
+    let users: &[bool] = match domain {
+        Domain::System(_) => &[true],
+        _ => &[false, true],
+    };
+
+    let mut layers = Vec::new();
+    for &any_user in users {
+        for &global in scopes {
+            layers.push(PrefLayer {
+                managed: true,
+                global,
+                any_user,
+                current_host: false,
+            });
+        }
+    }
+    for &any_user in users {
+        for &global in scopes {
+            for current_host in [true, false] {
+                layers.push(PrefLayer {
+                    managed: false,
+                    global,
+                    any_user,
+                    current_host,
+                });
+            }
+        }
+    }
+    layers
+}
+
+/// The plist a managed layer is read from.
+fn managed_plist(domain: &Domain, layer: &PrefLayer) -> Option<PathBuf> {
+    let name = if layer.global {
+        GLOBAL.to_string()
+    } else {
+        domain.get_cf_name()
+    };
+    let dir = PathBuf::from(MANAGED_DIR);
+    let dir = if layer.any_user {
+        dir
+    } else {
+        dir.join(env::var_os("USER")?)
+    };
+    Some(dir.join(format!("{name}.plist")))
+}
+
+/// The value of `key` stored in `layer`, without falling back to other layers.
+pub(crate) fn read_layer(domain: &Domain, key: &str, layer: &PrefLayer) -> Option<PrefValue> {
+    if domain.file().is_some() {
+        return foundation::read_pref(domain, key).ok();
+    }
+    if !layer.managed {
+        return foundation::read_layer(&foundation::app_id(domain), key, layer);
+    }
+
+    let root = Value::from_file(managed_plist(domain, layer)?).ok()?;
+    root.as_dictionary()?
+        .get(key)
+        .and_then(|v| plist_to_prefvalue(v).ok())
+}
+
+/// The values of every managed plist with the domain they apply to, those for every user before
+/// those for the current user.
+pub(crate) fn managed_values() -> Vec<(Domain, HashMap<String, PrefValue>)> {
+    let dir = PathBuf::from(MANAGED_DIR);
+    let user_dir = env::var_os("USER").map(|user| dir.join(user));
+
+    let mut found = Vec::new();
+    for dir in [Some(dir), user_dir].into_iter().flatten() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        paths.sort();
+        for path in paths {
+            let Some(name) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_suffix(".plist"))
+            else {
+                continue;
+            };
+            // complete.plist merges every other managed plist in the folder.
+            if name == "complete" {
+                continue;
+            }
+            let domain = match name {
+                GLOBAL => Domain::Global,
+                name => Domain::User(name.to_string()),
+            };
+            let Ok(Value::Dictionary(dict)) = Value::from_file(&path) else {
+                continue;
+            };
+            let values = dict
+                .iter()
+                .filter_map(|(k, v)| plist_to_prefvalue(v).ok().map(|v| (k.clone(), v)))
+                .collect();
+            found.push((domain, values));
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn written_layer_is_searched() {
+        let domains = [
+            Domain::Global,
+            Domain::User("com.apple.dock".into()),
+            Domain::ByHost("com.apple.screensaver".into()),
+            Domain::ByHost(GLOBAL.into()),
+            Domain::System("com.apple.loginwindow".into()),
+            Domain::Path("/tmp/example.plist".into()),
+        ];
+        for domain in domains {
+            assert!(
+                layers(&domain).contains(&PrefLayer::written_by(&domain)),
+                "{domain}"
+            );
+        }
+    }
+
+    #[test]
+    fn global_domain_writes_the_global_layer() {
+        let layer = PrefLayer::written_by(&Domain::Global);
+        assert!(layer.global);
+        assert!(!layer.any_user && !layer.current_host);
+    }
+}
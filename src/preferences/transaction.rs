@@ -0,0 +1,270 @@
+// SPDX-License-Identifier: MIT
+
+//! Staged changes across several domains, committed as a unit.
+//!
+//! A [`Transaction`] collects writes and deletes for any number of domains without touching
+//! them. [`Transaction::commit`] applies the changes domain by domain, in the order each domain was
+//! first staged. Before a domain is changed, the current values of its staged keys are recorded;
+//! if any change fails, every domain changed so far (including the failing one) is restored to
+//! those values, so Dock, Finder and NSGlobalDomain never end up half-configured.
+//! [`Transaction::commit_with`] applies another [`OnError`] policy instead.
+
+use anyhow::{Context, Result, anyhow, bail};
+use std::time::Instant;
+
+use crate::{
+    ApplyReport, ApplyStatus, Domain, PrefValue,
+    core::{foundation, guard},
+};
+
+use super::{backup, cache, report};
+
+#[derive(Debug, Clone)]
+enum Change {
+    Write(String, PrefValue),
+    Delete(String),
+}
+
+impl Change {
+    fn key(&self) -> &str {
+        match self {
+            Change::Write(key, _) | Change::Delete(key) => key,
+        }
+    }
+
+    /// The value the change leaves behind; `None` for deletions.
+    fn value(&self) -> Option<PrefValue> {
+        match self {
+            Change::Write(_, value) => Some(value.clone()),
+            Change::Delete(_) => None,
+        }
+    }
+}
+
+/// Writes and deletes staged across several domains.
+///
+/// ```no_run
+/// use defaults_rs::{Domain, PrefValue, Transaction};
+///
+/// let mut tx = Transaction::new();
+/// tx.write(Domain::User("com.apple.dock".into()), "autohide", PrefValue::Boolean(true))
+///     .write(Domain::User("com.apple.finder".into()), "ShowPathbar", PrefValue::Boolean(true))
+///     .write(Domain::Global, "AppleShowAllExtensions", PrefValue::Boolean(true));
+/// tx.commit().unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Transaction {
+    /// Staged changes grouped by domain, in the order domains were first staged.
+    domains: Vec<(Domain, Vec<Change>)>,
+}
+
+/// What [`Transaction::commit_with`] does when a change fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnError {
+    /// Apply every other change anyway, reporting the failures.
+    Continue,
+    /// Stop at the first failure, keeping the changes applied before it.
+    Abort,
+    /// Stop at the first failure and restore every domain changed so far.
+    #[default]
+    Rollback,
+}
+
+impl OnError {
+    /// Parse a policy name: `continue`, `abort` or `rollback`.
+    pub fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "continue" => OnError::Continue,
+            "abort" => OnError::Abort,
+            "rollback" => OnError::Rollback,
+            other => bail!("Unknown failure policy `{other}` (use continue, abort or rollback)"),
+        })
+    }
+}
+
+impl std::fmt::Display for OnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            OnError::Continue => "continue",
+            OnError::Abort => "abort",
+            OnError::Rollback => "rollback",
+        })
+    }
+}
+
+/// The original values of the keys staged for one domain; `None` for keys which didn't exist.
+type Snapshot = Vec<(String, Option<PrefValue>)>;
+
+impl Transaction {
+    /// Create an empty transaction.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn stage(&mut self, domain: Domain, change: Change) -> &mut Self {
+        match self.domains.iter_mut().find(|(d, _)| *d == domain) {
+            Some((_, changes)) => changes.push(change),
+            None => self.domains.push((domain, vec![change])),
+        }
+        self
+    }
+
+    /// Stage writing `value` to `key` in `domain`.
+    pub fn write(&mut self, domain: Domain, key: &str, value: PrefValue) -> &mut Self {
+        self.stage(domain, Change::Write(key.to_string(), value))
+    }
+
+    /// Stage deleting `key` from `domain`.
+    pub fn delete(&mut self, domain: Domain, key: &str) -> &mut Self {
+        self.stage(domain, Change::Delete(key.to_string()))
+    }
+
+    /// The domains touched by this transaction, in commit order.
+    pub fn domains(&self) -> Vec<Domain> {
+        self.domains.iter().map(|(d, _)| d.clone()).collect()
+    }
+
+    /// Whether nothing has been staged.
+    pub fn is_empty(&self) -> bool {
+        self.domains.is_empty()
+    }
+
+    /// Apply all staged changes, restoring every touched domain if any of them fails.
+    ///
+    /// On success, the report lists each staged change; changes to keys which already held the
+    /// staged value are reported as unchanged and not written.
+    pub fn commit(self) -> Result<ApplyReport> {
+        let report = self.commit_with(OnError::Rollback)?;
+        if let Some(item) = report.items.iter().find(|i| i.status == ApplyStatus::Error) {
+            let restored: Vec<String> = report.restored.iter().map(Domain::to_string).collect();
+            let mut context = format!(
+                "Transaction failed in {}; restored {}.",
+                item.domain,
+                if restored.is_empty() {
+                    "nothing".to_string()
+                } else {
+                    restored.join(", ")
+                }
+            );
+            for (domain, error) in &report.restore_failed {
+                context.push_str(&format!(" Couldn't restore {domain}: {error}."));
+            }
+            return Err(anyhow!(
+                "Failed to apply {}: {}",
+                item.key,
+                item.error.as_deref().unwrap_or("unknown error")
+            )
+            .context(context));
+        }
+        Ok(report)
+    }
+
+    /// Apply all staged changes, handling failures according to `policy`.
+    ///
+    /// Failures don't make this return an error; they are reported as [`ApplyStatus::Error`],
+    /// changes not attempted after one as [`ApplyStatus::Skipped`], and
+    /// [`ApplyReport::outcome`] tells a clean apply from a partial or rolled back one. A key that
+    /// can't be read for the rollback snapshot is an error, returned before anything is written.
+    pub fn commit_with(self, policy: OnError) -> Result<ApplyReport> {
+        let started = Instant::now();
+        for (domain, _) in &self.domains {
+            guard::ensure_writable(&domain.get_cf_name())?;
+        }
+        // Snapshot before anything is written, so a key that can't be read fails the commit
+        // instead of being restored as absent.
+        let mut snapshots = self
+            .domains
+            .iter()
+            .map(|(domain, changes)| snapshot(domain, changes))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter();
+
+        let mut applied: Vec<(&Domain, Snapshot)> = Vec::new();
+        let mut report = ApplyReport {
+            policy: Some(policy),
+            ..ApplyReport::default()
+        };
+        let mut failed = false;
+
+        for (domain, changes) in &self.domains {
+            if failed {
+                report.items.extend(
+                    changes
+                        .iter()
+                        .map(|c| report::skipped(domain, c.key(), c.value())),
+                );
+                continue;
+            }
+
+            applied.push((domain, snapshots.next().unwrap_or_default()));
+            let prepared = backup::ensure(domain).map_err(|e| format!("{e:#}"));
+            cache::invalidate(domain);
+
+            for change in changes {
+                let key = change.key();
+                if failed {
+                    report
+                        .items
+                        .push(report::skipped(domain, key, change.value()));
+                    continue;
+                }
+
+                let old = foundation::read_pref(domain, key).ok();
+                let item = report::apply(domain, key, old, change.value(), || {
+                    if let Err(e) = &prepared {
+                        bail!("{e}");
+                    }
+                    match change {
+                        Change::Write(_, value) => foundation::write_pref(domain, key, value),
+                        Change::Delete(_) => foundation::delete_key(domain, key),
+                    }
+                });
+                failed = item.status == ApplyStatus::Error && policy != OnError::Continue;
+                report.items.push(item);
+            }
+        }
+
+        if failed && policy == OnError::Rollback {
+            for (domain, snapshot) in applied.iter().rev() {
+                match restore(domain, snapshot) {
+                    Ok(()) => report.restored.push((*domain).clone()),
+                    Err(e) => report
+                        .restore_failed
+                        .push(((*domain).clone(), format!("{e:#}"))),
+                }
+            }
+        }
+        report.elapsed = started.elapsed();
+        Ok(report)
+    }
+}
+
+/// The current values of the keys `changes` touch, `None` for keys that aren't set.
+fn snapshot(domain: &Domain, changes: &[Change]) -> Result<Snapshot> {
+    let mut snapshot: Snapshot = Vec::new();
+    for change in changes {
+        let key = change.key();
+        if snapshot.iter().all(|(k, _)| k != key) {
+            let original = foundation::read_pref_opt(domain, key)
+                .with_context(|| format!("failed to read {key} in {domain} before changing it"))?;
+            snapshot.push((key.to_string(), original));
+        }
+    }
+    Ok(snapshot)
+}
+
+/// Restore the keys in `snapshot`, carrying on past failures and returning the first one.
+fn restore(domain: &Domain, snapshot: &Snapshot) -> Result<()> {
+    cache::invalidate(domain);
+    let mut first = None;
+    for (key, original) in snapshot {
+        let restored = match original {
+            Some(value) => foundation::write_pref(domain, key, value),
+            None => foundation::delete_key(domain, key),
+        };
+        if let Err(e) = restored {
+            first.get_or_insert(e.context(format!("failed to restore {key}")));
+        }
+    }
+    first.map_or(Ok(()), Err)
+}
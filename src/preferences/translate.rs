@@ -0,0 +1,161 @@
+// SPDX-License-Identifier: MIT
+
+//! Translating host-specific values captured on another Mac.
+//!
+//! Some preferences only make sense on the Mac they were written on: ByHost plists are named
+//! after its hardware UUID, and settings per display are keyed by display UUIDs. When an export
+//! from another Mac is imported with
+//! [`Preferences::import_translated`](crate::Preferences::import_translated), every value passes
+//! through a [`Translator`] first. Values it can't translate are reported as
+//! [`ApplyStatus::Untranslated`](crate::ApplyStatus::Untranslated) and left unwritten, rather than
+//! writing UUIDs which mean nothing on this Mac.
+//!
+//! [`UuidMap`] is the translator drs uses; any closure taking the domain, key and value works too:
+//!
+//! ```no_run
+//! use defaults_rs::{Domain, PrefValue, Preferences};
+//!
+//! let report = Preferences::import_translated(
+//!     Domain::User("com.example.app".into()),
+//!     "export/com.example.app.plist",
+//!     &|_: &Domain, key: &str, value: PrefValue| match key {
+//!         "LastWindowFrame" => anyhow::bail!("window frames depend on the displays"),
+//!         _ => Ok((key.to_string(), value)),
+//!     },
+//! )
+//! .unwrap();
+//! ```
+
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+
+use crate::{Domain, PrefValue};
+
+/// A step rewriting keys and values captured on another Mac for this one.
+pub trait Translator {
+    /// Translate `key` and its `value` in `domain`, or fail if they can't be used on this Mac.
+    fn translate(
+        &self,
+        domain: &Domain,
+        key: &str,
+        value: PrefValue,
+    ) -> Result<(String, PrefValue)>;
+}
+
+impl<F> Translator for F
+where
+    F: Fn(&Domain, &str, PrefValue) -> Result<(String, PrefValue)>,
+{
+    fn translate(
+        &self,
+        domain: &Domain,
+        key: &str,
+        value: PrefValue,
+    ) -> Result<(String, PrefValue)> {
+        self(domain, key, value)
+    }
+}
+
+/// Whether `text` is a UUID such as `01234567-89AB-CDEF-0123-456789ABCDEF`.
+pub(crate) fn is_uuid(text: &[u8]) -> bool {
+    text.len() == 36
+        && text.iter().enumerate().all(|(i, b)| match i {
+            8 | 13 | 18 | 23 => *b == b'-',
+            _ => b.is_ascii_hexdigit(),
+        })
+}
+
+/// The byte offsets of every UUID in `text`.
+fn find_uuids(text: &str) -> Vec<usize> {
+    let bytes = text.as_bytes();
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i + 36 <= bytes.len() {
+        if is_uuid(&bytes[i..i + 36]) {
+            found.push(i);
+            i += 36;
+        } else {
+            i += 1;
+        }
+    }
+    found
+}
+
+/// Translates host and display UUIDs through a fixed mapping.
+///
+/// Mapped UUIDs are replaced wherever they appear in keys and strings, ignoring case. Keys
+/// naming a UUID which isn't mapped, as per-display settings do, can't be translated. UUIDs in
+/// values are left alone unless mapped, since most of them aren't host-specific.
+#[derive(Debug, Clone, Default)]
+pub struct UuidMap {
+    /// Target UUIDs by uppercased source UUID.
+    map: HashMap<String, String>,
+}
+
+impl UuidMap {
+    /// An empty mapping.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Translate `from` to `to`.
+    pub fn map(mut self, from: &str, to: &str) -> Self {
+        self.map.insert(from.to_ascii_uppercase(), to.to_string());
+        self
+    }
+
+    /// Whether nothing is mapped.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// `text` with every mapped UUID replaced; unmapped UUIDs fail when `strict`.
+    fn replace(&self, text: &str, strict: bool) -> Result<String> {
+        let mut out = String::with_capacity(text.len());
+        let mut last = 0;
+        for start in find_uuids(text) {
+            let uuid = &text[start..start + 36];
+            match self.map.get(&uuid.to_ascii_uppercase()) {
+                Some(to) => {
+                    out.push_str(&text[last..start]);
+                    out.push_str(to);
+                    last = start + 36;
+                }
+                None if strict => bail!("{uuid} belongs to another Mac or display."),
+                None => {}
+            }
+        }
+        out.push_str(&text[last..]);
+        Ok(out)
+    }
+
+    fn translate_value(&self, value: PrefValue) -> Result<PrefValue> {
+        Ok(match value {
+            PrefValue::String(s) => PrefValue::String(self.replace(&s, false)?),
+            PrefValue::Uuid(u) => PrefValue::Uuid(self.replace(&u, false)?),
+            PrefValue::Array(items) => PrefValue::Array(
+                items
+                    .into_iter()
+                    .map(|item| self.translate_value(item))
+                    .collect::<Result<_>>()?,
+            ),
+            PrefValue::Dictionary(dict) => PrefValue::Dictionary(
+                dict.into_iter()
+                    .map(|(k, v)| Ok((self.replace(&k, true)?, self.translate_value(v)?)))
+                    .collect::<Result<_>>()?,
+            ),
+            other => other,
+        })
+    }
+}
+
+impl Translator for UuidMap {
+    fn translate(
+        &self,
+        _domain: &Domain,
+        key: &str,
+        value: PrefValue,
+    ) -> Result<(String, PrefValue)> {
+        Ok((self.replace(key, true)?, self.translate_value(value)?))
+    }
+}
@@ -1,14 +1,43 @@
 // SPDX-License-Identifier: MIT
 
+use serde::{Serialize, Serializer, ser::SerializeStruct};
+use std::path::{Path, PathBuf};
+
 use crate::PrefValue;
 
-/// Preferences domain (user or global).
+/// Preferences domain (user or global), or a plist file.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Domain {
     /// A user domain, e.g., "com.apple.finder"
     User(String),
     /// The global preferences domain / NSGlobalDomain / .GlobalPreferences
     Global,
+    /// The current host's (ByHost) preferences of a domain, holding its CoreFoundation name.
+    ///
+    /// Reads and writes only see values stored for this Mac, under
+    /// `~/Library/Preferences/ByHost`, rather than going through the search list.
+    ByHost(String),
+    /// The machine-wide preferences of a domain in `/Library/Preferences`, holding its
+    /// CoreFoundation name.
+    ///
+    /// These apply to every user; reading works for anyone, writing needs root.
+    System(String),
+    /// A sandboxed app's preferences in its container under `~/Library/Containers`, holding its
+    /// bundle identifier.
+    ///
+    /// User domains without a plist in `~/Library/Preferences` already resolve to their app's
+    /// container when it has one, so this is only needed to address the container explicitly.
+    Container(String),
+    /// A shared App Group suite in `~/Library/Group Containers`, holding the group identifier
+    /// (e.g. `group.com.example.app`).
+    Group(String),
+    /// A plist file anywhere on disk, read and written directly rather than through
+    /// CFPreferences.
+    ///
+    /// Writes lock the file's directory and replace the file atomically, keeping its format.
+    /// Running apps don't see changes made this way to their own plists, since cfprefsd keeps
+    /// serving its cached copy.
+    Path(PathBuf),
 }
 
 impl Domain {
@@ -16,23 +45,229 @@ impl Domain {
     pub fn get_cf_name(&self) -> String {
         match &self {
             Domain::Global => String::from(".GlobalPreferences"),
-            Domain::User(name) => name.clone(),
+            Domain::User(name)
+            | Domain::ByHost(name)
+            | Domain::System(name)
+            | Domain::Container(name)
+            | Domain::Group(name) => name.clone(),
+            Domain::Path(path) => path.with_extension("").to_string_lossy().into_owned(),
+        }
+    }
+
+    /// Returns the current host's (ByHost) counterpart of this domain.
+    pub fn current_host(&self) -> Domain {
+        Domain::ByHost(self.get_cf_name())
+    }
+
+    /// Returns the machine-wide (system) counterpart of this domain.
+    pub fn system(&self) -> Domain {
+        Domain::System(self.get_cf_name())
+    }
+
+    /// Returns the plist file this domain is read from and written to directly, for
+    /// [`Domain::Path`].
+    pub fn file(&self) -> Option<&Path> {
+        match self {
+            Domain::Path(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Whether this is a current-host (ByHost) domain.
+    pub fn is_current_host(&self) -> bool {
+        matches!(self, Domain::ByHost(_))
+    }
+
+    /// Whether this domain's values live in a container: either it is a [`Domain::Container`] or
+    /// [`Domain::Group`], or a user domain with a container plist but none of its own.
+    pub fn in_container(&self) -> bool {
+        dirs::home_dir().is_some_and(|home| self.in_container_of(&home))
+    }
+
+    /// [`Self::in_container`] for the user whose home is `home`.
+    fn in_container_of(&self, home: &Path) -> bool {
+        match self {
+            Domain::Container(_) | Domain::Group(_) => true,
+            Domain::User(name) => {
+                !home
+                    .join(format!("Library/Preferences/{name}.plist"))
+                    .is_file()
+                    && container_plist(home, name).is_file()
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the plist in the app's sandbox container this domain is kept in by the sandboxed
+    /// app itself, if it has one, even while a plist outside the container takes precedence for
+    /// other processes.
+    pub fn sandbox_plist(&self) -> Option<PathBuf> {
+        match self {
+            Domain::User(name) | Domain::Container(name) => {
+                Some(container_plist(&dirs::home_dir()?, name)).filter(|p| p.is_file())
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the plist file backing this domain for the current user.
+    ///
+    /// ByHost plists are named after the Mac's hardware UUID, so for those the existing file is
+    /// looked up and `None` is returned until one has been written.
+    pub fn plist_path(&self) -> Option<PathBuf> {
+        self.plist_path_in(&dirs::home_dir()?)
+    }
+
+    /// [`Self::plist_path`] for the user whose home is `home`.
+    pub(crate) fn plist_path_in(&self, home: &Path) -> Option<PathBuf> {
+        if let Domain::Path(path) = self {
+            return Some(path.clone());
+        }
+        let name = self.get_cf_name();
+        if let Domain::System(_) = self {
+            return Some(PathBuf::from(format!("/Library/Preferences/{name}.plist")));
+        }
+        if let Domain::Group(_) = self {
+            return Some(group_plist(home, &name));
+        }
+        if self.in_container_of(home) {
+            return Some(container_plist(home, &name));
         }
+
+        let dir = home.join("Library/Preferences");
+        if !self.is_current_host() {
+            return Some(dir.join(format!("{name}.plist")));
+        }
+
+        let prefix = format!("{name}.");
+        std::fs::read_dir(dir.join("ByHost"))
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .find(|path| {
+                path.file_name()
+                    .and_then(|f| f.to_str())
+                    .and_then(|f| f.strip_prefix(&prefix))
+                    .and_then(|rest| rest.strip_suffix(".plist"))
+                    .is_some_and(|uuid| !uuid.is_empty() && !uuid.contains('.'))
+            })
     }
 }
 
+/// The plist a sandboxed app with the bundle identifier `name` keeps its preferences in.
+fn container_plist(home: &Path, name: &str) -> PathBuf {
+    home.join("Library/Containers")
+        .join(name)
+        .join(format!("Data/Library/Preferences/{name}.plist"))
+}
+
+/// The plist of the App Group `name`'s shared suite.
+fn group_plist(home: &Path, name: &str) -> PathBuf {
+    home.join("Library/Group Containers")
+        .join(name)
+        .join(format!("Library/Preferences/{name}.plist"))
+}
+
 impl std::fmt::Display for Domain {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Domain::User(s) => write!(f, "{}", s),
             Domain::Global => write!(f, "NSGlobalDomain"),
+            Domain::ByHost(s) if s == ".GlobalPreferences" => {
+                write!(f, "NSGlobalDomain (current host)")
+            }
+            Domain::ByHost(s) => write!(f, "{} (current host)", s),
+            Domain::System(s) if s == ".GlobalPreferences" => write!(f, "NSGlobalDomain (system)"),
+            Domain::System(s) => write!(f, "{} (system)", s),
+            Domain::Container(s) => write!(f, "{} (container)", s),
+            Domain::Group(s) => write!(f, "{} (app group)", s),
+            Domain::Path(path) => write!(f, "{}", path.display()),
         }
     }
 }
 
+/// Encoding of an exported plist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlistFormat {
+    /// Binary plist, as stored under `~/Library/Preferences`.
+    #[default]
+    Binary,
+    /// XML plist, readable and diffable as text.
+    Xml,
+    /// Typed JSON (see [`PrefValue::to_typed_json`]), which keeps every value exactly.
+    Json,
+}
+
+/// What a [`FindMatch`] matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    /// The word is part of a dictionary key.
+    Key,
+    /// The word is part of a value.
+    Value,
+}
+
+/// Serialized as its display name, `key` or `value`.
+impl Serialize for MatchKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl std::fmt::Display for MatchKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            MatchKind::Key => "key",
+            MatchKind::Value => "value",
+        })
+    }
+}
+
 /// Result of a find operation.
 #[derive(Debug)]
 pub struct FindMatch {
+    /// The domain the match was found in.
+    pub domain: Domain,
+    /// Key path of the match, which can be passed straight to
+    /// [`Preferences::read`](crate::Preferences::read).
+    pub key: String,
+    pub kind: MatchKind,
+    pub value: PrefValue,
+    /// The type of `value`, as [`PrefValue::get_type`] names it.
+    pub value_type: &'static str,
+    /// `value` rendered for display, with any renderer registered for the key applied.
+    pub snippet: String,
+}
+
+/// Serialized with `domain` as its display name and `value` as typed JSON
+/// ([`PrefValue::to_typed_json`]), so values read back exactly.
+impl Serialize for FindMatch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("FindMatch", 6)?;
+        s.serialize_field("domain", &self.domain.to_string())?;
+        s.serialize_field("key", &self.key)?;
+        s.serialize_field("kind", &self.kind)?;
+        s.serialize_field("type", self.value_type)?;
+        s.serialize_field("value", &self.value.to_typed_json())?;
+        s.serialize_field("snippet", &self.snippet)?;
+        s.end()
+    }
+}
+
+/// A key forced by a configuration profile (MDM).
+#[derive(Debug)]
+pub struct ForcedKey {
+    pub domain: Domain,
     pub key: String,
+    /// The value the profile enforces.
     pub value: PrefValue,
 }
+
+/// A page of domain entries, ordered by key.
+#[derive(Debug)]
+pub struct DomainPage {
+    /// Entries in ascending key order.
+    pub entries: Vec<(String, PrefValue)>,
+    /// Cursor to pass for the next page, if more entries remain.
+    pub next_cursor: Option<String>,
+}
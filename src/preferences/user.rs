@@ -0,0 +1,163 @@
+// SPDX-License-Identifier: MIT
+
+//! Another user's preferences.
+//!
+//! CFPreferences only serves the current user (and any user, via `/Library/Preferences`), so a
+//! [`UserScope`] maps domains to the plists in that user's home instead, as [`Domain::Path`]s.
+//! This needs root and suits provisioning scripts configuring freshly created accounts: the
+//! user's cfprefsd doesn't notice the changes while they are logged in.
+
+use anyhow::{Context, Result, bail};
+use std::{
+    ffi::{CStr, CString, c_char},
+    mem::MaybeUninit,
+    path::{Path, PathBuf},
+    ptr,
+};
+
+use crate::Domain;
+
+/// `struct passwd` from `<pwd.h>` on macOS.
+#[repr(C)]
+struct Passwd {
+    pw_name: *const c_char,
+    pw_passwd: *const c_char,
+    pw_uid: u32,
+    pw_gid: u32,
+    pw_change: i64,
+    pw_class: *const c_char,
+    pw_gecos: *const c_char,
+    pw_dir: *const c_char,
+    pw_shell: *const c_char,
+    pw_expire: i64,
+}
+
+unsafe extern "C" {
+    fn getpwnam_r(
+        name: *const c_char,
+        pwd: *mut Passwd,
+        buf: *mut c_char,
+        buflen: usize,
+        result: *mut *mut Passwd,
+    ) -> i32;
+    fn getpwuid_r(
+        uid: u32,
+        pwd: *mut Passwd,
+        buf: *mut c_char,
+        buflen: usize,
+        result: *mut *mut Passwd,
+    ) -> i32;
+}
+
+/// `ERANGE`, returned when the buffer is too small for the entry's strings.
+const ERANGE: i32 = 34;
+
+/// A user whose preferences are read and written through the plists in their home.
+///
+/// ```no_run
+/// use defaults_rs::{Domain, PrefValue, Preferences, UserScope};
+///
+/// let user = UserScope::lookup("newhire").unwrap();
+/// let dock = user.domain(&Domain::User("com.apple.dock".into())).unwrap();
+/// Preferences::write(dock, "autohide", PrefValue::Boolean(true)).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserScope {
+    name: String,
+    uid: u32,
+    home: PathBuf,
+}
+
+impl UserScope {
+    /// Look up a user by login name or numeric user ID.
+    pub fn lookup(user: &str) -> Result<Self> {
+        let uid = user.parse::<u32>().ok();
+        let name = CString::new(user).context("User names can't contain NUL.")?;
+
+        // The entry's strings live in `buf`, which grows until they fit.
+        let mut buf: Vec<c_char> = vec![0; 4096];
+        let mut pwd = MaybeUninit::<Passwd>::uninit();
+        let mut entry: *mut Passwd = ptr::null_mut();
+        loop {
+            let err = unsafe {
+                match uid {
+                    Some(uid) => getpwuid_r(
+                        uid,
+                        pwd.as_mut_ptr(),
+                        buf.as_mut_ptr(),
+                        buf.len(),
+                        &mut entry,
+                    ),
+                    None => getpwnam_r(
+                        name.as_ptr(),
+                        pwd.as_mut_ptr(),
+                        buf.as_mut_ptr(),
+                        buf.len(),
+                        &mut entry,
+                    ),
+                }
+            };
+            match err {
+                0 => break,
+                ERANGE if buf.len() < 1 << 20 => buf.resize(buf.len() * 2, 0),
+                err => bail!(
+                    "Failed to look up user {user}: {}",
+                    std::io::Error::from_raw_os_error(err)
+                ),
+            }
+        }
+        if entry.is_null() {
+            bail!("No such user: {user}");
+        }
+
+        let entry = unsafe { &*entry };
+        let text = |ptr: *const c_char| {
+            if ptr.is_null() {
+                String::new()
+            } else {
+                unsafe { CStr::from_ptr(ptr) }
+                    .to_string_lossy()
+                    .into_owned()
+            }
+        };
+        let home = text(entry.pw_dir);
+        if home.is_empty() {
+            bail!("User {user} has no home directory.");
+        }
+
+        Ok(UserScope {
+            name: text(entry.pw_name),
+            uid: entry.pw_uid,
+            home: PathBuf::from(home),
+        })
+    }
+
+    /// The user's login name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The user's numeric ID.
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// The user's home directory.
+    pub fn home(&self) -> &Path {
+        &self.home
+    }
+
+    /// The plist backing `domain` for this user, as a [`Domain::Path`].
+    ///
+    /// System domains and plist files aren't per-user and are returned unchanged. ByHost
+    /// plists are named after the Mac's hardware UUID, so only existing ones can be found.
+    pub fn domain(&self, domain: &Domain) -> Result<Domain> {
+        if matches!(domain, Domain::System(_) | Domain::Path(_)) {
+            return Ok(domain.clone());
+        }
+        match domain.plist_path_in(&self.home) {
+            Some(path) => Ok(Domain::Path(path)),
+            None => bail!("{domain} has no ByHost plist for {} yet.", self.name),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: MIT
+
+//! Polling-based change notifications for one or more domains.
+//!
+//! The domains' plist modification times are checked on an adaptive interval: it starts at
+//! [`WatchOptions::min_interval`] and doubles up to [`WatchOptions::max_interval`] while nothing
+//! changes. Once a change shows up, polling drops back to the minimum interval and further
+//! changes are coalesced until the domain has been quiet for [`WatchOptions::debounce`], so an
+//! app rewriting its plist several times per second produces a single [`DomainChange`]. Any number
+//! of domains are watched from one thread, each with its own coalescing.
+
+use anyhow::Result;
+use std::{
+    collections::{HashMap, HashSet},
+    fs, thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{Domain, PrefValue, Preferences};
+
+/// Polling and coalescing settings for [`Preferences::watch`].
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// Shortest delay between two polls, used right after a change.
+    pub min_interval: Duration,
+    /// Longest delay between two polls, reached after a stretch without changes.
+    pub max_interval: Duration,
+    /// How long a domain must stay unchanged before a burst of changes is reported.
+    pub debounce: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::from_millis(100),
+            max_interval: Duration::from_secs(2),
+            debounce: Duration::from_millis(500),
+        }
+    }
+}
+
+/// A coalesced set of changes to a domain.
+#[derive(Debug)]
+pub struct DomainChange {
+    pub domain: Domain,
+    /// Keys which were added, removed or modified, in ascending order.
+    pub keys: Vec<String>,
+}
+
+fn snapshot(domain: &Domain) -> Result<HashMap<String, PrefValue>> {
+    match Preferences::read_domain(domain.clone())? {
+        PrefValue::Dictionary(dict) => Ok(dict),
+        _ => Ok(HashMap::new()),
+    }
+}
+
+fn mtime(domain: &Domain) -> Option<SystemTime> {
+    domain
+        .plist_path()
+        .and_then(|path| fs::metadata(path).ok())
+        .and_then(|meta| meta.modified().ok())
+}
+
+/// Keys whose values differ between two snapshots.
+pub(crate) fn changed_keys(
+    old: &HashMap<String, PrefValue>,
+    new: &HashMap<String, PrefValue>,
+) -> Vec<String> {
+    let mut keys: Vec<String> = old
+        .keys()
+        .chain(new.keys())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .filter(|k| old.get(*k) != new.get(*k))
+        .cloned()
+        .collect();
+    keys.sort();
+    keys
+}
+
+/// Per-domain state of a watch.
+struct Watched {
+    domain: Domain,
+    last_mtime: Option<SystemTime>,
+    baseline: HashMap<String, PrefValue>,
+    pending_since: Option<Instant>,
+}
+
+/// Watch domains, calling `on_change` for every coalesced change until it returns `false`.
+///
+/// All domains share a single polling loop, so each poll costs one `stat` per domain.
+pub(crate) fn watch(
+    domains: Vec<Domain>,
+    opts: &WatchOptions,
+    mut on_change: impl FnMut(&DomainChange) -> bool,
+) -> Result<()> {
+    let mut watched = domains
+        .into_iter()
+        .map(|domain| {
+            Ok(Watched {
+                last_mtime: mtime(&domain),
+                baseline: snapshot(&domain)?,
+                pending_since: None,
+                domain,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut interval = opts.min_interval;
+
+    loop {
+        thread::sleep(interval);
+        let mut active = false;
+
+        for w in &mut watched {
+            let current = mtime(&w.domain);
+            if current != w.last_mtime {
+                w.last_mtime = current;
+                w.pending_since = Some(Instant::now());
+                active = true;
+                continue;
+            }
+
+            match w.pending_since {
+                Some(since) if since.elapsed() >= opts.debounce => {
+                    w.pending_since = None;
+
+                    let latest = snapshot(&w.domain)?;
+                    let keys = changed_keys(&w.baseline, &latest);
+                    w.baseline = latest;
+
+                    if !keys.is_empty()
+                        && !on_change(&DomainChange {
+                            domain: w.domain.clone(),
+                            keys,
+                        })
+                    {
+                        return Ok(());
+                    }
+                }
+                Some(_) => active = true,
+                None => {}
+            }
+        }
+
+        interval = if active {
+            opts.min_interval
+        } else {
+            (interval * 2).min(opts.max_interval)
+        };
+    }
+}
@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: MIT
+
+//! Low-level CFPreferences wrappers with an explicit user and host.
+//!
+//! **Unstable:** only available with the `unstable-raw` feature, and may change in any release.
+//!
+//! These are the calls [`crate::Preferences`] is built on, without its policy layer: nothing is
+//! backed up, the domain cache isn't invalidated and key paths aren't resolved. Only read-only
+//! mode is still honored. Some things to keep in mind:
+//!
+//! - [`set_value`] only stages a change in this process; call [`synchronize`] with the same app,
+//!   user and host to write it out.
+//! - cfprefsd caches preferences, so values written here may not be seen by running apps until
+//!   they synchronize themselves, and edits made to plists on disk aren't seen here at all.
+//! - Writing for [`User::Any`] changes `/Library/Preferences` and needs root; without it,
+//!   [`synchronize`] returns `false`.
+//! - Use [`ANY_APPLICATION`] as the app to address the global domain.
+//!
+//! ```no_run
+//! use defaults_rs::raw::{self, Host, User};
+//!
+//! let idle = raw::copy_value("com.apple.screensaver", "idleTime", User::Current, Host::Current);
+//! ```
+
+pub use crate::core::foundation::{
+    ANY_APPLICATION, Host, User, copy_app_value, copy_application_list, copy_key_list, copy_value,
+    set_value, synchronize, value_is_forced,
+};
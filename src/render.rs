@@ -0,0 +1,225 @@
+// SPDX-License-Identifier: MIT
+
+//! Custom renderers for values whose raw form is hard to read.
+//!
+//! A renderer is registered for a domain pattern and a key path pattern, where `*` matches any
+//! run of characters (e.g. `AppleSymbolicHotKeys.*` or `LSHandlers[*]`). When `read` or `find`
+//! prints a value whose domain and [`KeyPath`] match, the renderer's output is shown instead of
+//! the value. Renderers registered later take precedence; a renderer may return `None` to fall
+//! back to the next match or the default output.
+
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+use crate::{Domain, KeyPath, PrefValue, displays};
+
+/// A function rendering a value for display, or `None` if it doesn't apply.
+pub type RenderFn = Box<dyn Fn(&PrefValue) -> Option<String> + Send + Sync>;
+
+struct Renderer {
+    domain: String,
+    key: String,
+    render: RenderFn,
+}
+
+static RENDERERS: Mutex<Vec<Renderer>> = Mutex::new(Vec::new());
+
+fn renderers() -> MutexGuard<'static, Vec<Renderer>> {
+    let mut renderers = RENDERERS.lock().unwrap_or_else(|e| e.into_inner());
+    if renderers.is_empty() {
+        register_builtins(&mut renderers);
+    }
+    renderers
+}
+
+/// Register a renderer for values in domains matching `domain` at key paths matching `key`.
+pub fn register_renderer(domain: &str, key: &str, render: RenderFn) {
+    renderers().push(Renderer {
+        domain: domain.to_string(),
+        key: key.to_string(),
+        render,
+    });
+}
+
+/// Renders data holding JSON or UTF-8 text decoded, e.g. `<JSON data> {"tab":2}`, or `None` for
+/// other data, which keeps its byte summary.
+///
+/// Not registered by default, since most data is binary; `drs read --decode-data` registers it
+/// for every key:
+///
+/// ```no_run
+/// use defaults_rs::render::{decode_data, register_renderer};
+///
+/// register_renderer("*", "*", Box::new(decode_data));
+/// ```
+pub fn decode_data(val: &PrefValue) -> Option<String> {
+    let PrefValue::Data(data) = val else {
+        return None;
+    };
+    let text = std::str::from_utf8(data).ok()?;
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    if trimmed.starts_with(['{', '['])
+        && let Ok(json) = serde_json::from_str::<serde_json::Value>(trimmed)
+    {
+        return Some(format!("<JSON data> {json}"));
+    }
+    // Text has no control characters other than whitespace; anything else is likely binary.
+    if text
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+    {
+        return None;
+    }
+    Some(format!("<UTF-8 data> {}", serde_json::Value::from(text)))
+}
+
+/// Render a value with the most recently registered matching renderer.
+pub(crate) fn render(domain: &Domain, path: &KeyPath, val: &PrefValue) -> Option<String> {
+    let domain = domain.get_cf_name();
+    let key = path.to_string();
+
+    renderers()
+        .iter()
+        .rev()
+        .filter(|r| glob_match(&r.domain, &domain) && glob_match(&r.key, &key))
+        .find_map(|r| (r.render)(val))
+}
+
+/// Matches `text` against a pattern in which `*` stands for any run of characters.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => {
+            let Some(text) = text.strip_prefix(prefix) else {
+                return false;
+            };
+            text.char_indices()
+                .map(|(i, _)| i)
+                .chain([text.len()])
+                .any(|i| glob_match(rest, &text[i..]))
+        }
+    }
+}
+
+fn register_builtins(renderers: &mut Vec<Renderer>) {
+    renderers.push(Renderer {
+        domain: "com.apple.symbolichotkeys".to_string(),
+        key: "AppleSymbolicHotKeys.*".to_string(),
+        render: Box::new(render_hotkey),
+    });
+    renderers.push(Renderer {
+        domain: "*launchservices.secure".to_string(),
+        key: "LSHandlers[*]".to_string(),
+        render: Box::new(render_handler),
+    });
+    renderers.push(Renderer {
+        domain: "com.apple.windowserver*".to_string(),
+        key: "*".to_string(),
+        render: Box::new(render_display_uuid),
+    });
+}
+
+fn dict_get<'a>(val: &'a PrefValue, key: &str) -> Option<&'a PrefValue> {
+    match val {
+        PrefValue::Dictionary(dict) => dict.get(key),
+        _ => None,
+    }
+}
+
+fn as_str(val: &PrefValue) -> Option<&str> {
+    match val {
+        PrefValue::String(s) => Some(s),
+        _ => None,
+    }
+}
+
+fn as_int(val: &PrefValue) -> Option<i64> {
+    match val {
+        PrefValue::Integer(i) => Some(*i),
+        PrefValue::Boolean(b) => Some(*b as i64),
+        _ => None,
+    }
+}
+
+/// Renders an `AppleSymbolicHotKeys` entry, e.g. `⇧⌘3 (enabled)`.
+fn render_hotkey(val: &PrefValue) -> Option<String> {
+    let enabled = dict_get(val, "enabled").and_then(as_int)? != 0;
+    let shortcut = dict_get(val, "value")
+        .and_then(|v| dict_get(v, "parameters"))
+        .and_then(|p| match p {
+            PrefValue::Array(params) => {
+                let params: Vec<i64> = params.iter().filter_map(as_int).collect();
+                match params.as_slice() {
+                    [ascii, keycode, modifiers] => {
+                        Some(describe_shortcut(*ascii, *keycode, *modifiers))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .unwrap_or_else(|| "no shortcut".to_string());
+
+    let state = if enabled { "enabled" } else { "disabled" };
+    Some(format!("{shortcut} ({state})"))
+}
+
+/// Describes a hotkey from its character code, virtual key code and modifier flags.
+pub(crate) fn describe_shortcut(ascii: i64, keycode: i64, modifiers: i64) -> String {
+    const MODIFIERS: [(i64, &str); 5] = [
+        (0x800000, "fn"),
+        (0x40000, "⌃"),
+        (0x80000, "⌥"),
+        (0x20000, "⇧"),
+        (0x100000, "⌘"),
+    ];
+
+    let mut out: String = MODIFIERS
+        .iter()
+        .filter(|(flag, _)| modifiers & flag != 0)
+        .map(|(_, sym)| *sym)
+        .collect();
+
+    match u8::try_from(ascii).ok().map(char::from) {
+        Some(c) if c.is_ascii_graphic() => out.push(c.to_ascii_uppercase()),
+        Some(' ') => out.push_str("Space"),
+        _ => out.push_str(&format!("key {keycode}")),
+    }
+    out
+}
+
+/// Renders an `LSHandlers` entry, e.g. `public.html → com.google.chrome`.
+fn render_handler(val: &PrefValue) -> Option<String> {
+    let target = ["LSHandlerContentType", "LSHandlerURLScheme"]
+        .iter()
+        .find_map(|k| dict_get(val, k).and_then(as_str))?;
+    let handler = [
+        "LSHandlerRoleAll",
+        "LSHandlerRoleViewer",
+        "LSHandlerRoleEditor",
+    ]
+    .iter()
+    .find_map(|k| dict_get(val, k).and_then(as_str))?;
+
+    Some(format!("{target} → {handler}"))
+}
+
+/// Renders the UUID of a connected display with its name, e.g. `37D8832A-… (Color LCD)`.
+fn render_display_uuid(val: &PrefValue) -> Option<String> {
+    static ONLINE: OnceLock<Vec<displays::Display>> = OnceLock::new();
+
+    let (PrefValue::String(uuid) | PrefValue::Uuid(uuid)) = val else {
+        return None;
+    };
+    if !crate::preferences::translate::is_uuid(uuid.as_bytes()) {
+        return None;
+    }
+    let display = ONLINE
+        .get_or_init(displays::online)
+        .iter()
+        .find(|d| d.uuid.eq_ignore_ascii_case(uuid))?;
+    Some(format!("{uuid} ({})", display.name))
+}
@@ -0,0 +1,25 @@
+// SPDX-License-Identifier: MIT
+
+//! Runs the golden plist corpus in `tests/roundtrip` through CoreFoundation.
+
+#![cfg(all(target_os = "macos", feature = "test-fixtures"))]
+
+use std::path::Path;
+
+use defaults_rs::TestSandbox;
+
+#[test]
+fn corpus_round_trips() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roundtrip");
+    let results = TestSandbox::new()
+        .round_trip_dir(&dir)
+        .unwrap_or_else(|e| panic!("{e:#}"));
+
+    assert!(!results.is_empty(), "no plists in {}", dir.display());
+    let failed: Vec<String> = results
+        .iter()
+        .filter(|(_, outcome)| !outcome.is_ok())
+        .map(|(path, outcome)| format!("{}: {outcome:?}", path.display()))
+        .collect();
+    assert!(failed.is_empty(), "{}", failed.join("\n"));
+}
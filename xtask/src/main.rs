@@ -1,9 +1,12 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use clap::{Parser, Subcommand};
 use clap_mangen::Man;
+use defaults_rs::TestSandbox;
 use defaults_rs::cli::build_cli;
 use std::fs::{self, File};
 use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
 
 #[derive(Parser)]
 struct Cli {
@@ -19,6 +22,27 @@ enum Commands {
         #[arg(short, long, default_value = "man/man1")]
         dir: PathBuf,
     },
+    /// Check that sample plists survive import, read and export unchanged
+    Roundtrip {
+        /// Directory of sample plists
+        #[arg(short, long, default_value = "tests/roundtrip")]
+        dir: PathBuf,
+    },
+    /// Measure cold-start latency of a drs command and fail if the median exceeds a budget
+    StartupBench {
+        /// drs binary to run (build it with `cargo build --release` first)
+        #[arg(long, default_value = "target/release/drs")]
+        bin: PathBuf,
+        /// Number of timed runs
+        #[arg(short, long, default_value_t = 50)]
+        runs: usize,
+        /// Maximum allowed median in milliseconds
+        #[arg(long, default_value_t = 15)]
+        budget_ms: u64,
+        /// Arguments passed to drs
+        #[arg(last = true, default_values = ["read", "-g", "AppleLocale"])]
+        args: Vec<String>,
+    },
 }
 
 fn main() -> Result<()> {
@@ -28,6 +52,17 @@ fn main() -> Result<()> {
         Commands::Manpage { dir } => {
             generate_manpage(dir)?;
         }
+        Commands::Roundtrip { dir } => {
+            run_roundtrip(dir)?;
+        }
+        Commands::StartupBench {
+            bin,
+            runs,
+            budget_ms,
+            args,
+        } => {
+            run_startup_bench(bin, runs, budget_ms, args)?;
+        }
     }
 
     Ok(())
@@ -51,3 +86,65 @@ fn generate_manpage(dir: PathBuf) -> Result<()> {
 
     Ok(())
 }
+
+fn run_roundtrip(dir: PathBuf) -> Result<()> {
+    let sandbox = TestSandbox::new();
+    let results = sandbox.round_trip_dir(&dir)?;
+
+    let mut failed = 0;
+    for (path, outcome) in &results {
+        println!("{}: {outcome:?}", path.display());
+        if !outcome.is_ok() {
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        bail!("{failed} of {} plist(s) did not round-trip", results.len());
+    }
+    println!("All {} plist(s) round-tripped.", results.len());
+
+    Ok(())
+}
+
+fn run_startup_bench(bin: PathBuf, runs: usize, budget_ms: u64, args: Vec<String>) -> Result<()> {
+    let run_once = || -> Result<Duration> {
+        let started = Instant::now();
+        let status = Command::new(&bin)
+            .args(&args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .with_context(|| format!("Failed to run {}", bin.display()))?;
+        let elapsed = started.elapsed();
+        if !status.success() {
+            bail!("`{} {}` failed", bin.display(), args.join(" "));
+        }
+        Ok(elapsed)
+    };
+
+    // Warm up the page cache so only process startup is measured.
+    run_once()?;
+
+    let mut times = (0..runs.max(1))
+        .map(|_| run_once())
+        .collect::<Result<Vec<_>>>()?;
+    times.sort();
+
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    let median = times[times.len() / 2];
+    let p95 = times[(times.len() * 95 / 100).min(times.len() - 1)];
+    println!(
+        "drs {}: median {:.1}ms, p95 {:.1}ms, min {:.1}ms over {} runs",
+        args.join(" "),
+        ms(median),
+        ms(p95),
+        ms(times[0]),
+        times.len()
+    );
+
+    if median > Duration::from_millis(budget_ms) {
+        bail!("Median startup exceeds the {budget_ms}ms budget");
+    }
+    Ok(())
+}